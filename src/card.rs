@@ -0,0 +1,245 @@
+use chrono::{Local, Months, NaiveDate};
+
+use crate::{config::TemplateFields, db::DynFieldKind};
+
+const MIN_DIGITS: usize = 12;
+const MAX_DIGITS: usize = 19;
+
+#[derive(thiserror::Error, Debug)]
+pub enum CardError {
+	#[error("A card number has between 12 and 19 digits.")]
+	Length(),
+	#[error("This card number has a typo, its check digit doesn't match.")]
+	Checksum(),
+	#[error("Card expiry dates have to be written like 08/27.")]
+	Expiry(),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CardBrand {
+	Visa,
+	Mastercard,
+	Amex,
+	Discover,
+	Diners,
+	Jcb,
+	UnionPay,
+	Maestro,
+}
+
+impl std::fmt::Display for CardBrand {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match *self {
+			CardBrand::Visa => write!(f, "Visa"),
+			CardBrand::Mastercard => write!(f, "Mastercard"),
+			CardBrand::Amex => write!(f, "American Express"),
+			CardBrand::Discover => write!(f, "Discover"),
+			CardBrand::Diners => write!(f, "Diners Club"),
+			CardBrand::Jcb => write!(f, "JCB"),
+			CardBrand::UnionPay => write!(f, "UnionPay"),
+			CardBrand::Maestro => write!(f, "Maestro"),
+		}
+	}
+}
+
+// the fields of the built-in payment card template
+pub fn card_fields() -> TemplateFields {
+	vec![
+		(String::from("Number"), String::new(), DynFieldKind::CardNumber),
+		(String::from("Holder"), String::new(), DynFieldKind::TextLine),
+		(String::from("Expires"), String::new(), DynFieldKind::CardExpiry),
+		(String::from("CVV"), String::new(), DynFieldKind::Pin),
+	]
+}
+
+// numbers are often written in groups of four
+fn digits(number: &str) -> String {
+	number.chars().filter(|c| !c.is_whitespace() && *c != '-').collect()
+}
+
+pub fn validate_number(number: &str) -> Result<(), CardError> {
+	let digits = digits(number);
+	if !digits.chars().all(|c| c.is_ascii_digit())
+		|| !(MIN_DIGITS..=MAX_DIGITS).contains(&digits.len())
+	{
+		return Err(CardError::Length());
+	}
+
+	if !luhn_check(&digits) {
+		return Err(CardError::Checksum());
+	}
+	Ok(())
+}
+
+// every second digit from the right is doubled, the sum has to end in 0
+pub fn luhn_check(digits: &str) -> bool {
+	let sum: u32 = digits
+		.chars()
+		.rev()
+		.filter_map(|c| c.to_digit(10))
+		.enumerate()
+		.map(|(idx, digit)| match idx % 2 {
+			0 => digit,
+			_ if digit > 4 => digit * 2 - 9,
+			_ => digit * 2,
+		})
+		.sum();
+
+	sum % 10 == 0
+}
+
+// from the issuer identification number at the start of the card number
+pub fn detect_brand(number: &str) -> Option<CardBrand> {
+	let digits = digits(number);
+	let prefix = |len: usize| {
+		digits.get(..len).and_then(|prefix| prefix.parse::<u32>().ok())
+	};
+	let in_range = |len: usize, from: u32, to: u32| {
+		prefix(len).is_some_and(|p| (from..=to).contains(&p))
+	};
+
+	if in_range(2, 34, 34) || in_range(2, 37, 37) {
+		Some(CardBrand::Amex)
+	} else if in_range(3, 300, 305) || in_range(2, 36, 36) || in_range(2, 38, 39)
+	{
+		Some(CardBrand::Diners)
+	} else if in_range(4, 3528, 3589) {
+		Some(CardBrand::Jcb)
+	} else if in_range(1, 4, 4) {
+		Some(CardBrand::Visa)
+	} else if in_range(2, 51, 55) || in_range(4, 2221, 2720) {
+		Some(CardBrand::Mastercard)
+	} else if in_range(4, 6011, 6011)
+		|| in_range(3, 644, 649)
+		|| in_range(2, 65, 65)
+	{
+		Some(CardBrand::Discover)
+	} else if in_range(2, 62, 62) {
+		Some(CardBrand::UnionPay)
+	} else if in_range(2, 50, 50) || in_range(2, 56, 58) || in_range(1, 6, 6) {
+		Some(CardBrand::Maestro)
+	} else {
+		None
+	}
+}
+
+// only the last four digits are shown, grouped the way they are embossed
+pub fn mask_number(number: &str) -> String {
+	let digits = digits(number);
+	let groups: &[usize] = match detect_brand(&digits) {
+		Some(CardBrand::Amex) => &[4, 6, 5],
+		Some(CardBrand::Diners) if digits.len() == 14 => &[4, 6, 4],
+		_ => &[4, 4, 4, 4, 4],
+	};
+
+	let visible_from = digits.len().saturating_sub(4);
+	let mut masked = String::new();
+	let mut idx = 0;
+	for group in groups {
+		if idx >= digits.len() {
+			break;
+		}
+		if idx > 0 {
+			masked.push(' ');
+		}
+		for c in digits.chars().skip(idx).take(*group) {
+			masked.push(if idx < visible_from { '•' } else { c });
+			idx += 1;
+		}
+	}
+	masked
+}
+
+// accepts MM/YY and MM/YYYY, a card is valid until the end of that month
+pub fn parse_expiry(value: &str) -> Result<NaiveDate, CardError> {
+	let (month, year) = value.trim().split_once('/').ok_or(CardError::Expiry())?;
+	let month = month.trim().parse::<u32>().map_err(|_| CardError::Expiry())?;
+	let year = match year.trim() {
+		year if year.len() == 2 => year.parse::<i32>().map(|year| year + 2000),
+		year if year.len() == 4 => year.parse::<i32>(),
+		_ => return Err(CardError::Expiry()),
+	}
+	.map_err(|_| CardError::Expiry())?;
+
+	NaiveDate::from_ymd_opt(year, month, 1)
+		.and_then(|first| first.checked_add_months(Months::new(1)))
+		.and_then(|next_month| next_month.pred_opt())
+		.ok_or(CardError::Expiry())
+}
+
+// days until the card stops working, negative once it did
+pub fn days_until_expiry(value: &str) -> Option<i64> {
+	let last_day = parse_expiry(value).ok()?;
+	Some((last_day - Local::now().date_naive()).num_days())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn luhn_accepts_valid_numbers_only() {
+		assert!(luhn_check("4111111111111111"));
+		assert!(luhn_check("378282246310005"));
+		assert!(luhn_check("79927398713"));
+		assert!(!luhn_check("4111111111111112"));
+		assert!(!luhn_check("79927398710"));
+	}
+
+	#[test]
+	fn numbers_are_validated_with_spaces_and_dashes() {
+		assert!(validate_number("4111 1111 1111 1111").is_ok());
+		assert!(validate_number("5555-5555-5555-4444").is_ok());
+		assert!(matches!(
+			validate_number("4111 1111 1111 1112"),
+			Err(CardError::Checksum())
+		));
+		assert!(matches!(validate_number("4111 1111"), Err(CardError::Length())));
+		assert!(matches!(
+			validate_number("4111 1111 1111 111a"),
+			Err(CardError::Length())
+		));
+	}
+
+	#[test]
+	fn brands_are_detected_from_the_prefix() {
+		let brands = [
+			("4111 1111 1111 1111", CardBrand::Visa),
+			("5555 5555 5555 4444", CardBrand::Mastercard),
+			("2223 0031 2200 3222", CardBrand::Mastercard),
+			("3782 822463 10005", CardBrand::Amex),
+			("3714 496353 98431", CardBrand::Amex),
+			("6011 1111 1111 1117", CardBrand::Discover),
+			("6445 6445 6445 6445", CardBrand::Discover),
+			("3056 930902 5904", CardBrand::Diners),
+			("3622 720627 1667", CardBrand::Diners),
+			("3530 1113 3330 0000", CardBrand::Jcb),
+			("6200 0000 0000 0005", CardBrand::UnionPay),
+			("6759 6498 2643 8453", CardBrand::Maestro),
+			("5018 0000 0009", CardBrand::Maestro),
+		];
+		for (number, brand) in brands {
+			assert_eq!(detect_brand(number), Some(brand), "{number}");
+		}
+		assert_eq!(detect_brand("1234 5678 9012 3456"), None);
+		assert_eq!(detect_brand(""), None);
+	}
+
+	#[test]
+	fn only_the_last_four_digits_are_shown() {
+		assert_eq!(mask_number("4111111111111111"), "•••• •••• •••• 1111");
+		assert_eq!(mask_number("378282246310005"), "•••• •••••• •0005");
+		assert_eq!(mask_number("30569309025904"), "•••• •••••• 5904");
+	}
+
+	#[test]
+	fn expiry_dates_last_until_the_end_of_the_month() {
+		let date = |year, month, day| NaiveDate::from_ymd_opt(year, month, day);
+		assert_eq!(parse_expiry("08/27").ok(), date(2027, 8, 31));
+		assert_eq!(parse_expiry(" 2 / 2028 ").ok(), date(2028, 2, 29));
+		assert_eq!(parse_expiry("12/30").ok(), date(2030, 12, 31));
+		assert!(matches!(parse_expiry("13/27"), Err(CardError::Expiry())));
+		assert!(matches!(parse_expiry("0827"), Err(CardError::Expiry())));
+		assert!(matches!(parse_expiry("08/027"), Err(CardError::Expiry())));
+	}
+}
@@ -2,13 +2,15 @@ use anyhow::Result;
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use std::{
-	io::Write,
-	{fs, sync::Arc},
+	sync::Arc,
+	time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use crate::{
-	db::DynFieldKind,
+	card::card_fields,
+	db::{DynFieldKind, SidebarSort},
 	env::Environment,
+	storage::atomic_write,
 	ui::keyboard::{Key, KeyModifier},
 };
 
@@ -16,6 +18,11 @@ pub const CONFIG_FILE_NAME: &str = "vault_config.toml";
 pub const DB_FILE_NAME: &str = "vault_db.toml";
 const SIDEBAR_WIDTH: f64 = 140.0;
 const WINDOW_SIZE: (f64, f64) = (800.0, 350.0);
+pub const BACKUP_COUNT: usize = 5;
+pub const CLIPBOARD_TIMEOUT: usize = 30;
+// failed unlocks that are allowed before we start making people wait
+const FREE_UNLOCK_ATTEMPTS: u32 = 3;
+const MAX_UNLOCK_BACKOFF: u64 = 300;
 
 #[derive(Debug, Deserialize, Serialize)]
 struct ConfigFile {
@@ -57,13 +64,96 @@ mod arc_rwlock_serde {
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ConfigGeneral {
 	pub db_timeout: f32,
+	#[serde(default)]
+	pub max_session: Option<f32>,
 	pub db_path: String,
+	#[serde(default = "default_backup_count")]
+	pub backup_count: usize,
+	#[serde(default = "default_clipboard_timeout")]
+	pub clipboard_timeout: usize,
+	#[serde(default)]
+	pub failed_unlocks: u32,
+	#[serde(default)]
+	pub last_failed_unlock: u64,
 	pub pass_gen_letter_count: usize,
 	pub window_settings: WindowSettings,
 	pub preset_fields: PresetFields,
+	#[serde(default = "default_entry_templates")]
+	pub entry_templates: EntryTemplates,
 	pub shortcuts: Shortcuts,
 }
 
+fn default_backup_count() -> usize {
+	BACKUP_COUNT
+}
+
+fn default_clipboard_timeout() -> usize {
+	CLIPBOARD_TIMEOUT
+}
+
+fn template_field(
+	title: &str,
+	value: &str,
+	kind: DynFieldKind,
+) -> (String, String, DynFieldKind) {
+	(String::from(title), String::from(value), kind)
+}
+
+fn default_entry_templates() -> EntryTemplates {
+	vec![
+		(0, String::from("Empty"), Vec::new()),
+		(
+			1,
+			String::from("Login"),
+			vec![
+				template_field("Username", "", DynFieldKind::TextLineSecret),
+				template_field("Password", "", DynFieldKind::TextLineSecret),
+				template_field("URL", "https://", DynFieldKind::Url),
+			],
+		),
+		(
+			2,
+			String::from("Server"),
+			vec![
+				template_field("Host", "", DynFieldKind::TextLine),
+				template_field("Port", "22", DynFieldKind::Number),
+				template_field("Username", "", DynFieldKind::TextLineSecret),
+				template_field("Password", "", DynFieldKind::TextLineSecret),
+			],
+		),
+		(
+			3,
+			String::from("Wi-Fi"),
+			vec![
+				template_field("Network", "", DynFieldKind::TextLine),
+				template_field("Password", "", DynFieldKind::TextLineSecret),
+				template_field("Security", "WPA2", DynFieldKind::TextLine),
+			],
+		),
+		(
+			4,
+			String::from("Database"),
+			vec![
+				template_field("Host", "", DynFieldKind::TextLine),
+				template_field("Port", "5432", DynFieldKind::Number),
+				template_field("Database", "", DynFieldKind::TextLine),
+				template_field("Username", "", DynFieldKind::TextLineSecret),
+				template_field("Password", "", DynFieldKind::TextLineSecret),
+			],
+		),
+		(
+			5,
+			String::from("API key"),
+			vec![
+				template_field("Key", "", DynFieldKind::TextLineSecret),
+				template_field("Secret", "", DynFieldKind::TextLineSecret),
+				template_field("URL", "https://", DynFieldKind::Url),
+			],
+		),
+		(6, String::from("Payment card"), card_fields()),
+	]
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Shortcuts {
 	pub lock: (Key, KeyModifier),
@@ -73,10 +163,16 @@ pub struct Shortcuts {
 
 pub type PresetFields = Vec<(usize, String, String, DynFieldKind)>;
 
+// the title, default value and kind of each field a new entry starts with
+pub type TemplateFields = Vec<(String, String, DynFieldKind)>;
+pub type EntryTemplates = Vec<(usize, String, TemplateFields)>;
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct WindowSettings {
 	pub sidebar_width: f64,
 	pub window_size: (f64, f64),
+	#[serde(default)]
+	pub sidebar_sort: SidebarSort,
 }
 
 impl Default for WindowSettings {
@@ -84,6 +180,7 @@ impl Default for WindowSettings {
 		WindowSettings {
 			sidebar_width: SIDEBAR_WIDTH,
 			window_size: WINDOW_SIZE,
+			sidebar_sort: SidebarSort::default(),
 		}
 	}
 }
@@ -99,8 +196,13 @@ impl Default for Config {
 		Config {
 			general: Arc::new(RwLock::new(ConfigGeneral {
 				db_timeout: 900.0,
+				max_session: None,
 				pass_gen_letter_count: 22,
 				db_path: db_path.into_os_string().to_string_lossy().to_string(),
+				backup_count: BACKUP_COUNT,
+				clipboard_timeout: CLIPBOARD_TIMEOUT,
+				failed_unlocks: 0,
+				last_failed_unlock: 0,
 				window_settings: WindowSettings::default(),
 				preset_fields: vec![
 					(
@@ -128,7 +230,27 @@ impl Default for Config {
 						String::from("Notes"),
 						DynFieldKind::TextLine,
 					),
+					(
+						5,
+						String::from("Email"),
+						String::from("Email"),
+						DynFieldKind::Email,
+					),
+					(
+						6,
+						String::from("Phone"),
+						String::from("Phone"),
+						DynFieldKind::Phone,
+					),
+					(7, String::from("PIN"), String::from("PIN"), DynFieldKind::Pin),
+					(
+						8,
+						String::from("Expiry date"),
+						String::from("Expires"),
+						DynFieldKind::Date,
+					),
 				],
+				entry_templates: default_entry_templates(),
 				shortcuts: Shortcuts {
 					lock: (Key::KeyL, KeyModifier::Super),
 					search: (Key::KeyF, KeyModifier::Super),
@@ -147,13 +269,20 @@ impl From<ConfigFile> for Config {
 		Config {
 			general: Arc::new(RwLock::new(ConfigGeneral {
 				db_timeout: config_file.general.db_timeout,
+				max_session: config_file.general.max_session,
 				db_path: config_file.general.db_path,
+				backup_count: config_file.general.backup_count,
+				clipboard_timeout: config_file.general.clipboard_timeout,
+				failed_unlocks: config_file.general.failed_unlocks,
+				last_failed_unlock: config_file.general.last_failed_unlock,
 				pass_gen_letter_count: config_file.general.pass_gen_letter_count,
 				window_settings: WindowSettings {
 					sidebar_width: config_file.general.window_settings.sidebar_width,
 					window_size: config_file.general.window_settings.window_size,
+					sidebar_sort: config_file.general.window_settings.sidebar_sort,
 				},
 				preset_fields: config_file.general.preset_fields,
+				entry_templates: config_file.general.entry_templates,
 				shortcuts: config_file.general.shortcuts,
 			})),
 			config_path: Arc::new(RwLock::new(String::from(""))),
@@ -189,8 +318,10 @@ impl Config {
 					config.general.write().db_path = db_path;
 				}
 
-				match fs::write(&config_path, toml::to_string_pretty(&config).unwrap())
-				{
+				match atomic_write(
+					&config_path,
+					toml::to_string_pretty(&config).unwrap().as_bytes(),
+				) {
 					Ok(_) => config,
 					Err(_) => panic!("Can't write config file"),
 				}
@@ -200,14 +331,7 @@ impl Config {
 
 	pub fn save(&self) -> Result<()> {
 		let config = toml::to_string_pretty(self)?;
-		let mut config_file = fs::OpenOptions::new()
-			.write(true)
-			.create(true)
-			.truncate(true)
-			.open(self.config_path.read().clone())?;
-		config_file.write_all(config.as_bytes())?;
-		config_file.flush()?;
-		Ok(())
+		atomic_write(&self.config_path.read(), config.as_bytes())
 	}
 
 	pub fn get_field_presets(&self) -> PresetFields {
@@ -263,6 +387,48 @@ impl Config {
 		self.get_field_presets()
 	}
 
+	pub fn get_entry_templates(&self) -> EntryTemplates {
+		self.general.read().entry_templates.clone()
+	}
+
+	pub fn add_entry_template(&self, name: String) -> EntryTemplates {
+		{
+			let id = self
+				.general
+				.read()
+				.entry_templates
+				.iter()
+				.map(|item| item.0)
+				.max()
+				.unwrap_or(0)
+				+ 1;
+			self.general.write().entry_templates.push((id, name, Vec::new()));
+		}
+
+		self.get_entry_templates()
+	}
+
+	pub fn edit_entry_template(
+		&self,
+		id: usize,
+		name: String,
+		fields: TemplateFields,
+	) -> EntryTemplates {
+		if let Some(template) =
+			self.general.write().entry_templates.iter_mut().find(|item| item.0 == id)
+		{
+			*template = (id, name, fields);
+		}
+
+		self.get_entry_templates()
+	}
+
+	pub fn delete_entry_template(&self, id: usize) -> EntryTemplates {
+		self.general.write().entry_templates.retain(|item| item.0 != id);
+
+		self.get_entry_templates()
+	}
+
 	pub fn edit_shortcuts(&self, shortcuts: Shortcuts) {
 		self.general.write().shortcuts = shortcuts;
 		let _ = self.save();
@@ -273,8 +439,170 @@ impl Config {
 		let _ = self.save();
 	}
 
+	pub fn set_sidebar_sort(&self, sort: SidebarSort) {
+		self.general.write().window_settings.sidebar_sort = sort;
+		let _ = self.save();
+	}
+
 	pub fn set_window_size(&self, size: (f64, f64)) {
 		self.general.write().window_settings.window_size = size;
 		let _ = self.save();
 	}
+
+	pub fn set_clipboard_timeout(&self, seconds: usize) {
+		self.general.write().clipboard_timeout = seconds;
+		let _ = self.save();
+	}
+
+	pub fn set_max_session(&self, seconds: Option<f32>) {
+		self.general.write().max_session = seconds;
+		let _ = self.save();
+	}
+
+	pub fn set_backup_count(&self, count: usize) {
+		self.general.write().backup_count = count;
+		let _ = self.save();
+	}
+
+	// how long until the next unlock attempt is allowed, the wait doubles with
+	// every failure after the free ones
+	pub fn unlock_backoff(&self) -> Option<Duration> {
+		self.unlock_backoff_at(now())
+	}
+
+	fn unlock_backoff_at(&self, now: u64) -> Option<Duration> {
+		let failed_unlocks = self.general.read().failed_unlocks;
+		if failed_unlocks < FREE_UNLOCK_ATTEMPTS {
+			return None;
+		}
+
+		let backoff = 2u64
+			.saturating_pow(failed_unlocks - FREE_UNLOCK_ATTEMPTS)
+			.min(MAX_UNLOCK_BACKOFF);
+		let unlock_at = self.general.read().last_failed_unlock + backoff;
+
+		if unlock_at > now {
+			Some(Duration::from_secs(unlock_at - now))
+		} else {
+			None
+		}
+	}
+
+	pub fn add_failed_unlock(&self) {
+		{
+			let mut general = self.general.write();
+			general.failed_unlocks = general.failed_unlocks.saturating_add(1);
+			general.last_failed_unlock = now();
+		}
+		let _ = self.save();
+	}
+
+	// the failed attempts since the last unlock and when the last one happened
+	pub fn take_failed_unlocks(&self) -> Option<(u32, u64)> {
+		let failed = {
+			let mut general = self.general.write();
+			let failed = (general.failed_unlocks, general.last_failed_unlock);
+			general.failed_unlocks = 0;
+			general.last_failed_unlock = 0;
+			failed
+		};
+
+		if failed.0 == 0 {
+			return None;
+		}
+		let _ = self.save();
+		Some(failed)
+	}
+}
+
+fn now() -> u64 {
+	SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::{fs, path::PathBuf};
+
+	const FAILED_AT: u64 = 1_700_000_000;
+
+	// saves go to a temporary directory instead of the real config
+	fn temp_config(failed_unlocks: u32) -> Config {
+		let dir = std::env::temp_dir().join(format!(
+			"vault_config_{}_{}",
+			failed_unlocks,
+			SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos()
+		));
+		fs::create_dir_all(&dir).unwrap();
+
+		let config = Config::default();
+		*config.config_path.write() =
+			dir.join(CONFIG_FILE_NAME).to_string_lossy().to_string();
+		{
+			let mut general = config.general.write();
+			general.failed_unlocks = failed_unlocks;
+			general.last_failed_unlock = FAILED_AT;
+		}
+		config
+	}
+
+	fn remove(config: Config) {
+		let path = PathBuf::from(config.config_path.read().clone());
+		let _ = fs::remove_dir_all(path.parent().unwrap());
+	}
+
+	fn wait(failed_unlocks: u32) -> Option<u64> {
+		let config = temp_config(failed_unlocks);
+		let wait = config.unlock_backoff_at(FAILED_AT).map(|wait| wait.as_secs());
+		remove(config);
+		wait
+	}
+
+	#[test]
+	fn the_first_attempts_are_free() {
+		for failed_unlocks in 0..FREE_UNLOCK_ATTEMPTS {
+			assert_eq!(wait(failed_unlocks), None);
+		}
+	}
+
+	#[test]
+	fn the_wait_doubles_after_the_free_attempts() {
+		assert_eq!(wait(FREE_UNLOCK_ATTEMPTS), Some(1));
+		assert_eq!(wait(FREE_UNLOCK_ATTEMPTS + 1), Some(2));
+		assert_eq!(wait(FREE_UNLOCK_ATTEMPTS + 2), Some(4));
+		assert_eq!(wait(FREE_UNLOCK_ATTEMPTS + 5), Some(32));
+
+		// only the time left is waited
+		let config = temp_config(FREE_UNLOCK_ATTEMPTS + 2);
+		assert_eq!(
+			config.unlock_backoff_at(FAILED_AT + 3),
+			Some(Duration::from_secs(1))
+		);
+		assert_eq!(config.unlock_backoff_at(FAILED_AT + 4), None);
+		remove(config);
+	}
+
+	#[test]
+	fn the_wait_is_capped() {
+		assert_eq!(wait(FREE_UNLOCK_ATTEMPTS + 9), Some(MAX_UNLOCK_BACKOFF));
+		assert_eq!(wait(u32::MAX), Some(MAX_UNLOCK_BACKOFF));
+	}
+
+	#[test]
+	fn failed_unlocks_are_counted_and_taken_once() {
+		let config = temp_config(0);
+		assert_eq!(config.take_failed_unlocks(), None);
+
+		config.add_failed_unlock();
+		config.add_failed_unlock();
+		let (failed_unlocks, last_failed_unlock) =
+			config.take_failed_unlocks().unwrap();
+		assert_eq!(failed_unlocks, 2);
+		assert!(last_failed_unlock > FAILED_AT);
+
+		assert_eq!(config.general.read().failed_unlocks, 0);
+		assert_eq!(config.general.read().last_failed_unlock, 0);
+		assert_eq!(config.take_failed_unlocks(), None);
+		remove(config);
+	}
 }
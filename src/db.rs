@@ -1,27 +1,55 @@
 use anyhow::bail;
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{Local, NaiveDate};
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::{
+	collections::HashMap,
 	fs,
-	io::Write,
-	path::PathBuf,
+	path::{Path, PathBuf},
 	sync::Arc,
 	time::{Duration, SystemTime, UNIX_EPOCH},
 };
+use uuid::Uuid;
 use zeroize::Zeroize;
 
 use floem::reactive::use_context;
 
 use crate::{
-	config::DB_FILE_NAME,
+	card::{days_until_expiry, parse_expiry, validate_number, CardError},
+	config::{TemplateFields, BACKUP_COUNT, DB_FILE_NAME},
 	db::ChangeError::WrongPassword,
-	encryption::{decrypt_vault, encrypt_vault, password_hash, CryptError},
+	encryption::{
+		decrypt_vault, encrypt_vault, generate_key, generate_recovery_code,
+		normalize_recovery_code, password_hash, unwrap_key, wrap_key, Cipher,
+		CryptError, KdfParams, KeyFileError,
+	},
 	env::Environment,
+	migrations::{
+		check_version, migrate_contents, migrate_header, MigrationError, DB_VERSION,
+	},
+	otp::{Otp, OtpError},
 	password_gen::get_random_string,
-	ui::app_view::SidebarList,
+	storage::{atomic_write, create_backup, get_backups, restore_backup},
+	ui::{app_view::SidebarList, primitives::toast::ToastSignals},
+	BackupList,
 };
 
 const SALT_LETTER_COUNT: usize = 32;
+// the first version that authenticates the header with the contents
+const AUTHENTICATED_HEADER_VERSION: u32 = 4;
+// attachments are part of the vault which is encrypted and written as a whole
+pub const MAX_ATTACHMENT_SIZE: usize = 5 * 1024 * 1024;
+const MAX_ATTACHMENTS_SIZE: usize = 50 * 1024 * 1024;
+// date fields are stored so they sort by date
+pub const DATE_FORMAT: &str = "%Y-%m-%d";
+// a date field within this many days is about to expire
+pub const EXPIRY_WARNING_DAYS: i64 = 30;
+// how often a backup is taken at most while the vault stays unlocked
+const BACKUP_INTERVAL: u64 = 60 * 60;
+pub const TAG_PREFIX: &str = "tag:";
+pub const RECENT_COUNT: usize = 5;
 
 type SecureField = (u64, String);
 
@@ -29,28 +57,100 @@ type SecureField = (u64, String);
 pub enum ChangeError {
 	#[error("Wrong password provided")]
 	WrongPassword(),
+	#[error("This vault has no {0} key slot")]
+	NoKeySlot(KeySlotKind),
 	#[error("Crypt error")]
 	CryptError(#[from] CryptError),
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(thiserror::Error, Debug)]
+pub enum AttachmentError {
+	#[error("The file could not be read: {0}")]
+	Unreadable(String),
+	#[error("Attachments can't be larger than 5 MB.")]
+	TooLarge(),
+	#[error("The vault can't hold more than 50 MB of attachments.")]
+	VaultFull(),
+	#[error("The attachment could not be saved: {0}")]
+	Unwritable(String),
+	#[error("This attachment is missing from the vault.")]
+	Missing(),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum FieldError {
+	#[error("This is not a valid email address.")]
+	Email(),
+	#[error(
+		"Phone numbers can only contain digits, spaces, dashes, dots, brackets and a leading +."
+	)]
+	Phone(),
+	#[error("A PIN can only contain digits.")]
+	Pin(),
+	#[error("Dates have to be written like 2024-12-31.")]
+	Date(),
+	#[error("This is not a number.")]
+	Number(),
+	#[error(transparent)]
+	Otp(#[from] OtpError),
+	#[error(transparent)]
+	Card(#[from] CardError),
+}
+
+// kept around as text so every unlock attempt can report it again
+#[derive(thiserror::Error, Debug, Clone)]
+pub enum LoadError {
+	#[error("The vault file could not be read: {0}")]
+	Unreadable(String),
+	#[error("The vault file is damaged and could not be parsed: {0}")]
+	Malformed(String),
+	#[error(transparent)]
+	Version(#[from] MigrationError),
+}
+
+impl LoadError {
+	// a newer file usually doesn't parse either so its version is checked first,
+	// toml errors quote the broken line, the first line is enough for a toast
+	fn from_parse(err: anyhow::Error) -> Self {
+		match err.downcast::<MigrationError>() {
+			Ok(err) => LoadError::Version(err),
+			Err(err) => LoadError::Malformed(
+				err.to_string().lines().next().unwrap_or_default().to_string(),
+			),
+		}
+	}
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 pub struct DynField {
-	id: usize,
+	id: Uuid,
 	kind: DynFieldKind,
 	title: String,
 	visible: bool,
 	value: Vec<SecureField>,
+	// base64 contents of attachments, files[n] is the file named in value[n]
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	files: Vec<String>,
+	// the counters used by counter based one-time passwords, keyed by the
+	// timestamp of the value the codes were generated from
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	counters: Vec<(u64, u64)>,
 }
 
 impl DynField {
 	pub fn zeroize(&mut self) {
-		self.id.zeroize();
+		self.id = Uuid::nil();
 		self.title.zeroize();
 		self.visible.zeroize();
 		for v in &mut self.value {
 			v.0.zeroize();
 			v.1.zeroize();
 		}
+		for file in &mut self.files {
+			file.zeroize();
+		}
+		self.files.clear();
+		self.counters.clear();
 	}
 }
 
@@ -64,6 +164,15 @@ pub enum DynFieldKind {
 	MultiLine,
 	MultiLineSecret,
 	Url,
+	Totp,
+	Attachment,
+	Email,
+	Phone,
+	Pin,
+	Date,
+	Number,
+	CardNumber,
+	CardExpiry,
 }
 
 impl std::fmt::Display for DynFieldKind {
@@ -74,6 +183,15 @@ impl std::fmt::Display for DynFieldKind {
 			DynFieldKind::MultiLineSecret => write!(f, "Multi Line Secret"),
 			DynFieldKind::MultiLine => write!(f, "Multi Line"),
 			DynFieldKind::Url => write!(f, "Url"),
+			DynFieldKind::Totp => write!(f, "One-time password"),
+			DynFieldKind::Attachment => write!(f, "Attachment"),
+			DynFieldKind::Email => write!(f, "Email"),
+			DynFieldKind::Phone => write!(f, "Phone"),
+			DynFieldKind::Pin => write!(f, "PIN"),
+			DynFieldKind::Date => write!(f, "Date"),
+			DynFieldKind::Number => write!(f, "Number"),
+			DynFieldKind::CardNumber => write!(f, "Card number"),
+			DynFieldKind::CardExpiry => write!(f, "Card expiry"),
 		}
 	}
 }
@@ -86,36 +204,257 @@ impl DynFieldKind {
 			DynFieldKind::MultiLineSecret,
 			DynFieldKind::MultiLine,
 			DynFieldKind::Url,
+			DynFieldKind::Totp,
+			DynFieldKind::Attachment,
+			DynFieldKind::Email,
+			DynFieldKind::Phone,
+			DynFieldKind::Pin,
+			DynFieldKind::Date,
+			DynFieldKind::Number,
+			DynFieldKind::CardNumber,
+			DynFieldKind::CardExpiry,
 		]
 	}
+
+	// checked before a value is saved into a field of this kind, clearing a
+	// field is always fine
+	pub fn validate(&self, value: &str) -> Result<(), FieldError> {
+		let value = value.trim();
+		if value.is_empty() {
+			return Ok(());
+		}
+
+		match self {
+			DynFieldKind::Email => {
+				let (local, domain) =
+					value.split_once('@').ok_or(FieldError::Email())?;
+				if local.is_empty()
+					|| domain.contains('@')
+					|| !domain.contains('.')
+					|| domain.starts_with('.')
+					|| domain.ends_with('.')
+					|| value.contains(char::is_whitespace)
+				{
+					return Err(FieldError::Email());
+				}
+			},
+			DynFieldKind::Phone => {
+				let digits = value.chars().filter(char::is_ascii_digit).count();
+				let is_valid = value.char_indices().all(|(idx, c)| {
+					c.is_ascii_digit() || " -.()/".contains(c) || (c == '+' && idx == 0)
+				});
+				if digits < 3 || !is_valid {
+					return Err(FieldError::Phone());
+				}
+			},
+			DynFieldKind::Pin => {
+				if !value.chars().all(|c| c.is_ascii_digit()) {
+					return Err(FieldError::Pin());
+				}
+			},
+			DynFieldKind::Date => {
+				NaiveDate::parse_from_str(value, DATE_FORMAT)
+					.map_err(|_| FieldError::Date())?;
+			},
+			DynFieldKind::Number => {
+				if !value.replace(' ', "").parse::<f64>().is_ok_and(f64::is_finite) {
+					return Err(FieldError::Number());
+				}
+			},
+			DynFieldKind::Totp => {
+				Otp::parse(value)?;
+			},
+			DynFieldKind::CardNumber => validate_number(value)?,
+			DynFieldKind::CardExpiry => {
+				parse_expiry(value)?;
+			},
+			DynFieldKind::TextLineSecret
+			| DynFieldKind::TextLine
+			| DynFieldKind::MultiLine
+			| DynFieldKind::MultiLineSecret
+			| DynFieldKind::Url
+			| DynFieldKind::Attachment => {},
+		}
+
+		Ok(())
+	}
+}
+
+// days until the date of a date field, negative once it passed
+pub fn days_until(date: &str) -> Option<i64> {
+	let date = NaiveDate::parse_from_str(date.trim(), DATE_FORMAT).ok()?;
+	Some((date - Local::now().date_naive()).num_days())
+}
+
+impl DynField {
+	// the getters only need the names of attachments, their contents are read
+	// in place so they aren't copied around with every lookup
+	fn without_files(&self) -> DynField {
+		DynField {
+			id: self.id,
+			kind: self.kind.clone(),
+			title: self.title.clone(),
+			visible: self.visible,
+			value: self.value.clone(),
+			files: Vec::new(),
+			counters: self.counters.clone(),
+		}
+	}
 }
 
 impl Default for DynField {
 	fn default() -> Self {
 		Self {
-			id: 0,
+			id: Uuid::nil(),
 			kind: DynFieldKind::TextLineSecret,
 			title: String::from("Notes"),
 			visible: true,
 			value: vec![(0, String::from("My notes"))],
+			files: Vec::new(),
+			counters: Vec::new(),
 		}
 	}
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 pub struct DbEntry {
-	pub id: usize,
+	pub id: Uuid,
 	pub title: String,
 	pub fields: Vec<DynField>,
+	// entries without a group live at the top of the sidebar
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub group: Option<Uuid>,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub tags: Vec<String>,
+	#[serde(default)]
+	pub favorite: bool,
+	// when a value was last copied or revealed
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub last_used: Option<u64>,
+	// when the entry was added, restoring, importing or merging it keeps this
+	#[serde(default)]
+	pub created: u64,
 }
 
 impl DbEntry {
 	pub fn zeroize(&mut self) {
-		self.id.zeroize();
+		self.id = Uuid::nil();
 		self.title.zeroize();
 		for field in &mut self.fields {
 			field.zeroize();
 		}
+		self.group = None;
+		for tag in &mut self.tags {
+			tag.zeroize();
+		}
+		self.tags.clear();
+		self.favorite.zeroize();
+		self.last_used = None;
+	}
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct DbGroup {
+	pub id: Uuid,
+	pub title: String,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub parent: Option<Uuid>,
+}
+
+impl DbGroup {
+	pub fn zeroize(&mut self) {
+		self.id = Uuid::nil();
+		self.title.zeroize();
+		self.parent = None;
+	}
+}
+
+// a row of the sidebar tree
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SidebarItem {
+	// id, title, depth and if it is collapsed
+	Group(Uuid, String, usize, bool),
+	// id, title and depth
+	Entry(Uuid, String, usize),
+	// a heading above the pinned and recently used entries
+	Section(String),
+}
+
+// how entries are ordered inside the sidebar
+#[derive(
+	Debug, Deserialize, Serialize, Copy, Clone, PartialEq, Eq, Default,
+)]
+pub enum SidebarSort {
+	#[default]
+	Created,
+	Title,
+	Modified,
+	LastUsed,
+}
+
+impl std::fmt::Display for SidebarSort {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match *self {
+			SidebarSort::Created => write!(f, "Created"),
+			SidebarSort::Title => write!(f, "Title"),
+			SidebarSort::Modified => write!(f, "Modified"),
+			SidebarSort::LastUsed => write!(f, "Last used"),
+		}
+	}
+}
+
+impl SidebarSort {
+	pub fn all_values() -> Vec<SidebarSort> {
+		vec![
+			SidebarSort::Created,
+			SidebarSort::Title,
+			SidebarSort::Modified,
+			SidebarSort::LastUsed,
+		]
+	}
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct DbTrashEntry {
+	pub deleted_at: u64,
+	pub entry: DbEntry,
+}
+
+impl DbTrashEntry {
+	pub fn zeroize(&mut self) {
+		self.deleted_at.zeroize();
+		self.entry.zeroize();
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct MergeConflict {
+	pub entry: String,
+	pub field: String,
+}
+
+impl std::fmt::Display for MergeConflict {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "{}: {}", self.entry, self.field)
+	}
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum MergeChange {
+	Added,
+	Changed,
+	Conflict,
+	Deleted,
+}
+
+impl std::fmt::Display for MergeChange {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match *self {
+			MergeChange::Added => write!(f, "New"),
+			MergeChange::Changed => write!(f, "Changed"),
+			MergeChange::Conflict => write!(f, "Conflict"),
+			MergeChange::Deleted => write!(f, "Deleted"),
+		}
 	}
 }
 
@@ -127,7 +466,7 @@ pub struct NewDbEntry {
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct DbEntryNonSecure {
-	pub id: usize,
+	pub id: Uuid,
 	pub title: String,
 }
 
@@ -135,7 +474,7 @@ pub struct DbEntryNonSecure {
 pub enum DbFields {
 	Id,
 	Title,
-	Fields(usize),
+	Fields(Uuid),
 }
 
 impl std::fmt::Display for DbFields {
@@ -143,7 +482,7 @@ impl std::fmt::Display for DbFields {
 		match *self {
 			DbFields::Id => write!(f, "Id"),
 			DbFields::Title => write!(f, "Title"),
-			DbFields::Fields(idx) => write!(f, "Fields-{}", idx),
+			DbFields::Fields(id) => write!(f, "Fields-{}", id),
 		}
 	}
 }
@@ -153,31 +492,137 @@ pub struct DbFile {
 	pub db: DbFileDb,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct DbFileDb {
+	// files written before versioning was introduced have no version
+	#[serde(default)]
+	pub version: u32,
 	pub encrypted: bool,
 	pub salt: String,
 	pub salt_letter_count: usize,
 	cypher: String,
+	pub cipher: Cipher,
+	pub kdf: KdfParams,
+	// the key file itself is never stored, only that one is needed
+	#[serde(default)]
+	pub key_file: bool,
+	// vaults from before key slots have none and use the password hash as key
+	#[serde(default)]
+	pub key_slots: Vec<KeySlot>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum KeySlotKind {
+	Password,
+	RecoveryCode,
+}
+
+impl std::fmt::Display for KeySlotKind {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match *self {
+			KeySlotKind::Password => write!(f, "password"),
+			KeySlotKind::RecoveryCode => write!(f, "recovery code"),
+		}
+	}
+}
+
+// every slot holds the vault key encrypted with a key derived from its secret
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct KeySlot {
+	pub kind: KeySlotKind,
+	pub salt: String,
+	pub kdf: KdfParams,
+	pub cipher: Cipher,
+	key: String,
+}
+
+enum HeaderChanges {
+	None,
+	Theirs,
+	Conflict,
+}
+
+// the parts of the header that decide how the vault is unlocked
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+struct UnlockHeader {
+	salt: String,
+	cipher: Cipher,
+	kdf: KdfParams,
+	#[serde(default)]
+	key_file: bool,
+	#[serde(default)]
+	key_slots: Vec<KeySlot>,
+}
+
+impl From<&DbFileDb> for UnlockHeader {
+	fn from(config_db: &DbFileDb) -> Self {
+		UnlockHeader {
+			salt: config_db.salt.clone(),
+			cipher: config_db.cipher,
+			kdf: config_db.kdf,
+			key_file: config_db.key_file,
+			key_slots: config_db.key_slots.clone(),
+		}
+	}
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 struct DbFileCypher {
 	pub contents: Vec<DbEntry>,
+	#[serde(default)]
+	pub trash: Vec<DbTrashEntry>,
+	#[serde(default)]
+	pub groups: Vec<DbGroup>,
+}
+
+impl DbFileCypher {
+	pub fn zeroize(&mut self) {
+		for entry in &mut self.contents {
+			entry.zeroize();
+		}
+		self.contents.clear();
+		for trashed in &mut self.trash {
+			trashed.zeroize();
+		}
+		self.trash.clear();
+		for group in &mut self.groups {
+			group.zeroize();
+		}
+		self.groups.clear();
+	}
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Db {
 	#[serde(skip_serializing, with = "arc_rwlock_serde")]
 	pub contents: Arc<RwLock<Vec<DbEntry>>>,
+	#[serde(skip)]
+	pub trash: Arc<RwLock<Vec<DbTrashEntry>>>,
+	#[serde(skip)]
+	pub groups: Arc<RwLock<Vec<DbGroup>>>,
 	#[serde(rename(serialize = "db"), with = "arc_rwlock_serde")]
 	pub config_db: Arc<RwLock<DbFileDb>>,
 	#[serde(skip)]
 	pub vault_unlocked: Arc<RwLock<bool>>,
+	// the random key the vault is encrypted with, unwrapped from a key slot
 	#[serde(skip)]
 	hash: Arc<RwLock<[u8; 32]>>,
 	#[serde(skip)]
 	db_path: Arc<RwLock<String>>,
+	#[serde(skip)]
+	backup_count: Arc<RwLock<usize>>,
+	// when this session last backed up the file, reset on every unlock
+	#[serde(skip)]
+	last_backup: Arc<RwLock<Option<u64>>>,
+	#[serde(skip)]
+	file_hash: Arc<RwLock<Option<[u8; 32]>>>,
+	// the header the cypher was encrypted with, empty for older files
+	#[serde(skip)]
+	cypher_header: Arc<RwLock<String>>,
+	#[serde(skip)]
+	load_error: Arc<RwLock<Option<LoadError>>>,
+	#[serde(skip)]
+	key_file: Arc<RwLock<Option<[u8; 32]>>>,
 }
 
 mod arc_rwlock_serde {
@@ -211,18 +656,20 @@ impl Default for Db {
 
 		Db {
 			contents: Arc::new(RwLock::new(vec![DbEntry {
-				id: 1,
+				id: Uuid::new_v4(),
 				title: String::from("My Bank Deets"),
 				fields: vec![
 					DynField {
-						id: 0,
+						id: Uuid::new_v4(),
 						kind: DynFieldKind::TextLine,
 						title: String::from("Title"),
 						visible: true,
 						value: vec![(1702851212, String::from("Bank"))],
+						files: Vec::new(),
+						counters: Vec::new(),
 					},
 					DynField {
-						id: 1,
+						id: Uuid::new_v4(),
 						kind: DynFieldKind::Url,
 						title: String::from("Url"),
 						visible: true,
@@ -230,34 +677,54 @@ impl Default for Db {
 							1702851212,
 							String::from("https://bankofaustralia.com.au"),
 						)],
+						files: Vec::new(),
+						counters: Vec::new(),
 					},
 					DynField {
-						id: 2,
+						id: Uuid::new_v4(),
 						kind: DynFieldKind::TextLineSecret,
 						title: String::from("Username"),
 						visible: true,
 						value: vec![(1702851212, String::from("Dom"))],
+						files: Vec::new(),
+						counters: Vec::new(),
 					},
 					DynField {
-						id: 3,
+						id: Uuid::new_v4(),
 						kind: DynFieldKind::TextLineSecret,
 						title: String::from("Password"),
 						visible: true,
 						value: vec![(1702851212, String::from("password"))],
+						files: Vec::new(),
+						counters: Vec::new(),
 					},
 					DynField {
-						id: 4,
+						id: Uuid::new_v4(),
 						kind: DynFieldKind::TextLineSecret,
 						title: String::from("Notes"),
 						visible: true,
 						value: vec![(1702851212, String::from("These are my bank deets"))],
+						files: Vec::new(),
+						counters: Vec::new(),
 					},
 				],
+				group: None,
+				tags: Vec::new(),
+				favorite: false,
+				last_used: None,
+				created: 1702851212,
 			}])),
+			trash: Arc::new(RwLock::new(Vec::new())),
+			groups: Arc::new(RwLock::new(Vec::new())),
 			config_db: Arc::new(RwLock::new(DbFileDb {
+				version: DB_VERSION,
 				encrypted: true,
 				salt_letter_count: SALT_LETTER_COUNT,
 				salt: get_random_string(SALT_LETTER_COUNT),
+				cipher: Cipher::Aes256GcmSiv,
+				kdf: KdfParams::default(),
+				key_file: false,
+				key_slots: Vec::new(),
 				cypher: "".to_string(),
 			})),
 			vault_unlocked: Arc::new(Default::default()),
@@ -265,81 +732,583 @@ impl Default for Db {
 			db_path: Arc::new(RwLock::new(
 				db_path.into_os_string().to_string_lossy().to_string(),
 			)),
+			backup_count: Arc::new(RwLock::new(BACKUP_COUNT)),
+			last_backup: Arc::new(RwLock::new(None)),
+			file_hash: Arc::new(RwLock::new(None)),
+			cypher_header: Arc::new(RwLock::new(String::from(""))),
+			load_error: Arc::new(RwLock::new(None)),
+			key_file: Arc::new(RwLock::new(None)),
 		}
 	}
 }
 
-fn to_tuple(item: &DbEntry, idx: usize) -> (usize, String, usize) {
+fn to_tuple(item: &DbEntry, idx: usize) -> (Uuid, String, usize) {
 	(item.id, item.title.clone(), idx)
 }
 
+// the rows of a group and everything nested in it, when searching only groups
+// with matching entries are shown and all of them are open
+fn tree_rows(
+	parent: Option<Uuid>,
+	depth: usize,
+	groups: &[DbGroup],
+	entries: &[(Uuid, String, Option<Uuid>)],
+	collapsed: &[Uuid],
+	is_search: bool,
+) -> Vec<SidebarItem> {
+	let mut rows = Vec::new();
+
+	let mut children =
+		groups.iter().filter(|group| group.parent == parent).collect::<Vec<_>>();
+	children.sort_by_key(|group| group.title.to_lowercase());
+
+	for group in children {
+		let group_rows = tree_rows(
+			Some(group.id),
+			depth + 1,
+			groups,
+			entries,
+			collapsed,
+			is_search,
+		);
+		let has_entries =
+			group_rows.iter().any(|row| matches!(row, SidebarItem::Entry(..)));
+		if is_search && !has_entries {
+			continue;
+		}
+
+		let is_collapsed = !is_search && collapsed.contains(&group.id);
+		rows.push(SidebarItem::Group(
+			group.id,
+			group.title.clone(),
+			depth,
+			is_collapsed,
+		));
+		if !is_collapsed {
+			rows.extend(group_rows);
+		}
+	}
+
+	rows.extend(
+		entries
+			.iter()
+			.filter(|(_, _, group)| *group == parent)
+			.map(|(id, title, _)| SidebarItem::Entry(*id, title.clone(), depth)),
+	);
+
+	rows
+}
+
+fn file_hash(content: &[u8]) -> [u8; 32] {
+	Sha256::digest(content).into()
+}
+
+// everything in the header but the cypher itself
+fn header_aad(mut header: toml::Value) -> String {
+	if let toml::Value::Table(table) = &mut header {
+		table.remove("cypher");
+	}
+	header.to_string()
+}
+
+// the newest change to any of the fields of an entry
+fn last_changed(entry: &DbEntry) -> u64 {
+	entry
+		.fields
+		.iter()
+		.filter_map(|field| field.value.last())
+		.map(|value| value.0)
+		.max()
+		.unwrap_or(0)
+}
+
+// take the side that changed since the base, returns true when both did
+fn merge_value<T: PartialEq + Clone>(
+	base: Option<&T>,
+	ours: &T,
+	theirs: &T,
+) -> (T, bool) {
+	if ours == theirs || base == Some(theirs) {
+		(ours.clone(), false)
+	} else if base == Some(ours) {
+		(theirs.clone(), false)
+	} else {
+		(ours.clone(), true)
+	}
+}
+
+// histories only ever grow so the values both sides share are the base and
+// everything after is interleaved by timestamp, returns true when both sides
+// added values that ended up different
+fn merge_history<T: PartialEq + Clone>(
+	ours: &[(u64, T)],
+	theirs: &[(u64, T)],
+) -> (Vec<(u64, T)>, bool) {
+	let shared = ours.iter().zip(theirs).take_while(|(a, b)| a == b).count();
+
+	let mut history = ours.to_vec();
+	for value in &theirs[shared..] {
+		if !history.contains(value) {
+			history.push(value.clone());
+		}
+	}
+	history.sort_by_key(|value| value.0);
+
+	let is_conflict = shared < ours.len()
+		&& shared < theirs.len()
+		&& ours.last() != theirs.last();
+
+	(history, is_conflict)
+}
+
+fn merge_field(
+	base: Option<&DynField>,
+	ours: &mut DynField,
+	theirs: &DynField,
+) -> bool {
+	let (title, title_conflict) =
+		merge_value(base.map(|field| &field.title), &ours.title, &theirs.title);
+	let (kind, kind_conflict) =
+		merge_value(base.map(|field| &field.kind), &ours.kind, &theirs.kind);
+	// a different visibility is not worth bothering anyone with
+	let (visible, _) = merge_value(
+		base.map(|field| &field.visible),
+		&ours.visible,
+		&theirs.visible,
+	);
+	let (value, files, value_conflict) =
+		if ours.files.is_empty() && theirs.files.is_empty() {
+			let (value, value_conflict) = merge_history(&ours.value, &theirs.value);
+			(value, Vec::new(), value_conflict)
+		} else {
+			// attachments are merged together with their file names so every file
+			// stays at the index of its name
+			let with_files = |field: &DynField| {
+				field
+					.value
+					.iter()
+					.enumerate()
+					.map(|(index, (timestamp, name))| {
+						let file = field.files.get(index).cloned().unwrap_or_default();
+						(*timestamp, (name.clone(), file))
+					})
+					.collect::<Vec<(u64, (String, String))>>()
+			};
+			let (history, value_conflict) =
+				merge_history(&with_files(ours), &with_files(theirs));
+			let (value, files) = history
+				.into_iter()
+				.map(|(timestamp, (name, file))| ((timestamp, name), file))
+				.unzip();
+			(value, files, value_conflict)
+		};
+	// every generated code used up a counter no matter which side generated it
+	let (counters, _) = merge_history(&ours.counters, &theirs.counters);
+
+	ours.title = title;
+	ours.kind = kind;
+	ours.visible = visible;
+	ours.value = value;
+	ours.files = files;
+	ours.counters = counters;
+
+	title_conflict || kind_conflict || value_conflict
+}
+
+// tags either side removed since the base stay removed, added ones are kept
+fn merge_tags(
+	base: Option<&[String]>,
+	ours: &[String],
+	theirs: &[String],
+) -> Vec<String> {
+	let was_there = |tag: &String| base.is_some_and(|base| base.contains(tag));
+
+	let mut tags = ours
+		.iter()
+		.filter(|tag| theirs.contains(tag) || !was_there(tag))
+		.cloned()
+		.collect::<Vec<String>>();
+	for tag in theirs {
+		if !tags.contains(tag) && !ours.contains(tag) && !was_there(tag) {
+			tags.push(tag.clone());
+		}
+	}
+
+	tags
+}
+
+// tags are single words so they can be searched for with tag:name
+pub fn normalize_tag(tag: &str) -> String {
+	tag.split_whitespace().collect::<Vec<&str>>().join("-").to_lowercase()
+}
+
+fn merge_entry(
+	base: Option<&DbEntry>,
+	ours: &mut DbEntry,
+	theirs: &DbEntry,
+) -> Vec<MergeConflict> {
+	let mut conflicts = Vec::new();
+
+	let (title, is_conflict) =
+		merge_value(base.map(|entry| &entry.title), &ours.title, &theirs.title);
+	if is_conflict {
+		conflicts.push(MergeConflict {
+			entry: title.clone(),
+			field: String::from("Title"),
+		});
+	}
+	ours.title = title;
+	// a different group is not worth bothering anyone with
+	let (group, _) =
+		merge_value(base.map(|entry| &entry.group), &ours.group, &theirs.group);
+	ours.group = group;
+	ours.tags = merge_tags(
+		base.map(|entry| entry.tags.as_slice()),
+		&ours.tags,
+		&theirs.tags,
+	);
+	let (favorite, _) = merge_value(
+		base.map(|entry| &entry.favorite),
+		&ours.favorite,
+		&theirs.favorite,
+	);
+	ours.favorite = favorite;
+	ours.last_used = ours.last_used.max(theirs.last_used);
+
+	for their_field in &theirs.fields {
+		let base_field = base.and_then(|entry| {
+			entry.fields.iter().find(|field| field.id == their_field.id)
+		});
+
+		match ours.fields.iter_mut().find(|field| field.id == their_field.id) {
+			Some(our_field) => {
+				if merge_field(base_field, our_field, their_field) {
+					conflicts.push(MergeConflict {
+						entry: ours.title.clone(),
+						field: our_field.title.clone(),
+					});
+				}
+			},
+			None => ours.fields.push(their_field.clone()),
+		}
+	}
+
+	conflicts
+}
+
 impl From<DbFile> for Db {
 	fn from(db_file: DbFile) -> Self {
 		Db {
 			contents: Arc::new(RwLock::new(Vec::<DbEntry>::new())),
+			trash: Arc::new(RwLock::new(Vec::<DbTrashEntry>::new())),
+			groups: Arc::new(RwLock::new(Vec::<DbGroup>::new())),
 			config_db: Arc::new(RwLock::new(DbFileDb {
+				version: db_file.db.version,
 				encrypted: db_file.db.encrypted,
 				salt_letter_count: db_file.db.salt_letter_count,
 				salt: db_file.db.salt,
+				cipher: db_file.db.cipher,
+				kdf: db_file.db.kdf,
+				key_file: db_file.db.key_file,
+				key_slots: db_file.db.key_slots,
 				cypher: db_file.db.cypher,
 			})),
 			vault_unlocked: Arc::new(RwLock::new(false)),
 			hash: Arc::new(RwLock::new(*b"00000000000000000000000000000000")),
 			db_path: Arc::new(RwLock::new(String::from(""))),
+			backup_count: Arc::new(RwLock::new(BACKUP_COUNT)),
+			last_backup: Arc::new(RwLock::new(None)),
+			file_hash: Arc::new(RwLock::new(None)),
+			cypher_header: Arc::new(RwLock::new(String::from(""))),
+			load_error: Arc::new(RwLock::new(None)),
+			key_file: Arc::new(RwLock::new(None)),
 		}
 	}
 }
 
 impl Db {
+	fn parse(content: &str) -> anyhow::Result<Self> {
+		let mut file_contents: toml::Value = toml::from_str(content)?;
+		let version = file_contents
+			.get("db")
+			.and_then(|db| db.get("version"))
+			.and_then(|version| version.as_integer())
+			.unwrap_or(0) as u32;
+		// as it was written, before any migration touches it
+		let cypher_header = match file_contents.get("db") {
+			Some(header) if version >= AUTHENTICATED_HEADER_VERSION => {
+				header_aad(header.clone())
+			},
+			_ => String::from(""),
+		};
+		migrate_header(version, &mut file_contents)?;
+		let file_contents = DbFile::deserialize(file_contents)?;
+		let db: Db = file_contents.into();
+		*db.file_hash.write() = Some(file_hash(content.as_bytes()));
+		*db.cypher_header.write() = cypher_header;
+		Ok(db)
+	}
+
 	pub fn load(db_path: String) -> Self {
 		let path = PathBuf::from(db_path.as_str());
 
-		match fs::read_to_string(path.clone()) {
-			Ok(content) => {
-				let file_contents: DbFile = toml::from_str(&content).unwrap();
-				let db: Db = file_contents.into();
-				db.db_path.write().clone_from(&db_path);
-				db
-			},
-			Err(_) => Db {
-				db_path: Arc::new(RwLock::new(db_path.clone())),
-				..Default::default()
+		let load_error = match fs::read_to_string(path.clone()) {
+			Ok(content) => match Db::parse(&content) {
+				Ok(db) => {
+					db.db_path.write().clone_from(&db_path);
+					return db;
+				},
+				Err(err) => Some(LoadError::from_parse(err)),
 			},
+			Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+			Err(err) => Some(LoadError::Unreadable(err.to_string())),
+		};
+
+		Db {
+			db_path: Arc::new(RwLock::new(db_path.clone())),
+			load_error: Arc::new(RwLock::new(load_error)),
+			..Default::default()
+		}
+	}
+
+	pub fn load_error(&self) -> Option<LoadError> {
+		self.load_error.read().clone()
+	}
+
+	// the right key failed to decrypt the contents so the file itself is wrong
+	fn tampered(err: anyhow::Error) -> anyhow::Error {
+		match err.downcast_ref::<CryptError>() {
+			Some(CryptError::Decryption(_)) => CryptError::HeaderTampered().into(),
+			_ => err,
 		}
 	}
 
-	pub fn decrypt_database(&self, password: String) -> anyhow::Result<()> {
-		let mut hash = self.hash.write();
-		*hash = password_hash(password, self.config_db.read().salt.clone())?;
-		drop(hash);
+	// runs the same checks as an unlock against the file on disk
+	pub fn verify_integrity(&self) -> anyhow::Result<()> {
+		let db_path = self.db_path.read().clone();
+		let content = fs::read_to_string(&db_path)
+			.map_err(|err| LoadError::Unreadable(err.to_string()))?;
+		let db = Db::parse(&content).map_err(LoadError::from_parse)?;
+
+		let mut contents =
+			db.decrypt_cypher(*self.hash.read()).map_err(Db::tampered)?;
+		contents.zeroize();
+		Ok(())
+	}
+
+	// the password field also takes the recovery code, returns which one it was
+	pub fn decrypt_database(
+		&self,
+		password: String,
+	) -> anyhow::Result<KeySlotKind> {
+		if let Some(err) = self.load_error() {
+			bail!(err)
+		}
+		let version = self.config_db.read().version;
+		check_version(version)?;
+		let needs_key_file =
+			self.config_db.read().key_file && self.key_file.read().is_none();
+
+		if self.config_db.read().key_slots.is_empty() {
+			if needs_key_file {
+				bail!(KeyFileError::Missing())
+			}
+			let kdf = self.config_db.read().kdf;
+			*self.hash.write() = password_hash(
+				password.clone(),
+				self.config_db.read().salt.clone(),
+				&kdf,
+				self.key_file.read().as_ref(),
+			)?;
+			let contents = self.decrypt_cypher(*self.hash.read())?;
+
+			// the old password and the salt in the header would keep deriving this
+			// key no matter how often the password changes, so the vault moves to a
+			// random key and a new salt and is encrypted with it on the next save
+			*self.hash.write() = generate_key();
+			self.change_salt();
+			let key_file = *self.key_file.read();
+			self.wrap_slot(
+				KeySlotKind::Password,
+				password,
+				kdf,
+				key_file.as_ref(),
+			)?;
+			self.open(contents);
+			// until then the file still opens with the old key, an error here only
+			// means we migrate again on the next unlock
+			let _ = self.save();
+			return Ok(KeySlotKind::Password);
+		}
 
-		let contents = if self.config_db.read().encrypted {
-			let decrypted =
-				decrypt_vault(self.config_db.read().cypher.clone(), *self.hash.read())?;
-			toml::from_str::<DbFileCypher>(decrypted.as_str())?
+		let recovery_code = normalize_recovery_code(&password);
+		let unlocked = if needs_key_file {
+			Err(KeyFileError::Missing().into())
 		} else {
-			toml::from_str::<DbFileCypher>(&self.config_db.read().cypher.clone())?
+			self.unlock_slot(KeySlotKind::Password, password)
+		};
+		let (kind, key) = match (unlocked, recovery_code) {
+			(Ok(key), _) => (KeySlotKind::Password, key),
+			(Err(err), Some(code)) => {
+				match self.unlock_slot(KeySlotKind::RecoveryCode, code) {
+					Ok(key) => (KeySlotKind::RecoveryCode, key),
+					Err(_) => return Err(err),
+				}
+			},
+			(Err(err), None) => return Err(err),
 		};
 
+		*self.hash.write() = key;
+		// the key unwrapped so the password was right, if the contents still
+		// don't decrypt the header they are bound to was changed
+		let contents = self.decrypt_cypher(key).map_err(Db::tampered)?;
+		self.open(contents);
+		Ok(kind)
+	}
+
+	fn open(&self, contents: DbFileCypher) {
+		// the next save writes the file in the current format
+		self.config_db.write().version = DB_VERSION;
+		*self.last_backup.write() = None;
 		*self.vault_unlocked.write() = true;
 		*self.contents.write() = contents.contents;
+		*self.trash.write() = contents.trash;
+		*self.groups.write() = contents.groups;
+	}
+
+	pub fn has_key_slot(&self, kind: KeySlotKind) -> bool {
+		self.config_db.read().key_slots.iter().any(|slot| slot.kind == kind)
+	}
+
+	// derive the key of a slot from its secret and unwrap the vault key with it
+	fn unlock_slot(
+		&self,
+		kind: KeySlotKind,
+		secret: String,
+	) -> anyhow::Result<[u8; 32]> {
+		let slot = self
+			.config_db
+			.read()
+			.key_slots
+			.iter()
+			.find(|slot| slot.kind == kind)
+			.cloned();
+		let Some(slot) = slot else {
+			bail!(ChangeError::NoKeySlot(kind))
+		};
+
+		// the recovery code has to work without the key file
+		let key_file = match kind {
+			KeySlotKind::Password => *self.key_file.read(),
+			KeySlotKind::RecoveryCode => None,
+		};
+		let mut hash =
+			password_hash(secret, slot.salt, &slot.kdf, key_file.as_ref())?;
+		let key = unwrap_key(slot.key, hash, slot.cipher);
+		hash.zeroize();
+		Ok(key?)
+	}
+
+	// replace the slot of that kind with one wrapping the current vault key
+	fn wrap_slot(
+		&self,
+		kind: KeySlotKind,
+		secret: String,
+		kdf: KdfParams,
+		key_file: Option<&[u8; 32]>,
+	) -> anyhow::Result<()> {
+		let salt = get_random_string(self.config_db.read().salt_letter_count);
+		let cipher = self.config_db.read().cipher;
+		let mut hash = password_hash(secret, salt.clone(), &kdf, key_file)?;
+		let key = wrap_key(&self.hash.read(), hash, cipher);
+		hash.zeroize();
+
+		let slot = KeySlot {
+			kind,
+			salt,
+			kdf,
+			cipher,
+			key: key?,
+		};
+		let mut config_db = self.config_db.write();
+		config_db.key_slots.retain(|slot| slot.kind != kind);
+		config_db.key_slots.push(slot);
+		// after unlocking with the recovery code we may not have the key file
+		if kind == KeySlotKind::Password {
+			config_db.key_file = key_file.is_some();
+		}
 		Ok(())
 	}
 
+	// takes the password or the recovery code
+	fn verify(&self, secret: String) -> anyhow::Result<()> {
+		let recovery_code = normalize_recovery_code(&secret);
+		let key = self.unlock_slot(KeySlotKind::Password, secret).or_else(|err| {
+			match recovery_code {
+				Some(code) => self.unlock_slot(KeySlotKind::RecoveryCode, code),
+				None => Err(err),
+			}
+		});
+
+		match key {
+			Ok(mut key) => {
+				let matches = key == *self.hash.read();
+				key.zeroize();
+				if !matches {
+					bail!(WrongPassword())
+				}
+				Ok(())
+			},
+			Err(_) => bail!(WrongPassword()),
+		}
+	}
+
+	// decrypt the cypher of the db file and bring it up to the current format
+	fn decrypt_cypher(&self, hash: [u8; 32]) -> anyhow::Result<DbFileCypher> {
+		let config_db = self.config_db.read();
+		check_version(config_db.version)?;
+
+		let mut contents = if config_db.encrypted {
+			let decrypted = decrypt_vault(
+				config_db.cypher.clone(),
+				hash,
+				config_db.cipher,
+				&self.cypher_header.read(),
+			)?;
+			toml::from_str::<toml::Value>(decrypted.as_str())?
+		} else {
+			// an unencrypted vault can't be authenticated, only the debug settings
+			// are allowed to turn the encryption off
+			if !config_db.key_slots.is_empty() && std::env::var("DEBUG").is_err() {
+				bail!(CryptError::HeaderTampered())
+			}
+			toml::from_str::<toml::Value>(&config_db.cypher)?
+		};
+		migrate_contents(config_db.version, &mut contents)?;
+		Ok(DbFileCypher::deserialize(contents)?)
+	}
+
 	fn serialize_db(&self) -> anyhow::Result<()> {
 		// self.db -> self.config_db.cypher as toml
 		#[derive(Debug, Serialize, Deserialize)]
 		struct DbStruct {
 			contents: Vec<DbEntry>,
+			trash: Vec<DbTrashEntry>,
+			groups: Vec<DbGroup>,
 		}
 		let db = DbStruct {
 			contents: self.contents.read().clone(),
+			trash: self.trash.read().clone(),
+			groups: self.groups.read().clone(),
 		};
 		let mut cypher = toml::to_string(&db)?;
+		let header = header_aad(toml::Value::try_from(&*self.config_db.read())?);
 		if self.config_db.read().encrypted {
-			cypher = encrypt_vault(cypher, *self.hash.read())?;
+			let cipher = self.config_db.read().cipher;
+			cypher = encrypt_vault(cypher, *self.hash.read(), cipher, &header)?;
 		}
 		self.config_db.write().cypher = cypher;
+		*self.cypher_header.write() = header;
 		Ok(())
 	}
 
@@ -348,28 +1317,549 @@ impl Db {
 		Ok(toml::to_string_pretty(self)?)
 	}
 
+	// a vault of its own with only the entries of a group, it is encrypted with
+	// the same key slots so it opens with the same password
+	pub fn export_group(&self, id: &Uuid) -> anyhow::Result<String> {
+		let subgroups = self.get_subgroups(id);
+		let contents = self
+			.contents
+			.read()
+			.iter()
+			.filter(|entry| {
+				entry.group.is_some_and(|group| subgroups.contains(&group))
+			})
+			.cloned()
+			.collect();
+		let groups = self
+			.groups
+			.read()
+			.iter()
+			.filter(|group| subgroups.contains(&group.id))
+			.cloned()
+			.map(|mut group| {
+				if group.id == *id {
+					group.parent = None;
+				}
+				group
+			})
+			.collect();
+
+		let export = Db {
+			contents: Arc::new(RwLock::new(contents)),
+			groups: Arc::new(RwLock::new(groups)),
+			config_db: Arc::new(RwLock::new(self.config_db.read().clone())),
+			hash: Arc::new(RwLock::new(*self.hash.read())),
+			..Db::default()
+		};
+		let exported = export.export();
+
+		export.hash.write().zeroize();
+		for entry in &mut *export.contents.write() {
+			entry.zeroize();
+		}
+		for group in &mut *export.groups.write() {
+			group.zeroize();
+		}
+
+		exported
+	}
+
 	pub fn save(&self) -> anyhow::Result<()> {
+		// never overwrite a file we don't understand
+		if let Some(err) = self.load_error() {
+			bail!(err)
+		}
+		check_version(self.config_db.read().version)?;
+		let db_path = self.db_path.read().clone();
+		self.merge_external_changes(&db_path)?;
+
 		let config = self.export()?;
-		let mut config_file = fs::OpenOptions::new()
-			.write(true)
-			.create(true)
-			.truncate(true)
-			.open(self.db_path.read().clone())?;
-		config_file.write_all(config.as_bytes())?;
-		config_file.flush()?;
+		self.backup_if_due(&db_path);
+		atomic_write(&db_path, config.as_bytes())?;
+		*self.file_hash.write() = Some(file_hash(config.as_bytes()));
 		Ok(())
 	}
 
-	pub fn change_password(
-		&self,
-		old: String,
+	// saves happen on every edit and copy so we only back up the first save after
+	// an unlock and then once an interval, otherwise a few quick edits would
+	// rotate out all of the history
+	fn backup_if_due(&self, db_path: &str) {
+		let now = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap_or_default()
+			.as_secs();
+		let is_due = self
+			.last_backup
+			.read()
+			.map_or(true, |last| now.saturating_sub(last) >= BACKUP_INTERVAL);
+
+		// a failed backup should not keep us from saving the users changes
+		if is_due && create_backup(db_path, *self.backup_count.read()).is_ok() {
+			*self.last_backup.write() = Some(now);
+			self.refresh_backups();
+		}
+	}
+
+	fn refresh_backups(&self) {
+		if let Some(backups) = use_context::<BackupList>() {
+			backups.set(self.get_backups());
+		}
+	}
+
+	// another instance of the app or a sync tool might have written to the file
+	// since we last read it so we merge their changes instead of overwriting them
+	fn merge_external_changes(&self, db_path: &str) -> anyhow::Result<()> {
+		let content = match fs::read_to_string(db_path) {
+			Ok(content) => content,
+			Err(_) => return Ok(()),
+		};
+
+		if *self.file_hash.read() == Some(file_hash(content.as_bytes())) {
+			return Ok(());
+		}
+
+		let toast_signals = use_context::<ToastSignals>();
+
+		let theirs = match Db::parse(&content) {
+			Ok(theirs) => theirs,
+			// never overwrite a file a newer version wrote, not even with a backup
+			Err(err) if err.is::<MigrationError>() => return Err(err),
+			Err(_) => return self.keep_external_as_backup(db_path),
+		};
+
+		// password changes only rewrap the vault key so this only fails when the
+		// vault was set up with a new key somewhere else
+		let mut their_contents = match theirs.decrypt_cypher(*self.hash.read()) {
+			Ok(contents) => contents,
+			Err(_) => return self.keep_external_as_backup(db_path),
+		};
+		// the cypher still holds what we last read or wrote which is our base
+		let mut base = self.decrypt_cypher(*self.hash.read()).ok();
+
+		let conflicts = self.merge_cypher(base.as_ref(), &their_contents);
+		let header_changes =
+			self.merge_header(&UnlockHeader::from(&*theirs.config_db.read()));
+
+		their_contents.zeroize();
+		if let Some(base) = &mut base {
+			base.zeroize();
+		}
+
+		if let Some(list_sidebar_signal) = use_context::<SidebarList>() {
+			list_sidebar_signal.set(self.get_sidebar_list());
+		}
+
+		match header_changes {
+			HeaderChanges::Conflict => {
+				create_backup(db_path, (*self.backup_count.read()).max(1))?;
+				self.refresh_backups();
+				if let Some(toast_signals) = toast_signals {
+					toast_signals.add(String::from(
+						"The password or encryption of the vault was also changed by another program.\nOur settings were kept, the other version was kept as a backup.",
+					));
+				}
+			},
+			HeaderChanges::Theirs => {
+				if let Some(toast_signals) = toast_signals {
+					toast_signals.add(String::from(
+						"The password or encryption of the vault was changed by another program.\nTheir settings are used from now on.",
+					));
+				}
+			},
+			HeaderChanges::None => {},
+		}
+
+		if let Some(toast_signals) = toast_signals {
+			if conflicts.is_empty() {
+				toast_signals.add(String::from(
+					"The vault was changed by another program.\nTheir changes were merged.",
+				));
+			} else {
+				toast_signals.add(format!(
+					"The vault was changed by another program.\nConflicting changes, check the history for the other values:\n{}",
+					conflicts
+						.iter()
+						.map(|conflict| conflict.to_string())
+						.collect::<Vec<String>>()
+						.join("\n")
+				));
+			}
+		}
+
+		Ok(())
+	}
+
+	// the header we last read or wrote is the base, that way a password, key file
+	// or encryption change from another instance isn't reverted by our save
+	fn merge_header(&self, theirs: &UnlockHeader) -> HeaderChanges {
+		// the header is kept as an inline table, files from before it was
+		// authenticated have no base to go by
+		let cypher_header = self.cypher_header.read().clone();
+		let header_value = toml::de::ValueDeserializer::new(&cypher_header);
+		let Ok(base) = UnlockHeader::deserialize(header_value) else {
+			return HeaderChanges::None;
+		};
+		let mut config_db = self.config_db.write();
+		let ours = UnlockHeader::from(&*config_db);
+
+		let (header, is_conflict) = merge_value(Some(&base), &ours, theirs);
+		if is_conflict {
+			return HeaderChanges::Conflict;
+		}
+		if header == ours {
+			return HeaderChanges::None;
+		}
+
+		config_db.salt = header.salt;
+		config_db.cipher = header.cipher;
+		config_db.kdf = header.kdf;
+		config_db.key_file = header.key_file;
+		config_db.key_slots = header.key_slots;
+		HeaderChanges::Theirs
+	}
+
+	// if we can't merge with the file on disk we keep it around as a backup
+	fn keep_external_as_backup(&self, db_path: &str) -> anyhow::Result<()> {
+		create_backup(db_path, (*self.backup_count.read()).max(1))?;
+		self.refresh_backups();
+
+		if let Some(toast_signals) = use_context::<ToastSignals>() {
+			toast_signals.add(String::from(
+				"The vault was changed by another program and could not be merged.\nThe other version was kept as a backup.",
+			));
+		}
+
+		Ok(())
+	}
+
+	// merge another vault into ours, entries are matched by their id
+	pub fn merge(&self, other: &Db) -> Vec<MergeConflict> {
+		let mut theirs = DbFileCypher {
+			contents: other.contents.read().clone(),
+			trash: other.trash.read().clone(),
+			groups: other.groups.read().clone(),
+		};
+		// we have no common base so the field histories have to do
+		let conflicts = self.merge_cypher(None, &theirs);
+		theirs.zeroize();
+
+		conflicts
+	}
+
+	// what merging another vault would change in ours
+	pub fn merge_preview(
+		&self,
+		other: &Db,
+	) -> im::Vector<(Uuid, String, MergeChange)> {
+		let contents = self.contents.read();
+		let trash = self.trash.read();
+		let mut changes = im::Vector::new();
+
+		for their_entry in other.contents.read().iter() {
+			let our_entry = contents
+				.iter()
+				.find(|entry| entry.id == their_entry.id)
+				.or_else(|| {
+					trash
+						.iter()
+						.map(|trashed| &trashed.entry)
+						.find(|entry| entry.id == their_entry.id)
+				});
+
+			match our_entry {
+				None => changes.push_back((
+					their_entry.id,
+					their_entry.title.clone(),
+					MergeChange::Added,
+				)),
+				Some(our_entry) => {
+					let mut merged = our_entry.clone();
+					let conflicts = merge_entry(None, &mut merged, their_entry);
+					if !conflicts.is_empty() {
+						changes.push_back((
+							their_entry.id,
+							their_entry.title.clone(),
+							MergeChange::Conflict,
+						));
+					} else if merged != *our_entry {
+						changes.push_back((
+							their_entry.id,
+							their_entry.title.clone(),
+							MergeChange::Changed,
+						));
+					}
+					merged.zeroize();
+				},
+			}
+		}
+
+		for their_trashed in other.trash.read().iter() {
+			if contents.iter().any(|entry| entry.id == their_trashed.entry.id) {
+				changes.push_back((
+					their_trashed.entry.id,
+					their_trashed.entry.title.clone(),
+					MergeChange::Deleted,
+				));
+			}
+		}
+
+		changes
+	}
+
+	// merge the entries of another version of this vault into ours
+	fn merge_cypher(
+		&self,
+		base: Option<&DbFileCypher>,
+		theirs: &DbFileCypher,
+	) -> Vec<MergeConflict> {
+		// the base entry and if it was in the trash at the time
+		let find_base = |id: &Uuid| -> Option<(&DbEntry, bool)> {
+			let base = base?;
+			base
+				.contents
+				.iter()
+				.find(|entry| entry.id == *id)
+				.map(|entry| (entry, false))
+				.or_else(|| {
+					base
+						.trash
+						.iter()
+						.find(|trashed| trashed.entry.id == *id)
+						.map(|trashed| (&trashed.entry, true))
+				})
+		};
+
+		let mut conflicts = Vec::new();
+		let mut contents = self.contents.write();
+		let mut trash = self.trash.write();
+		let mut groups = self.groups.write();
+
+		for their_group in &theirs.groups {
+			let base_group = base.and_then(|base| {
+				base.groups.iter().find(|group| group.id == their_group.id)
+			});
+
+			match groups.iter_mut().find(|group| group.id == their_group.id) {
+				Some(our_group) => {
+					our_group.title = merge_value(
+						base_group.map(|group| &group.title),
+						&our_group.title,
+						&their_group.title,
+					)
+					.0;
+					our_group.parent = merge_value(
+						base_group.map(|group| &group.parent),
+						&our_group.parent,
+						&their_group.parent,
+					)
+					.0;
+				},
+				None if base_group.is_none() => groups.push(their_group.clone()),
+				// otherwise we deleted it
+				None => {},
+			}
+		}
+
+		for their_entry in &theirs.contents {
+			let base_entry = find_base(&their_entry.id);
+
+			if let Some(our_entry) =
+				contents.iter_mut().find(|entry| entry.id == their_entry.id)
+			{
+				conflicts.extend(merge_entry(
+					base_entry.map(|(entry, _)| entry),
+					our_entry,
+					their_entry,
+				));
+			} else if let Some(trashed) =
+				trash.iter_mut().find(|trashed| trashed.entry.id == their_entry.id)
+			{
+				// we deleted it, it stays in the trash but keeps their changes
+				if last_changed(their_entry) > trashed.deleted_at {
+					conflicts.push(MergeConflict {
+						entry: trashed.entry.title.clone(),
+						field: String::from("deleted here but changed elsewhere"),
+					});
+				}
+				merge_entry(
+					base_entry.map(|(entry, _)| entry),
+					&mut trashed.entry,
+					their_entry,
+				);
+			} else if base_entry.is_none() {
+				contents.push(their_entry.clone());
+			}
+			// otherwise we removed it from the trash for good
+		}
+
+		for their_trashed in &theirs.trash {
+			let base_entry = find_base(&their_trashed.entry.id);
+
+			if let Some(index) =
+				contents.iter().position(|entry| entry.id == their_trashed.entry.id)
+			{
+				merge_entry(
+					base_entry.map(|(entry, _)| entry),
+					&mut contents[index],
+					&their_trashed.entry,
+				);
+
+				// we either restored it or changed it after they deleted it
+				let is_restored = matches!(base_entry, Some((_, true)));
+				if !is_restored {
+					if last_changed(&contents[index]) > their_trashed.deleted_at {
+						conflicts.push(MergeConflict {
+							entry: contents[index].title.clone(),
+							field: String::from("deleted elsewhere but changed here"),
+						});
+					} else {
+						let entry = contents.remove(index);
+						trash.push(DbTrashEntry {
+							deleted_at: their_trashed.deleted_at,
+							entry,
+						});
+					}
+				}
+			} else if let Some(trashed) = trash
+				.iter_mut()
+				.find(|trashed| trashed.entry.id == their_trashed.entry.id)
+			{
+				merge_entry(
+					base_entry.map(|(entry, _)| entry),
+					&mut trashed.entry,
+					&their_trashed.entry,
+				);
+				trashed.deleted_at = trashed.deleted_at.max(their_trashed.deleted_at);
+			} else if base_entry.is_none() {
+				trash.push(their_trashed.clone());
+			}
+		}
+
+		conflicts
+	}
+
+	pub fn get_backups(&self) -> Vec<(String, u64)> {
+		get_backups(&self.db_path.read())
+	}
+
+	pub fn restore_backup(&self, backup_path: &str) -> anyhow::Result<()> {
+		let db_path = self.db_path.read().clone();
+		restore_backup(&db_path, backup_path, *self.backup_count.read())?;
+
+		// pick up the restored header, the contents are decrypted on next unlock
+		let restored = Db::load(db_path);
+		std::mem::swap(
+			&mut *self.config_db.write(),
+			&mut *restored.config_db.write(),
+		);
+		std::mem::swap(
+			&mut *self.file_hash.write(),
+			&mut *restored.file_hash.write(),
+		);
+		std::mem::swap(
+			&mut *self.cypher_header.write(),
+			&mut *restored.cypher_header.write(),
+		);
+		Ok(())
+	}
+
+	// only the password slot is rewrapped, the vault key stays the same, old can
+	// be the current password or the recovery code if the password was forgotten
+	pub fn change_password(
+		&self,
+		old: String,
 		new: String,
 	) -> anyhow::Result<()> {
-		let old_hash = password_hash(old, self.config_db.read().salt.clone())?;
-		if old_hash != *self.hash.read() {
-			bail!(WrongPassword())
-		}
-		self.set_password(new)
+		self.verify(old)?;
+		let kdf = self.config_db.read().kdf;
+		let key_file = *self.key_file.read();
+		self.wrap_slot(KeySlotKind::Password, new, kdf, key_file.as_ref())?;
+		self.save()?;
+		Ok(())
+	}
+
+	// rewrap the password slot with a key derived with the new parameters
+	pub fn change_kdf(
+		&self,
+		password: String,
+		kdf: KdfParams,
+	) -> anyhow::Result<()> {
+		self.verify(password.clone())?;
+
+		// invalid parameters fail here before anything changed
+		let key_file = *self.key_file.read();
+		self.wrap_slot(KeySlotKind::Password, password, kdf, key_file.as_ref())?;
+		self.config_db.write().kdf = kdf;
+		self.save()?;
+		Ok(())
+	}
+
+	// rewrap the password slot with or without a key file, None removes it
+	pub fn change_key_file(
+		&self,
+		password: String,
+		key_file: Option<[u8; 32]>,
+	) -> anyhow::Result<()> {
+		self.verify(password.clone())?;
+
+		let kdf = self.config_db.read().kdf;
+		self.wrap_slot(KeySlotKind::Password, password, kdf, key_file.as_ref())?;
+		self.set_key_file(key_file);
+		self.save()?;
+		Ok(())
+	}
+
+	// the contents are encrypted with the new cipher on save, the password slot
+	// is rewrapped with it right away
+	pub fn change_cipher(
+		&self,
+		password: String,
+		cipher: Cipher,
+	) -> anyhow::Result<()> {
+		self.verify(password.clone())?;
+
+		self.config_db.write().cipher = cipher;
+		let kdf = self.config_db.read().kdf;
+		let key_file = *self.key_file.read();
+		self.wrap_slot(KeySlotKind::Password, password, kdf, key_file.as_ref())?;
+		self.save()?;
+		Ok(())
+	}
+
+	// replaces any previous recovery code, the code is only ever shown once
+	pub fn create_recovery_code(
+		&self,
+		password: String,
+	) -> anyhow::Result<String> {
+		self.verify(password)?;
+
+		let code = generate_recovery_code();
+		let kdf = self.config_db.read().kdf;
+		self.wrap_slot(
+			KeySlotKind::RecoveryCode,
+			normalize_recovery_code(&code).unwrap_or_default(),
+			kdf,
+			None,
+		)?;
+		self.save()?;
+		Ok(code)
+	}
+
+	pub fn remove_recovery_code(&self, password: String) -> anyhow::Result<()> {
+		self.verify(password)?;
+		self
+			.config_db
+			.write()
+			.key_slots
+			.retain(|slot| slot.kind != KeySlotKind::RecoveryCode);
+		self.save()?;
+		Ok(())
+	}
+
+	pub fn set_key_file(&self, key_file: Option<[u8; 32]>) {
+		let mut current = self.key_file.write();
+		current.zeroize();
+		*current = key_file;
 	}
 
 	pub fn change_salt(&self) {
@@ -378,11 +1868,13 @@ impl Db {
 		self.config_db.write().salt = salt;
 	}
 
+	// a new vault gets a fresh key and a single password slot
 	pub fn set_password(&self, new: String) -> anyhow::Result<()> {
-		self.change_salt();
-
-		let new_hash = password_hash(new, self.config_db.read().salt.clone())?;
-		*self.hash.write() = new_hash;
+		*self.hash.write() = generate_key();
+		self.config_db.write().key_slots.clear();
+		let kdf = self.config_db.read().kdf;
+		let key_file = *self.key_file.read();
+		self.wrap_slot(KeySlotKind::Password, new, kdf, key_file.as_ref())?;
 		self.save()?;
 		Ok(())
 	}
@@ -391,8 +1883,13 @@ impl Db {
 		*self.db_path.write() = path;
 	}
 
+	pub fn set_backup_count(&self, count: usize) {
+		*self.backup_count.write() = count;
+	}
+
 	pub fn lock(&self) {
 		self.hash.write().zeroize();
+		self.set_key_file(None);
 		{
 			// clear data
 			for content in &mut *self.contents.write() {
@@ -401,6 +1898,18 @@ impl Db {
 
 			// remove meta data (number of entries)
 			self.contents.write().clear();
+
+			// same for the trash
+			for trashed in &mut *self.trash.write() {
+				trashed.zeroize();
+			}
+			self.trash.write().clear();
+
+			// and the groups
+			for group in &mut *self.groups.write() {
+				group.zeroize();
+			}
+			self.groups.write().clear();
 		}
 
 		// cleaning context
@@ -409,7 +1918,7 @@ impl Db {
 		list_sidebar_signal.update(|sidebar| {
 			// clear data
 			for (id, title, idx) in sidebar.iter_mut() {
-				id.zeroize();
+				*id = Uuid::nil();
 				title.zeroize();
 				idx.zeroize();
 			}
@@ -420,22 +1929,36 @@ impl Db {
 	}
 
 	// PRIVATE: get content of entry
-	fn get_by_id_secure(&self, id: &usize) -> DbEntry {
+	fn get_by_id_secure(&self, id: &Uuid) -> DbEntry {
 		if let Some(found_entry) =
 			self.contents.read().iter().find(|item| item.id == *id)
 		{
-			found_entry.clone()
+			DbEntry {
+				title: found_entry.title.clone(),
+				fields: found_entry
+					.fields
+					.iter()
+					.map(DynField::without_files)
+					.collect(),
+				tags: found_entry.tags.clone(),
+				..*found_entry
+			}
 		} else {
 			DbEntry {
 				id: *id,
 				title: String::from("Not found"),
 				fields: vec![DynField::default()],
+				group: None,
+				tags: Vec::new(),
+				favorite: false,
+				last_used: None,
+				created: 0,
 			}
 		}
 	}
 
 	// PRIVATE: get content of field by id
-	fn get_field_by_id(&self, entry: &DbEntry, field_id: &usize) -> DynField {
+	fn get_field_by_id(&self, entry: &DbEntry, field_id: &Uuid) -> DynField {
 		entry
 			.fields
 			.clone()
@@ -447,11 +1970,13 @@ impl Db {
 				title: String::from("Notes"),
 				visible: true,
 				value: vec![(0, String::from("My Notes"))],
+				files: Vec::new(),
+				counters: Vec::new(),
 			})
 	}
 
 	// get the list of all entries for sidebar view
-	pub fn get_sidebar_list(&self) -> im::Vector<(usize, String, usize)> {
+	pub fn get_sidebar_list(&self) -> im::Vector<(Uuid, String, usize)> {
 		self
 			.contents
 			.read()
@@ -462,17 +1987,141 @@ impl Db {
 			.collect()
 	}
 
-	// search through db and return a list for sidebar view
-	pub fn search(&self, needle: &str) -> im::Vector<(usize, String, usize)> {
+	// the sidebar list arranged into the group tree, outside of a search pinned
+	// and recently used entries are shown on top of it
+	pub fn get_sidebar_tree(
+		&self,
+		list: &im::Vector<(Uuid, String, usize)>,
+		collapsed: &[Uuid],
+		is_search: bool,
+		sort: SidebarSort,
+	) -> im::Vector<(usize, SidebarItem)> {
+		let groups = self.groups.read();
+		let known_group = |group: Option<Uuid>| {
+			group.filter(|group| groups.iter().any(|item| item.id == *group))
+		};
+
+		// groups we don't know about, say after a merge, end up at the top
+		let tree_groups = groups
+			.iter()
+			.cloned()
+			.map(|mut group| {
+				group.parent = known_group(group.parent);
+				group
+			})
+			.collect::<Vec<DbGroup>>();
+
+		let contents = self.contents.read();
+		let entry_info = contents
+			.iter()
+			.map(|entry| (entry.id, entry))
+			.collect::<HashMap<Uuid, &DbEntry>>();
+
+		// entries created in the same second keep the order of the db
+		let mut list = list.iter().cloned().collect::<Vec<_>>();
+		list.sort_by(|a, b| {
+			let (entry_a, entry_b) = (entry_info.get(&a.0), entry_info.get(&b.0));
+			match sort {
+				SidebarSort::Created => entry_b
+					.map(|entry| entry.created)
+					.cmp(&entry_a.map(|entry| entry.created)),
+				SidebarSort::Title => a.1.to_lowercase().cmp(&b.1.to_lowercase()),
+				SidebarSort::Modified => entry_b
+					.map(|entry| last_changed(entry))
+					.cmp(&entry_a.map(|entry| last_changed(entry))),
+				SidebarSort::LastUsed => entry_b
+					.and_then(|entry| entry.last_used)
+					.cmp(&entry_a.and_then(|entry| entry.last_used)),
+			}
+			.then_with(|| b.2.cmp(&a.2))
+		});
+
+		let entries = list
+			.iter()
+			.map(|(id, title, _)| {
+				let group = entry_info.get(id).and_then(|entry| entry.group);
+				(*id, title.clone(), known_group(group))
+			})
+			.collect::<Vec<(Uuid, String, Option<Uuid>)>>();
+
+		let mut rows = Vec::new();
+		if !is_search {
+			let pinned = list
+				.iter()
+				.filter(|(id, _, _)| {
+					entry_info.get(id).is_some_and(|entry| entry.favorite)
+				})
+				.map(|(id, title, _)| SidebarItem::Entry(*id, title.clone(), 0))
+				.collect::<Vec<SidebarItem>>();
+
+			let mut recent = list
+				.iter()
+				.filter_map(|(id, title, _)| {
+					let last_used = entry_info.get(id)?.last_used?;
+					Some((last_used, SidebarItem::Entry(*id, title.clone(), 0)))
+				})
+				.collect::<Vec<(u64, SidebarItem)>>();
+			recent.sort_by_key(|(last_used, _)| std::cmp::Reverse(*last_used));
+			recent.truncate(RECENT_COUNT);
+
+			if !pinned.is_empty() {
+				rows.push(SidebarItem::Section(String::from("Pinned")));
+				rows.extend(pinned);
+			}
+			if !recent.is_empty() {
+				rows.push(SidebarItem::Section(String::from("Recently used")));
+				rows.extend(recent.into_iter().map(|(_, row)| row));
+			}
+			if !rows.is_empty() {
+				rows.push(SidebarItem::Section(String::from("All entries")));
+			}
+		}
+
+		rows.extend(tree_rows(
+			None,
+			0,
+			&tree_groups,
+			&entries,
+			collapsed,
+			is_search,
+		));
+		rows.into_iter().enumerate().collect()
+	}
+
+	// search through db and return a list for sidebar view, only looking inside
+	// a group and its subgroups when given one
+	pub fn search(
+		&self,
+		needle: &str,
+		group: Option<Uuid>,
+	) -> im::Vector<(Uuid, String, usize)> {
+		let scope = group.map(|group| self.get_subgroups(&group));
+
+		// tag:name narrows the list down, everything else is searched for as text
+		let (tags, words): (Vec<&str>, Vec<&str>) =
+			needle.split_whitespace().partition(|word| word.starts_with(TAG_PREFIX));
+		let tags = tags
+			.iter()
+			.map(|tag| normalize_tag(&tag[TAG_PREFIX.len()..]))
+			.filter(|tag| !tag.is_empty())
+			.collect::<Vec<String>>();
+		let needle = words.join(" ");
+
 		self
 			.contents
 			.read()
 			.iter()
 			.enumerate()
+			.filter(|(_, entry)| match &scope {
+				Some(scope) => entry.group.is_some_and(|group| scope.contains(&group)),
+				None => true,
+			})
+			.filter(|(_, entry)| tags.iter().all(|tag| entry.tags.contains(tag)))
 			.filter(|(_, entry)| {
 				let lowercase_needle = needle.to_lowercase();
 				// look at entry title
 				entry.title.to_lowercase().contains(&lowercase_needle)
+					|| entry.tags.iter().any(|tag| tag.contains(&lowercase_needle))
 					|| entry.fields.iter().any(|field| {
 						// look at field title
 						field.title.to_lowercase().contains(&lowercase_needle) ||
@@ -492,7 +2141,7 @@ impl Db {
 	}
 
 	// get non secure content of entry
-	pub fn get_by_id(&self, id: &usize) -> DbEntryNonSecure {
+	pub fn get_by_id(&self, id: &Uuid) -> DbEntryNonSecure {
 		let entry = self.get_by_id_secure(id);
 
 		DbEntryNonSecure {
@@ -502,7 +2151,7 @@ impl Db {
 	}
 
 	// get a list of all fields
-	pub fn get_fields(&self, id: &usize) -> Vec<(DbFields, bool)> {
+	pub fn get_fields(&self, id: &Uuid) -> Vec<(DbFields, bool)> {
 		let entry = self.get_by_id_secure(id);
 
 		entry
@@ -513,7 +2162,7 @@ impl Db {
 	}
 
 	// get a list of all fields
-	pub fn get_visible_fields(&self, id: &usize) -> Vec<DbFields> {
+	pub fn get_visible_fields(&self, id: &Uuid) -> Vec<DbFields> {
 		let entry = self.get_by_id_secure(id);
 
 		entry
@@ -525,7 +2174,7 @@ impl Db {
 	}
 
 	// get a list of all fields
-	pub fn get_hidden_fields(&self, id: &usize) -> Vec<DbFields> {
+	pub fn get_hidden_fields(&self, id: &Uuid) -> Vec<DbFields> {
 		let entry = self.get_by_id_secure(id);
 
 		entry
@@ -537,17 +2186,17 @@ impl Db {
 	}
 
 	// get name of field
-	pub fn get_name_of_field(&self, id: &usize, field: &DbFields) -> String {
+	pub fn get_name_of_field(&self, id: &Uuid, field: &DbFields) -> String {
 		let entry = self.get_by_id_secure(id);
 		let field_id = match field {
-			DbFields::Fields(idx) => idx,
-			_ => &0,
+			DbFields::Fields(field_id) => *field_id,
+			_ => Uuid::nil(),
 		};
-		self.get_field_by_id(&entry, field_id).title
+		self.get_field_by_id(&entry, &field_id).title
 	}
 
 	// get kind of field
-	pub fn get_field_kind(&self, id: &usize, field: &DbFields) -> DynFieldKind {
+	pub fn get_field_kind(&self, id: &Uuid, field: &DbFields) -> DynFieldKind {
 		let entry = self.get_by_id_secure(id);
 		match field {
 			DbFields::Id | DbFields::Title => DynFieldKind::TextLine,
@@ -555,8 +2204,37 @@ impl Db {
 		}
 	}
 
+	// days until the first card of an entry expires, for the sidebar
+	pub fn get_card_expiry(&self, id: &Uuid) -> Option<i64> {
+		self
+			.contents
+			.read()
+			.iter()
+			.filter(|item| item.id == *id)
+			.flat_map(|item| item.fields.iter())
+			.filter(|field| field.visible && field.kind == DynFieldKind::CardExpiry)
+			.filter_map(|field| field.value.last())
+			.filter_map(|value| days_until_expiry(&value.1))
+			.min()
+	}
+
+	// only the latest value of one field, for views that poll it and shouldn't
+	// clone every secret of the entry each time
+	pub fn get_last_field_value(&self, id: &Uuid, field_id: &Uuid) -> String {
+		self
+			.contents
+			.read()
+			.iter()
+			.filter(|item| item.id == *id)
+			.flat_map(|item| item.fields.iter())
+			.find(|field| field.id == *field_id)
+			.and_then(|field| field.value.last())
+			.map(|value| value.1.clone())
+			.unwrap_or_default()
+	}
+
 	// get the latest entry of a field
-	pub fn get_last_by_field(&self, id: &usize, field: &DbFields) -> String {
+	pub fn get_last_by_field(&self, id: &Uuid, field: &DbFields) -> String {
 		let entry = self.get_by_id_secure(id);
 
 		match field {
@@ -571,7 +2249,7 @@ impl Db {
 	// get the entry n of a field (look into the history of a field)
 	pub fn get_n_by_field(
 		&self,
-		id: &usize,
+		id: &Uuid,
 		field: &DbFields,
 		n: usize,
 	) -> String {
@@ -591,10 +2269,77 @@ impl Db {
 		}
 	}
 
+	// the counters of the codes generated from the value n (look into the
+	// history of a field)
+	pub fn get_hotp_counters(
+		&self,
+		id: &Uuid,
+		field: &DbFields,
+		n: usize,
+	) -> Vec<u64> {
+		let DbFields::Fields(field_id) = field else {
+			return Vec::new();
+		};
+		let contents = self.contents.read();
+		let Some(field) = contents
+			.iter()
+			.filter(|item| item.id == *id)
+			.flat_map(|item| item.fields.iter())
+			.find(|field| field.id == *field_id)
+		else {
+			return Vec::new();
+		};
+		let Some(value) = field.value.iter().rev().nth(n) else {
+			return Vec::new();
+		};
+
+		field
+			.counters
+			.iter()
+			.filter(|(timestamp, _)| *timestamp == value.0)
+			.map(|(_, counter)| *counter)
+			.collect()
+	}
+
+	// hands out the counter for the next code of the latest value and keeps
+	// it, start is the counter of the otpauth link the first code uses
+	pub fn next_hotp_counter(
+		&self,
+		id: &Uuid,
+		field: &DbFields,
+		start: u64,
+	) -> u64 {
+		let DbFields::Fields(field_id) = field else {
+			return start;
+		};
+		let mut contents = self.contents.write();
+		let Some(field) = contents
+			.iter_mut()
+			.filter(|item| item.id == *id)
+			.flat_map(|item| item.fields.iter_mut())
+			.find(|field| field.id == *field_id)
+		else {
+			return start;
+		};
+		let Some(timestamp) = field.value.last().map(|value| value.0) else {
+			return start;
+		};
+
+		let counter = field
+			.counters
+			.iter()
+			.filter(|(value_timestamp, _)| *value_timestamp == timestamp)
+			.map(|(_, counter)| counter + 1)
+			.max()
+			.unwrap_or(start);
+		field.counters.push((timestamp, counter));
+		counter
+	}
+
 	// get the entire history of a field
 	pub fn get_history(
 		&self,
-		id: &usize,
+		id: &Uuid,
 		field: &DbFields,
 	) -> Option<im::Vector<SecureField>> {
 		let entry = self.get_by_id_secure(id);
@@ -616,7 +2361,7 @@ impl Db {
 	// get the date and id of a field
 	pub fn get_history_dates(
 		&self,
-		id: &usize,
+		id: &Uuid,
 		field: &DbFields,
 	) -> Vec<(usize, u64)> {
 		let entry = self.get_by_id_secure(id);
@@ -635,79 +2380,466 @@ impl Db {
 	}
 
 	// add a new entry
-	pub fn add(&self, title: String) -> usize {
-		let new_id = self
-			.contents
-			.read()
-			.last()
-			.unwrap_or(&DbEntry {
-				id: 1,
-				title: String::from("New Entry"),
-				fields: vec![DynField::default()],
-			})
-			.id + 1;
+	pub fn add(&self, title: String) -> Uuid {
+		let new_id = Uuid::new_v4();
+		let timestamp: u64 = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap_or(Duration::new(0, 0))
+			.as_secs();
 
 		self.contents.write().push(DbEntry {
 			id: new_id,
 			title,
 			fields: Vec::new(),
+			group: None,
+			tags: Vec::new(),
+			favorite: false,
+			last_used: None,
+			created: timestamp,
 		});
 
 		new_id
 	}
 
-	// add a new field to an entry
-	pub fn add_field(
+	// a new entry that starts out with the fields of a template
+	pub fn add_from_template(
 		&self,
-		id: &usize,
-		kind: DynFieldKind,
-		title_value: String,
-		field_value: String,
-	) -> DbFields {
-		let mut field = DbFields::Id;
-		self.contents.write().iter_mut().for_each(|item| {
-			if item.id == *id {
-				let id = item.fields.last().unwrap_or(&DynField::default()).id + 1;
-				item.fields.push(DynField {
-					id,
-					kind: kind.clone(),
-					title: title_value.clone(),
-					visible: true,
-					value: vec![(0, field_value.clone())],
-				});
-				field = DbFields::Fields(id);
-			}
-		});
+		title: String,
+		fields: TemplateFields,
+	) -> Uuid {
+		let new_id = self.add(title);
 
-		field
+		for (title, value, kind) in fields {
+			self.add_field(&new_id, kind, title, value);
+		}
+
+		new_id
 	}
 
-	// change the title of a field
-	pub fn edit_field_title(&self, id: &usize, field: &DbFields, title: String) {
-		self.contents.write().iter_mut().for_each(|item| {
-			if item.id == *id {
-				if let DbFields::Fields(field_id) = field {
-					item
-						.fields
-						.iter_mut()
-						.find(|field| field.id == *field_id)
-						.unwrap_or(&mut DynField {
-							id: *field_id,
-							kind: DynFieldKind::TextLineSecret,
-							title: String::from("Notes"),
-							visible: true,
-							value: vec![(0, String::from("My Notes"))],
-						})
-						.title
-						.clone_from(&title);
-				}
+	// copy an entry with its entire history over from another database
+	pub fn import_entry(&self, import_db: &Db, id: &Uuid) -> Uuid {
+		// the attachments have to come along so this can't use get_by_id_secure
+		let Some(mut entry) =
+			import_db.contents.read().iter().find(|item| item.id == *id).cloned()
+		else {
+			return *id;
+		};
+
+		// we keep ids stable across vaults unless this vault already knows it
+		let is_taken = self.contents.read().iter().any(|item| item.id == entry.id)
+			|| self.trash.read().iter().any(|item| item.entry.id == entry.id);
+		if is_taken {
+			entry.id = Uuid::new_v4();
+		}
+
+		// bring along the groups it was in unless this vault has them already
+		let mut group = entry.group;
+		while let Some(group_id) = group {
+			if self.groups.read().iter().any(|item| item.id == group_id) {
+				break;
 			}
-		});
+			let their_group = import_db
+				.groups
+				.read()
+				.iter()
+				.find(|item| item.id == group_id)
+				.cloned();
+			let Some(their_group) = their_group else {
+				break;
+			};
+			group = their_group.parent;
+			self.groups.write().push(their_group);
+		}
+
+		let new_id = entry.id;
+		self.contents.write().push(entry);
+
+		new_id
 	}
 
-	pub fn edit_field_visbility(
-		&self,
-		id: &usize,
+	pub fn get_group_title(&self, id: &Uuid) -> String {
+		self
+			.groups
+			.read()
+			.iter()
+			.find(|group| group.id == *id)
+			.map(|group| group.title.clone())
+			.unwrap_or_default()
+	}
+
+	pub fn add_group(&self, title: String, parent: Option<Uuid>) -> Uuid {
+		let new_id = Uuid::new_v4();
+
+		self.groups.write().push(DbGroup {
+			id: new_id,
+			title,
+			parent,
+		});
+
+		new_id
+	}
+
+	pub fn rename_group(&self, id: &Uuid, title: String) {
+		if let Some(group) =
+			self.groups.write().iter_mut().find(|group| group.id == *id)
+		{
+			group.title = title;
+		}
+	}
+
+	// everything in a deleted group moves up into its parent
+	pub fn delete_group(&self, id: &Uuid) {
+		let position = self.groups.read().iter().position(|group| group.id == *id);
+
+		if let Some(index) = position {
+			let mut deleted = self.groups.write().remove(index);
+			let parent = deleted.parent;
+
+			for group in self.groups.write().iter_mut() {
+				if group.parent == Some(*id) {
+					group.parent = parent;
+				}
+			}
+			for entry in self.contents.write().iter_mut() {
+				if entry.group == Some(*id) {
+					entry.group = parent;
+				}
+			}
+			for trashed in self.trash.write().iter_mut() {
+				if trashed.entry.group == Some(*id) {
+					trashed.entry.group = parent;
+				}
+			}
+
+			deleted.zeroize();
+		}
+	}
+
+	pub fn get_tags(&self, id: &Uuid) -> Vec<String> {
+		self
+			.contents
+			.read()
+			.iter()
+			.find(|entry| entry.id == *id)
+			.map(|entry| entry.tags.clone())
+			.unwrap_or_default()
+	}
+
+	pub fn add_tag(&self, id: &Uuid, tag: &str) {
+		let tag = normalize_tag(tag);
+		if tag.is_empty() {
+			return;
+		}
+
+		if let Some(entry) =
+			self.contents.write().iter_mut().find(|entry| entry.id == *id)
+		{
+			if !entry.tags.contains(&tag) {
+				entry.tags.push(tag);
+			}
+		}
+	}
+
+	pub fn remove_tag(&self, id: &Uuid, tag: &str) {
+		if let Some(entry) =
+			self.contents.write().iter_mut().find(|entry| entry.id == *id)
+		{
+			entry.tags.retain(|item| item != tag);
+		}
+	}
+
+	// every tag in use and on how many entries, the most used first
+	pub fn get_all_tags(&self) -> Vec<(String, usize)> {
+		let mut counts = HashMap::<String, usize>::new();
+		for entry in self.contents.read().iter() {
+			for tag in &entry.tags {
+				*counts.entry(tag.clone()).or_default() += 1;
+			}
+		}
+
+		let mut tags = counts.into_iter().collect::<Vec<(String, usize)>>();
+		tags.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+		tags
+	}
+
+	pub fn is_favorite(&self, id: &Uuid) -> bool {
+		self.contents.read().iter().any(|entry| entry.id == *id && entry.favorite)
+	}
+
+	pub fn set_favorite(&self, id: &Uuid, favorite: bool) {
+		if let Some(entry) =
+			self.contents.write().iter_mut().find(|entry| entry.id == *id)
+		{
+			entry.favorite = favorite;
+		}
+	}
+
+	// called whenever a value of an entry is copied or revealed
+	pub fn set_last_used(&self, id: &Uuid) {
+		let timestamp: u64 = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap_or(Duration::new(0, 0))
+			.as_secs();
+
+		if let Some(entry) =
+			self.contents.write().iter_mut().find(|entry| entry.id == *id)
+		{
+			entry.last_used = Some(timestamp);
+		}
+	}
+
+	pub fn get_entry_group(&self, id: &Uuid) -> Option<Uuid> {
+		self
+			.contents
+			.read()
+			.iter()
+			.find(|entry| entry.id == *id)
+			.and_then(|entry| entry.group)
+	}
+
+	pub fn move_entry(&self, id: &Uuid, group: Option<Uuid>) {
+		if let Some(entry) =
+			self.contents.write().iter_mut().find(|entry| entry.id == *id)
+		{
+			entry.group = group;
+		}
+	}
+
+	// PRIVATE: a group and all the groups nested in it
+	fn get_subgroups(&self, id: &Uuid) -> Vec<Uuid> {
+		let groups = self.groups.read();
+		let mut subgroups = vec![*id];
+		let mut idx = 0;
+
+		while let Some(parent) = subgroups.get(idx).copied() {
+			for group in groups.iter() {
+				if group.parent == Some(parent) && !subgroups.contains(&group.id) {
+					subgroups.push(group.id);
+				}
+			}
+			idx += 1;
+		}
+
+		subgroups
+	}
+
+	// add a new field to an entry
+	pub fn add_field(
+		&self,
+		id: &Uuid,
+		kind: DynFieldKind,
+		title_value: String,
+		field_value: String,
+	) -> DbFields {
+		let mut field = DbFields::Id;
+		self.contents.write().iter_mut().for_each(|item| {
+			if item.id == *id {
+				let id = Uuid::new_v4();
+				item.fields.push(DynField {
+					id,
+					kind: kind.clone(),
+					title: title_value.clone(),
+					visible: true,
+					value: vec![(0, field_value.clone())],
+					files: Vec::new(),
+					counters: Vec::new(),
+				});
+				field = DbFields::Fields(id);
+			}
+		});
+
+		field
+	}
+
+	// PRIVATE: read a file for an attachment and make sure it still fits
+	fn read_attachment(
+		&self,
+		path: &str,
+	) -> Result<(String, String), AttachmentError> {
+		let size = fs::metadata(path)
+			.map_err(|err| AttachmentError::Unreadable(err.to_string()))?
+			.len() as usize;
+		if size > MAX_ATTACHMENT_SIZE {
+			return Err(AttachmentError::TooLarge());
+		}
+
+		// trashed entries are restorable so their files still count
+		let contents = self.contents.read();
+		let trash = self.trash.read();
+		let stored_size: usize = contents
+			.iter()
+			.chain(trash.iter().map(|trashed| &trashed.entry))
+			.flat_map(|entry| &entry.fields)
+			.flat_map(|field| &field.files)
+			.map(|file| file.len() / 4 * 3)
+			.sum();
+		if stored_size + size > MAX_ATTACHMENTS_SIZE {
+			return Err(AttachmentError::VaultFull());
+		}
+
+		let mut bytes = fs::read(path)
+			.map_err(|err| AttachmentError::Unreadable(err.to_string()))?;
+		let content = general_purpose::STANDARD.encode(&bytes);
+		bytes.zeroize();
+
+		let name = Path::new(path)
+			.file_name()
+			.and_then(|name| name.to_str())
+			.unwrap_or("attachment")
+			.to_string();
+
+		Ok((name, content))
+	}
+
+	// add a new attachment field to an entry
+	pub fn add_attachment(
+		&self,
+		id: &Uuid,
+		title_value: String,
+		path: &str,
+	) -> Result<DbFields, AttachmentError> {
+		let (name, content) = self.read_attachment(path)?;
+		let timestamp: u64 = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap_or(Duration::new(0, 0))
+			.as_secs();
+
+		let mut field = DbFields::Id;
+		self.contents.write().iter_mut().for_each(|item| {
+			if item.id == *id {
+				let id = Uuid::new_v4();
+				item.fields.push(DynField {
+					id,
+					kind: DynFieldKind::Attachment,
+					title: title_value.clone(),
+					visible: true,
+					value: vec![(timestamp, name.clone())],
+					files: vec![content.clone()],
+					counters: Vec::new(),
+				});
+				field = DbFields::Fields(id);
+			}
+		});
+
+		Ok(field)
+	}
+
+	// replace the file of an attachment field, the old one stays in the history
+	pub fn replace_attachment(
+		&self,
+		id: &Uuid,
+		field: &DbFields,
+		path: &str,
+	) -> Result<(), AttachmentError> {
+		let (name, content) = self.read_attachment(path)?;
+		let timestamp: u64 = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap_or(Duration::new(0, 0))
+			.as_secs();
+
+		if let DbFields::Fields(field_id) = field {
+			if let Some(dyn_field) = self
+				.contents
+				.write()
+				.iter_mut()
+				.filter(|item| item.id == *id)
+				.flat_map(|item| item.fields.iter_mut())
+				.find(|dyn_field| dyn_field.id == *field_id)
+			{
+				dyn_field.value.push((timestamp, name));
+				dyn_field.files.push(content);
+				return Ok(());
+			}
+		}
+
+		Err(AttachmentError::Missing())
+	}
+
+	// size in bytes of the attachment n (look into the history of a field)
+	pub fn get_attachment_size(
+		&self,
+		id: &Uuid,
+		field: &DbFields,
+		n: usize,
+	) -> Option<usize> {
+		self.with_attachment(id, field, n, |content| {
+			let padding = content.chars().rev().take_while(|c| *c == '=').count();
+			content.len() / 4 * 3 - padding
+		})
+	}
+
+	// write the attachment n (look into the history of a field) back to disk
+	pub fn save_attachment(
+		&self,
+		id: &Uuid,
+		field: &DbFields,
+		n: usize,
+		path: &str,
+	) -> Result<(), AttachmentError> {
+		let mut bytes = self
+			.with_attachment(id, field, n, |content| {
+				general_purpose::STANDARD.decode(content)
+			})
+			.ok_or(AttachmentError::Missing())?
+			.map_err(|_| AttachmentError::Missing())?;
+		let result = fs::write(path, &bytes)
+			.map_err(|err| AttachmentError::Unwritable(err.to_string()));
+		bytes.zeroize();
+
+		result
+	}
+
+	// PRIVATE: look at the content of an attachment without cloning the entry
+	fn with_attachment<T>(
+		&self,
+		id: &Uuid,
+		field: &DbFields,
+		n: usize,
+		read: impl FnOnce(&str) -> T,
+	) -> Option<T> {
+		let DbFields::Fields(field_id) = field else {
+			return None;
+		};
+
+		let contents = self.contents.read();
+		let dyn_field = contents
+			.iter()
+			.filter(|item| item.id == *id)
+			.flat_map(|item| item.fields.iter())
+			.find(|dyn_field| dyn_field.id == *field_id)?;
+		let index = dyn_field.value.len().checked_sub(n + 1)?;
+
+		dyn_field.files.get(index).map(|file| read(file))
+	}
+
+	// change the title of a field
+	pub fn edit_field_title(&self, id: &Uuid, field: &DbFields, title: String) {
+		self.contents.write().iter_mut().for_each(|item| {
+			if item.id == *id {
+				if let DbFields::Fields(field_id) = field {
+					item
+						.fields
+						.iter_mut()
+						.find(|field| field.id == *field_id)
+						.unwrap_or(&mut DynField {
+							id: *field_id,
+							kind: DynFieldKind::TextLineSecret,
+							title: String::from("Notes"),
+							visible: true,
+							value: vec![(0, String::from("My Notes"))],
+							files: Vec::new(),
+							counters: Vec::new(),
+						})
+						.title
+						.clone_from(&title);
+				}
+			}
+		});
+	}
+
+	pub fn edit_field_visbility(
+		&self,
+		id: &Uuid,
 		field: &DbFields,
 		visible: bool,
 	) -> Vec<DbFields> {
@@ -724,6 +2856,8 @@ impl Db {
 							title: String::from("Notes"),
 							visible,
 							value: vec![(0, String::from("My Notes"))],
+							files: Vec::new(),
+							counters: Vec::new(),
 						})
 						.visible = visible;
 				}
@@ -734,7 +2868,7 @@ impl Db {
 	}
 
 	// edit a field
-	pub fn edit_field(&self, id: usize, field: &DbFields, new_content: String) {
+	pub fn edit_field(&self, id: Uuid, field: &DbFields, new_content: String) {
 		let mut index: usize = 0;
 		self.contents.read().iter().enumerate().find(|(idx, item)| {
 			if item.id == id {
@@ -769,6 +2903,8 @@ impl Db {
 							title: String::from("Notes"),
 							visible: true,
 							value: vec![(0, String::from("My Notes"))],
+							files: Vec::new(),
+							counters: Vec::new(),
 						})
 						.value
 						.push((timestamp, new_content));
@@ -776,4 +2912,975 @@ impl Db {
 			}
 		}
 	}
+
+	// move an entry into the trash
+	pub fn delete_entry(&self, id: &Uuid) {
+		let position = self.contents.read().iter().position(|item| item.id == *id);
+
+		if let Some(index) = position {
+			let entry = self.contents.write().remove(index);
+			let timestamp: u64 = SystemTime::now()
+				.duration_since(UNIX_EPOCH)
+				.unwrap_or(Duration::new(0, 0))
+				.as_secs();
+
+			self.trash.write().push(DbTrashEntry {
+				deleted_at: timestamp,
+				entry,
+			});
+		}
+	}
+
+	// move an entry out of the trash back into the contents
+	pub fn restore_entry(&self, id: &Uuid) {
+		let position =
+			self.trash.read().iter().position(|item| item.entry.id == *id);
+
+		if let Some(index) = position {
+			let entry = self.trash.write().remove(index).entry;
+			self.contents.write().push(entry);
+		}
+	}
+
+	// remove an entry from the trash for good
+	pub fn purge_entry(&self, id: &Uuid) {
+		let position =
+			self.trash.read().iter().position(|item| item.entry.id == *id);
+
+		if let Some(index) = position {
+			let mut trashed = self.trash.write().remove(index);
+			trashed.zeroize();
+		}
+	}
+
+	// remove all entries from the trash for good
+	pub fn empty_trash(&self) {
+		for trashed in &mut *self.trash.write() {
+			trashed.zeroize();
+		}
+
+		self.trash.write().clear();
+	}
+
+	// get the list of all trashed entries for the trash view
+	pub fn get_trash_list(&self) -> im::Vector<(Uuid, String, u64)> {
+		self
+			.trash
+			.read()
+			.iter()
+			.map(|item| (item.entry.id, item.entry.title.clone(), item.deleted_at))
+			.rev()
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn newer_files_report_their_version() {
+		let content =
+			format!("[db]\nversion = {}\nsomething_new = 1", DB_VERSION + 1);
+		let err = LoadError::from_parse(Db::parse(&content).unwrap_err());
+		assert!(matches!(
+			err,
+			LoadError::Version(MigrationError::NewerVersion(version))
+				if version == DB_VERSION + 1
+		));
+	}
+
+	#[test]
+	fn broken_files_are_malformed() {
+		let err = LoadError::from_parse(Db::parse("[db\nversion = 1").unwrap_err());
+		assert!(matches!(err, LoadError::Malformed(_)));
+	}
+
+	#[test]
+	fn newer_files_on_disk_are_never_overwritten() {
+		let path = temp_vault_path("newer");
+		let ours = new_vault(&path, "one");
+
+		let mut file: toml::Value =
+			toml::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+		file["db"]["version"] = toml::Value::Integer(DB_VERSION as i64 + 1);
+		let newer = toml::to_string(&file).unwrap();
+		fs::write(&path, &newer).unwrap();
+
+		ours.add(String::from("Added here"));
+		let err = ours.save().unwrap_err();
+		assert!(matches!(
+			err.downcast_ref::<MigrationError>(),
+			Some(MigrationError::NewerVersion(version)) if *version == DB_VERSION + 1
+		));
+		assert_eq!(fs::read_to_string(&path).unwrap(), newer);
+
+		let _ = fs::remove_dir_all(PathBuf::from(path).parent().unwrap());
+	}
+
+	fn field(id: Uuid, values: &[(u64, &str)]) -> DynField {
+		DynField {
+			id,
+			kind: DynFieldKind::TextLine,
+			title: String::from("Password"),
+			visible: true,
+			value: values
+				.iter()
+				.map(|(timestamp, value)| (*timestamp, value.to_string()))
+				.collect(),
+			files: Vec::new(),
+			counters: Vec::new(),
+		}
+	}
+
+	fn entry(id: Uuid, title: &str, fields: Vec<DynField>) -> DbEntry {
+		DbEntry {
+			id,
+			title: title.to_string(),
+			fields,
+			group: None,
+			tags: Vec::new(),
+			favorite: false,
+			last_used: None,
+			created: 0,
+		}
+	}
+
+	fn cypher(contents: Vec<DbEntry>) -> DbFileCypher {
+		DbFileCypher {
+			contents,
+			trash: Vec::new(),
+			groups: Vec::new(),
+		}
+	}
+
+	fn temp_vault_path(name: &str) -> String {
+		let dir = std::env::temp_dir().join(format!(
+			"vault_{}_{}",
+			name,
+			SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos()
+		));
+		fs::create_dir_all(&dir).unwrap();
+		dir.join(DB_FILE_NAME).to_string_lossy().to_string()
+	}
+
+	// argon2 with the real parameters takes ages in debug builds
+	fn cheap_kdf() -> KdfParams {
+		KdfParams {
+			m_cost: 8,
+			t_cost: 1,
+			p_cost: 1,
+			..KdfParams::default()
+		}
+	}
+
+	fn new_vault(path: &str, password: &str) -> Db {
+		let db = Db::default();
+		db.set_db_path(path.to_string());
+		db.config_db.write().kdf = cheap_kdf();
+		db.set_password(password.to_string()).unwrap();
+		db
+	}
+
+	#[test]
+	fn key_file_vaults_need_the_right_file() {
+		let path = temp_vault_path("key_file");
+		let (key_file, other_file) = ([1u8; 32], [2u8; 32]);
+		let db = Db::default();
+		db.set_db_path(path.clone());
+		db.config_db.write().kdf = cheap_kdf();
+		db.set_key_file(Some(key_file));
+		db.set_password(String::from("one")).unwrap();
+
+		let err =
+			Db::load(path.clone()).decrypt_database(String::from("one")).unwrap_err();
+		assert!(matches!(
+			err.downcast_ref::<KeyFileError>(),
+			Some(KeyFileError::Missing())
+		));
+
+		let other = Db::load(path.clone());
+		other.set_key_file(Some(other_file));
+		assert!(other.decrypt_database(String::from("one")).is_err());
+
+		let unlocked = Db::load(path.clone());
+		unlocked.set_key_file(Some(key_file));
+		unlocked.decrypt_database(String::from("one")).unwrap();
+
+		// without the key file the password alone opens the vault again
+		unlocked.change_key_file(String::from("one"), None).unwrap();
+		let reopened = Db::load(path.clone());
+		assert!(!reopened.config_db.read().key_file);
+		reopened.decrypt_database(String::from("one")).unwrap();
+
+		let _ = fs::remove_dir_all(PathBuf::from(path).parent().unwrap());
+	}
+
+	#[test]
+	fn recovery_codes_unlock_however_they_are_typed() {
+		let path = temp_vault_path("recovery");
+		let db = new_vault(&path, "one");
+		let code = db.create_recovery_code(String::from("one")).unwrap();
+
+		let typed = [
+			code.clone(),
+			code.to_lowercase(),
+			code.replace('-', " "),
+			code.replace('-', ""),
+		];
+		for code in typed {
+			let reopened = Db::load(path.clone());
+			assert_eq!(
+				reopened.decrypt_database(code).unwrap(),
+				KeySlotKind::RecoveryCode
+			);
+			assert_eq!(*reopened.hash.read(), *db.hash.read());
+		}
+
+		db.remove_recovery_code(String::from("one")).unwrap();
+		let reopened = Db::load(path.clone());
+		assert!(!reopened.has_key_slot(KeySlotKind::RecoveryCode));
+		assert!(reopened.decrypt_database(code).is_err());
+		assert_eq!(
+			reopened.decrypt_database(String::from("one")).unwrap(),
+			KeySlotKind::Password
+		);
+
+		let _ = fs::remove_dir_all(PathBuf::from(path).parent().unwrap());
+	}
+
+	#[test]
+	fn password_changes_only_rewrap_the_password_slot() {
+		let path = temp_vault_path("rewrap");
+		let db = new_vault(&path, "one");
+		let code = db.create_recovery_code(String::from("one")).unwrap();
+		let slot = |db: &Db, kind: KeySlotKind| {
+			db.config_db
+				.read()
+				.key_slots
+				.iter()
+				.find(|slot| slot.kind == kind)
+				.cloned()
+				.unwrap()
+		};
+		let key = *db.hash.read();
+		let (password_slot, recovery_slot) =
+			(slot(&db, KeySlotKind::Password), slot(&db, KeySlotKind::RecoveryCode));
+
+		db.change_password(String::from("one"), String::from("two")).unwrap();
+		assert_eq!(*db.hash.read(), key);
+		assert_ne!(slot(&db, KeySlotKind::Password), password_slot);
+		assert_eq!(slot(&db, KeySlotKind::RecoveryCode), recovery_slot);
+
+		// a forgotten password is replaced with the recovery code
+		let reopened = Db::load(path.clone());
+		reopened.decrypt_database(String::from("two")).unwrap();
+		assert_eq!(*reopened.hash.read(), key);
+		reopened.change_password(code, String::from("three")).unwrap();
+		let reopened = Db::load(path.clone());
+		assert!(reopened.decrypt_database(String::from("two")).is_err());
+		reopened.decrypt_database(String::from("three")).unwrap();
+		assert_eq!(*reopened.hash.read(), key);
+
+		let _ = fs::remove_dir_all(PathBuf::from(path).parent().unwrap());
+	}
+
+	type HeaderEdit = fn(&mut toml::Table);
+
+	// change the plain text header of the file on disk
+	fn edit_header(path: &str, edit: impl FnOnce(&mut toml::Table)) {
+		let mut file: toml::Value =
+			toml::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+		if let Some(toml::Value::Table(header)) = file.get_mut("db") {
+			edit(header);
+		}
+		fs::write(path, toml::to_string(&file).unwrap()).unwrap();
+	}
+
+	fn is_tampered(err: &anyhow::Error) -> bool {
+		matches!(
+			err.downcast_ref::<CryptError>(),
+			Some(CryptError::HeaderTampered())
+		)
+	}
+
+	#[test]
+	fn changed_headers_are_reported_as_tampering() {
+		let path = temp_vault_path("tampered");
+		new_vault(&path, "one").add(String::from("Bank"));
+		let original = fs::read_to_string(&path).unwrap();
+
+		let edits: [(&str, HeaderEdit); 4] = [
+			("encrypted", |header| {
+				header.insert(String::from("encrypted"), toml::Value::Boolean(false));
+			}),
+			("salt", |header| {
+				header.insert(
+					String::from("salt"),
+					toml::Value::String(String::from("another salt")),
+				);
+			}),
+			("kdf", |header| {
+				header["kdf"]["t_cost"] = toml::Value::Integer(2);
+			}),
+			("version", |header| {
+				header.insert(
+					String::from("version"),
+					toml::Value::Integer(AUTHENTICATED_HEADER_VERSION as i64),
+				);
+			}),
+		];
+		for (name, edit) in edits {
+			fs::write(&path, &original).unwrap();
+			edit_header(&path, edit);
+			let err = Db::load(path.clone())
+				.decrypt_database(String::from("one"))
+				.unwrap_err();
+			assert!(is_tampered(&err), "{name}: {err}");
+		}
+
+		// the unchanged file still opens
+		fs::write(&path, &original).unwrap();
+		Db::load(path.clone()).decrypt_database(String::from("one")).unwrap();
+
+		let _ = fs::remove_dir_all(PathBuf::from(path).parent().unwrap());
+	}
+
+	#[test]
+	fn unauthenticated_headers_are_bound_on_save() {
+		let path = temp_vault_path("unbound");
+		let db = new_vault(&path, "one");
+		db.add(String::from("Bank"));
+		db.save().unwrap();
+		let key = *db.hash.read();
+
+		// encrypt the contents again the way files before the header was bound
+		// to them were written
+		let file: toml::Value =
+			toml::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+		let contents = decrypt_vault(
+			file["db"]["cypher"].as_str().unwrap().to_string(),
+			key,
+			Cipher::Aes256GcmSiv,
+			&header_aad(file["db"].clone()),
+		)
+		.unwrap();
+		let cypher =
+			encrypt_vault(contents, key, Cipher::Aes256GcmSiv, "").unwrap();
+		edit_header(&path, |header| {
+			header.insert(String::from("cypher"), toml::Value::String(cypher));
+			header.insert(
+				String::from("version"),
+				toml::Value::Integer(AUTHENTICATED_HEADER_VERSION as i64 - 1),
+			);
+		});
+
+		let old = Db::load(path.clone());
+		assert!(old.cypher_header.read().is_empty());
+		old.decrypt_database(String::from("one")).unwrap();
+		assert!(old.contents.read().iter().any(|entry| entry.title == "Bank"));
+		old.save().unwrap();
+
+		let reopened = Db::load(path.clone());
+		assert_eq!(reopened.config_db.read().version, DB_VERSION);
+		reopened.decrypt_database(String::from("one")).unwrap();
+
+		// from now on the header can't be changed either
+		edit_header(&path, |header| {
+			header.insert(
+				String::from("salt"),
+				toml::Value::String(String::from("another salt")),
+			);
+		});
+		let err =
+			Db::load(path.clone()).decrypt_database(String::from("one")).unwrap_err();
+		assert!(is_tampered(&err));
+
+		let _ = fs::remove_dir_all(PathBuf::from(path).parent().unwrap());
+	}
+
+	#[test]
+	fn vaults_change_their_cipher_and_back() {
+		let path = temp_vault_path("cipher");
+		let db = new_vault(&path, "one");
+		let id = db.add(String::from("Bank"));
+		db.add_field(
+			&id,
+			DynFieldKind::TextLineSecret,
+			String::from("Password"),
+			String::from("hunter2"),
+		);
+		db.save().unwrap();
+		let contents = db.contents.read().clone();
+
+		let mut unlocked = db;
+		for cipher in [Cipher::XChaCha20Poly1305, Cipher::Aes256GcmSiv] {
+			unlocked.change_cipher(String::from("one"), cipher).unwrap();
+
+			let reopened = Db::load(path.clone());
+			assert_eq!(reopened.config_db.read().cipher, cipher);
+			assert!(reopened
+				.config_db
+				.read()
+				.key_slots
+				.iter()
+				.all(|slot| slot.cipher == cipher));
+			reopened.decrypt_database(String::from("one")).unwrap();
+			assert_eq!(*reopened.contents.read(), contents);
+			unlocked = reopened;
+		}
+
+		let _ = fs::remove_dir_all(PathBuf::from(path).parent().unwrap());
+	}
+
+	#[test]
+	fn trashed_attachments_count_towards_the_vault_limit() {
+		let path = temp_vault_path("attachments");
+		let file = PathBuf::from(&path).with_file_name("note.txt");
+		fs::write(&file, "hello").unwrap();
+		let file = file.to_string_lossy().to_string();
+		let db = Db::default();
+		let id = db.add(String::from("Bank"));
+		assert!(db.add_attachment(&id, String::from("Note"), &file).is_ok());
+
+		let mut large = field(Uuid::new_v4(), &[(0, "large.bin")]);
+		large.kind = DynFieldKind::Attachment;
+		large.files = vec!["A".repeat(MAX_ATTACHMENTS_SIZE / 3 * 4)];
+		let old_id = Uuid::new_v4();
+		db.contents.write().push(entry(old_id, "Old", vec![large]));
+		db.delete_entry(&old_id);
+
+		assert!(matches!(
+			db.add_attachment(&id, String::from("Note"), &file),
+			Err(AttachmentError::VaultFull())
+		));
+
+		let _ = fs::remove_dir_all(PathBuf::from(path).parent().unwrap());
+	}
+
+	#[test]
+	fn legacy_vaults_move_to_a_new_key_and_salt() {
+		let path = temp_vault_path("legacy");
+		let db = Db::default();
+		db.set_db_path(path.clone());
+		db.config_db.write().kdf = cheap_kdf();
+		let salt = db.config_db.read().salt.clone();
+		let legacy_key =
+			password_hash(String::from("old"), salt.clone(), &cheap_kdf(), None)
+				.unwrap();
+		*db.hash.write() = legacy_key;
+		db.save().unwrap();
+
+		let legacy = Db::load(path.clone());
+		assert!(legacy.config_db.read().key_slots.is_empty());
+		legacy.decrypt_database(String::from("old")).unwrap();
+
+		let migrated = Db::load(path.clone());
+		assert_ne!(migrated.config_db.read().salt, salt);
+		assert_eq!(migrated.config_db.read().key_slots.len(), 1);
+		assert!(migrated.decrypt_cypher(legacy_key).is_err());
+		migrated.decrypt_database(String::from("old")).unwrap();
+
+		migrated.change_password(String::from("old"), String::from("new")).unwrap();
+		let changed = Db::load(path.clone());
+		assert!(changed.decrypt_database(String::from("old")).is_err());
+		changed.decrypt_database(String::from("new")).unwrap();
+
+		let _ = fs::remove_dir_all(PathBuf::from(path).parent().unwrap());
+	}
+
+	#[test]
+	fn password_changes_elsewhere_survive_our_save() {
+		let path = temp_vault_path("header");
+		let ours = new_vault(&path, "one");
+
+		let theirs = Db::load(path.clone());
+		theirs.decrypt_database(String::from("one")).unwrap();
+		theirs.change_password(String::from("one"), String::from("two")).unwrap();
+
+		ours.add(String::from("Added here"));
+		ours.save().unwrap();
+
+		let reopened = Db::load(path.clone());
+		assert!(reopened.decrypt_database(String::from("one")).is_err());
+		reopened.decrypt_database(String::from("two")).unwrap();
+		assert!(reopened
+			.contents
+			.read()
+			.iter()
+			.any(|entry| entry.title == "Added here"));
+
+		let _ = fs::remove_dir_all(PathBuf::from(path).parent().unwrap());
+	}
+
+	#[test]
+	fn hotp_counters_follow_the_value_they_were_used_with() {
+		let (id, field_id) = (Uuid::new_v4(), Uuid::new_v4());
+		let db = Db::default();
+		db.contents.write().push(entry(
+			id,
+			"Bank",
+			vec![field(field_id, &[(1, "otpauth://hotp/Bank?secret=AAAA")])],
+		));
+		let field = DbFields::Fields(field_id);
+
+		assert!(db.get_hotp_counters(&id, &field, 0).is_empty());
+		assert_eq!(db.next_hotp_counter(&id, &field, 5), 5);
+		assert_eq!(db.next_hotp_counter(&id, &field, 5), 6);
+		assert_eq!(db.get_hotp_counters(&id, &field, 0), vec![5, 6]);
+
+		// a new secret starts over at the counter of its own link
+		db.edit_field(id, &field, String::from("otpauth://hotp/Bank?secret=BBBB"));
+		assert!(db.get_hotp_counters(&id, &field, 0).is_empty());
+		assert_eq!(db.next_hotp_counter(&id, &field, 0), 0);
+		assert_eq!(db.get_hotp_counters(&id, &field, 0), vec![0]);
+		assert_eq!(db.get_hotp_counters(&id, &field, 1), vec![5, 6]);
+		assert_eq!(
+			db.get_last_field_value(&id, &field_id),
+			"otpauth://hotp/Bank?secret=BBBB"
+		);
+	}
+
+	#[test]
+	fn attachments_stay_with_their_names() {
+		let field_id = Uuid::new_v4();
+		let attachment = |values: &[(u64, &str)], files: &[&str]| DynField {
+			kind: DynFieldKind::Attachment,
+			files: files.iter().map(|file| file.to_string()).collect(),
+			..field(field_id, values)
+		};
+		let base = attachment(&[(1, "a.txt")], &["YQ=="]);
+		let mut ours =
+			attachment(&[(1, "a.txt"), (2, "ours.txt")], &["YQ==", "b3Vy"]);
+		// added in the same second as ours
+		let theirs =
+			attachment(&[(1, "a.txt"), (2, "theirs.txt")], &["YQ==", "dGhlaXI="]);
+
+		assert!(merge_field(Some(&base), &mut ours, &theirs));
+		assert_eq!(ours.value.len(), 3);
+		for (value, file) in ours.value.iter().zip(&ours.files) {
+			let expected = match value.1.as_str() {
+				"a.txt" => "YQ==",
+				"ours.txt" => "b3Vy",
+				_ => "dGhlaXI=",
+			};
+			assert_eq!(file, expected);
+		}
+
+		let id = Uuid::new_v4();
+		let db = Db::default();
+		db.contents.write().push(entry(id, "Keys", vec![ours]));
+		let field = DbFields::Fields(field_id);
+		let newest = db.get_last_by_field(&id, &field);
+		assert_eq!(
+			db.with_attachment(&id, &field, 0, |file| file.to_string()),
+			Some(String::from(if newest == "ours.txt" {
+				"b3Vy"
+			} else {
+				"dGhlaXI="
+			}))
+		);
+		assert_eq!(db.get_attachment_size(&id, &field, 2), Some(1));
+		assert!(db.get_by_id_secure(&id).fields[0].files.is_empty());
+	}
+
+	#[test]
+	fn merge_value_takes_the_side_that_changed() {
+		let base = String::from("base");
+		let changed = String::from("changed");
+		let other = String::from("other");
+
+		assert_eq!(
+			merge_value(Some(&base), &base, &changed),
+			(changed.clone(), false)
+		);
+		assert_eq!(
+			merge_value(Some(&base), &changed, &base),
+			(changed.clone(), false)
+		);
+		assert_eq!(
+			merge_value(Some(&base), &changed, &changed),
+			(changed.clone(), false)
+		);
+		assert_eq!(
+			merge_value(Some(&base), &changed, &other),
+			(changed.clone(), true)
+		);
+		assert_eq!(merge_value(None, &changed, &other), (changed, true));
+	}
+
+	#[test]
+	fn merge_history_interleaves_by_timestamp() {
+		let ours = [(1, String::from("a")), (3, String::from("ours"))];
+		let theirs = [(1, String::from("a")), (2, String::from("theirs"))];
+		let (history, is_conflict) = merge_history(&ours, &theirs);
+		assert_eq!(
+			history,
+			vec![
+				(1, String::from("a")),
+				(2, String::from("theirs")),
+				(3, String::from("ours"))
+			]
+		);
+		assert!(is_conflict);
+
+		// only one side added something
+		let (history, is_conflict) = merge_history(&ours[..1], &theirs);
+		assert_eq!(history, theirs.to_vec());
+		assert!(!is_conflict);
+	}
+
+	#[test]
+	fn merge_tags_keeps_removals_and_additions() {
+		let base = [String::from("a"), String::from("b")];
+		let ours = [String::from("a"), String::from("c")];
+		let theirs = [String::from("b"), String::from("a"), String::from("d")];
+		assert_eq!(
+			merge_tags(Some(&base), &ours, &theirs),
+			vec![String::from("a"), String::from("c"), String::from("d")]
+		);
+	}
+
+	#[test]
+	fn merge_cypher_merges_both_sides() {
+		let (edited, added, deleted) =
+			(Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+		let field_id = Uuid::new_v4();
+
+		let base = cypher(vec![
+			entry(edited, "Bank", vec![field(field_id, &[(1, "old")])]),
+			entry(deleted, "Gone", Vec::new()),
+		]);
+		let theirs = cypher(vec![
+			entry(edited, "My Bank", vec![field(field_id, &[(1, "old")])]),
+			entry(deleted, "Gone", Vec::new()),
+			entry(added, "New", Vec::new()),
+		]);
+
+		let db = Db::default();
+		*db.contents.write() = vec![entry(
+			edited,
+			"Bank",
+			vec![field(field_id, &[(1, "old"), (2, "new")])],
+		)];
+
+		let conflicts = db.merge_cypher(Some(&base), &theirs);
+		assert!(conflicts.is_empty());
+
+		let contents = db.contents.read();
+		let ids = contents.iter().map(|entry| entry.id).collect::<Vec<Uuid>>();
+		assert_eq!(ids, vec![edited, added]);
+		assert_eq!(contents[0].title, "My Bank");
+		assert_eq!(contents[0].fields[0].value.last().unwrap().1, "new");
+	}
+
+	#[test]
+	fn merge_cypher_reports_conflicts() {
+		let (id, field_id) = (Uuid::new_v4(), Uuid::new_v4());
+		let base =
+			cypher(vec![entry(id, "Bank", vec![field(field_id, &[(1, "a")])])]);
+		let theirs = cypher(vec![entry(
+			id,
+			"Their Bank",
+			vec![field(field_id, &[(1, "a"), (3, "theirs")])],
+		)]);
+
+		let db = Db::default();
+		*db.contents.write() = vec![entry(
+			id,
+			"Our Bank",
+			vec![field(field_id, &[(1, "a"), (2, "ours")])],
+		)];
+
+		let conflicts = db.merge_cypher(Some(&base), &theirs);
+		let fields = conflicts
+			.iter()
+			.map(|conflict| conflict.field.as_str())
+			.collect::<Vec<&str>>();
+		assert_eq!(fields, vec!["Title", "Password"]);
+
+		// ours wins the title, the newest value wins the field
+		let contents = db.contents.read();
+		assert_eq!(contents[0].title, "Our Bank");
+		assert_eq!(contents[0].fields[0].value.len(), 3);
+		assert_eq!(contents[0].fields[0].value.last().unwrap().1, "theirs");
+	}
+
+	#[test]
+	fn field_kinds_accept_what_they_can_store() {
+		let valid = [
+			(DynFieldKind::Email, "jane@example.com"),
+			(DynFieldKind::Email, " jane.doe+vault@mail.example.org "),
+			(DynFieldKind::Phone, "+41 (0)44 123 45-67"),
+			(DynFieldKind::Phone, "030/123.456"),
+			(DynFieldKind::Pin, "0042"),
+			(DynFieldKind::Date, "2024-02-29"),
+			(DynFieldKind::Number, "-12.5"),
+			(DynFieldKind::Number, "1 000 000"),
+		];
+		for (kind, value) in valid {
+			assert!(kind.validate(value).is_ok(), "{kind:?} rejected {value:?}");
+		}
+
+		// an empty field is always fine
+		for kind in [
+			DynFieldKind::Email,
+			DynFieldKind::Phone,
+			DynFieldKind::Pin,
+			DynFieldKind::Date,
+			DynFieldKind::Number,
+		] {
+			assert!(kind.validate("  ").is_ok());
+		}
+	}
+
+	#[test]
+	fn field_kinds_reject_what_they_cant_store() {
+		let invalid = [
+			(DynFieldKind::Email, "jane.example.com"),
+			(DynFieldKind::Email, "@example.com"),
+			(DynFieldKind::Email, "jane@example"),
+			(DynFieldKind::Email, "jane@@example.com"),
+			(DynFieldKind::Email, "jane doe@example.com"),
+			(DynFieldKind::Phone, "12"),
+			(DynFieldKind::Phone, "044 123 45 67 ext"),
+			(DynFieldKind::Phone, "044 +123"),
+			(DynFieldKind::Pin, "12a4"),
+			(DynFieldKind::Pin, "-1234"),
+			(DynFieldKind::Date, "2023-02-29"),
+			(DynFieldKind::Date, "31.12.2024"),
+			(DynFieldKind::Number, "twelve"),
+			(DynFieldKind::Number, "inf"),
+		];
+		for (kind, value) in invalid {
+			let err = kind.validate(value).unwrap_err();
+			assert!(
+				matches!(
+					(&kind, &err),
+					(DynFieldKind::Email, FieldError::Email())
+						| (DynFieldKind::Phone, FieldError::Phone())
+						| (DynFieldKind::Pin, FieldError::Pin())
+						| (DynFieldKind::Date, FieldError::Date())
+						| (DynFieldKind::Number, FieldError::Number())
+				),
+				"{kind:?} reported {err:?} for {value:?}"
+			);
+		}
+	}
+
+	fn grouped_entry(db: &Db, title: &str, group: Option<Uuid>) -> Uuid {
+		let id = db.add(title.to_string());
+		db.move_entry(&id, group);
+		id
+	}
+
+	fn sidebar_rows(
+		db: &Db,
+		list: &im::Vector<(Uuid, String, usize)>,
+		collapsed: &[Uuid],
+		is_search: bool,
+	) -> Vec<SidebarItem> {
+		db.get_sidebar_tree(list, collapsed, is_search, SidebarSort::Title)
+			.into_iter()
+			.map(|(_, row)| row)
+			.collect()
+	}
+
+	#[test]
+	fn groups_are_shown_as_a_tree() {
+		let db = Db::default();
+		// without the example entry
+		db.contents.write().clear();
+		let work = db.add_group(String::from("Work"), None);
+		let clients = db.add_group(String::from("Clients"), Some(work));
+		let banking = db.add_group(String::from("banking"), None);
+		let notes = grouped_entry(&db, "Notes", None);
+		let email = grouped_entry(&db, "Email", Some(work));
+		let acme = grouped_entry(&db, "Acme", Some(clients));
+		let bank = grouped_entry(&db, "Bank", Some(banking));
+		let list = db.get_sidebar_list();
+
+		// groups come first and are sorted ignoring case, entries sit one level
+		// deeper than their group
+		assert_eq!(
+			sidebar_rows(&db, &list, &[], false),
+			vec![
+				SidebarItem::Group(banking, String::from("banking"), 0, false),
+				SidebarItem::Entry(bank, String::from("Bank"), 1),
+				SidebarItem::Group(work, String::from("Work"), 0, false),
+				SidebarItem::Group(clients, String::from("Clients"), 1, false),
+				SidebarItem::Entry(acme, String::from("Acme"), 2),
+				SidebarItem::Entry(email, String::from("Email"), 1),
+				SidebarItem::Entry(notes, String::from("Notes"), 0),
+			]
+		);
+
+		assert_eq!(
+			sidebar_rows(&db, &list, &[work], false),
+			vec![
+				SidebarItem::Group(banking, String::from("banking"), 0, false),
+				SidebarItem::Entry(bank, String::from("Bank"), 1),
+				SidebarItem::Group(work, String::from("Work"), 0, true),
+				SidebarItem::Entry(notes, String::from("Notes"), 0),
+			]
+		);
+
+		// a search opens collapsed groups and leaves out the ones without hits
+		let found = db.search("acme", None);
+		assert_eq!(
+			sidebar_rows(&db, &found, &[work], true),
+			vec![
+				SidebarItem::Group(work, String::from("Work"), 0, false),
+				SidebarItem::Group(clients, String::from("Clients"), 1, false),
+				SidebarItem::Entry(acme, String::from("Acme"), 2),
+			]
+		);
+	}
+
+	#[test]
+	fn deleted_groups_hand_their_contents_to_the_parent() {
+		let db = Db::default();
+		let work = db.add_group(String::from("Work"), None);
+		let clients = db.add_group(String::from("Clients"), Some(work));
+		let archive = db.add_group(String::from("Archive"), Some(clients));
+		let email = grouped_entry(&db, "Email", Some(work));
+		let acme = grouped_entry(&db, "Acme", Some(clients));
+		let old = grouped_entry(&db, "Old", Some(clients));
+		db.delete_entry(&old);
+
+		db.delete_group(&clients);
+		assert_eq!(db.get_entry_group(&acme), Some(work));
+		assert_eq!(db.trash.read()[0].entry.group, Some(work));
+		let groups = db.groups.read().clone();
+		assert_eq!(groups.len(), 2);
+		assert!(groups
+			.iter()
+			.any(|group| group.id == archive && group.parent == Some(work)));
+
+		db.delete_group(&work);
+		assert_eq!(db.get_entry_group(&email), None);
+		assert_eq!(db.get_entry_group(&acme), None);
+		assert_eq!(db.trash.read()[0].entry.group, None);
+	}
+
+	#[test]
+	fn exported_groups_include_their_subgroups() {
+		let path = temp_vault_path("export_group");
+		let db = new_vault(&path, "one");
+		let work = db.add_group(String::from("Work"), None);
+		let clients = db.add_group(String::from("Clients"), Some(work));
+		let private = db.add_group(String::from("Private"), None);
+		grouped_entry(&db, "Email", Some(work));
+		grouped_entry(&db, "Acme", Some(clients));
+		grouped_entry(&db, "Diary", Some(private));
+		grouped_entry(&db, "Notes", None);
+
+		let export_path = PathBuf::from(&path).with_file_name("export.toml");
+		fs::write(&export_path, db.export_group(&clients).unwrap()).unwrap();
+		let exported = Db::load(export_path.to_string_lossy().to_string());
+		exported.decrypt_database(String::from("one")).unwrap();
+		let titles = |db: &Db| {
+			let mut titles = db
+				.contents
+				.read()
+				.iter()
+				.map(|entry| entry.title.clone())
+				.collect::<Vec<String>>();
+			titles.sort();
+			titles
+		};
+		assert_eq!(titles(&exported), vec!["Acme"]);
+
+		fs::write(&export_path, db.export_group(&work).unwrap()).unwrap();
+		let exported = Db::load(export_path.to_string_lossy().to_string());
+		exported.decrypt_database(String::from("one")).unwrap();
+		assert_eq!(titles(&exported), vec!["Acme", "Email"]);
+		// the exported group becomes the top of the new vault
+		let mut groups = exported.groups.read().clone();
+		groups.sort_by_key(|group| group.title.clone());
+		assert_eq!(
+			groups.iter().map(|group| (group.id, group.parent)).collect::<Vec<_>>(),
+			vec![(clients, Some(work)), (work, None)]
+		);
+
+		let _ = fs::remove_dir_all(PathBuf::from(path).parent().unwrap());
+	}
+
+	fn tagged_entry(db: &Db, title: &str, tags: &[&str]) -> Uuid {
+		let id = db.add(title.to_string());
+		for tag in tags {
+			db.add_tag(&id, tag);
+		}
+		id
+	}
+
+	fn search_titles(db: &Db, needle: &str, group: Option<Uuid>) -> Vec<String> {
+		let mut titles = db
+			.search(needle, group)
+			.into_iter()
+			.map(|(_, title, _)| title)
+			.collect::<Vec<String>>();
+		titles.sort();
+		titles
+	}
+
+	#[test]
+	fn tags_are_normalized() {
+		assert_eq!(normalize_tag("finance"), "finance");
+		assert_eq!(normalize_tag("  Tax  Return 2024 "), "tax-return-2024");
+		assert_eq!(normalize_tag(" \t "), "");
+	}
+
+	#[test]
+	fn tags_are_only_added_once() {
+		let db = Db::default();
+		let id = tagged_entry(&db, "Bank", &["Tax Return", "tax  return", "  "]);
+		assert_eq!(db.get_tags(&id), vec!["tax-return"]);
+
+		tagged_entry(&db, "Accountant", &["tax return", "work"]);
+		assert_eq!(
+			db.get_all_tags(),
+			vec![(String::from("tax-return"), 2), (String::from("work"), 1)]
+		);
+	}
+
+	#[test]
+	fn tag_searches_need_every_tag_and_the_text() {
+		let db = Db::default();
+		db.contents.write().clear();
+		tagged_entry(&db, "Bank", &["finance", "personal"]);
+		tagged_entry(&db, "Broker", &["finance"]);
+		tagged_entry(&db, "Bakery", &["personal"]);
+
+		assert_eq!(
+			search_titles(&db, "tag:finance tag:personal", None),
+			vec!["Bank"]
+		);
+		assert_eq!(search_titles(&db, "tag:finance", None), vec!["Bank", "Broker"]);
+		assert_eq!(search_titles(&db, "br tag:Finance", None), vec!["Broker"]);
+		assert_eq!(
+			search_titles(&db, "tag:personal ba", None),
+			vec!["Bakery", "Bank"]
+		);
+		assert!(search_titles(&db, "tag:finance tag:unknown", None).is_empty());
+	}
+
+	#[test]
+	fn searches_stay_inside_their_group() {
+		let db = Db::default();
+		let work = db.add_group(String::from("Work"), None);
+		let clients = db.add_group(String::from("Clients"), Some(work));
+		let private = db.add_group(String::from("Private"), None);
+		grouped_entry(&db, "Acme", Some(clients));
+		grouped_entry(&db, "Acme Mail", Some(work));
+		grouped_entry(&db, "Acme Shop", Some(private));
+		grouped_entry(&db, "Acme Notes", None);
+
+		assert_eq!(
+			search_titles(&db, "acme", Some(work)),
+			vec!["Acme", "Acme Mail"]
+		);
+		assert_eq!(search_titles(&db, "acme", Some(clients)), vec!["Acme"]);
+		assert_eq!(search_titles(&db, "acme", None).len(), 4);
+	}
 }
@@ -1,38 +1,218 @@
 use aes_gcm_siv::{
 	aead,
-	aead::{Aead, AeadCore, KeyInit, OsRng},
-	Aes256GcmSiv, Nonce,
+	aead::{
+		generic_array::typenum::Unsigned, Aead, AeadCore, KeyInit, Nonce, OsRng,
+		Payload,
+	},
+	Aes256GcmSiv,
 };
-use argon2::Argon2;
+use argon2::{Algorithm, Argon2, Params, Version};
 use base64::{engine::general_purpose, Engine as _};
-use std::str::from_utf8;
+use chacha20poly1305::XChaCha20Poly1305;
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+	fs,
+	str::from_utf8,
+	time::{Duration, Instant},
+};
+use zeroize::Zeroize;
+
+// the values argon2 0.5 used by default, vaults from before we stored the
+// parameters in the header were all hashed with these
+const LEGACY_M_COST: u32 = 19 * 1024;
+const LEGACY_T_COST: u32 = 2;
+const LEGACY_P_COST: u32 = 1;
+
+const CALIBRATION_M_COST: u32 = 64 * 1024;
+const CALIBRATION_MAX_T_COST: u32 = 100;
+
+const KEY_FILE_SIZE: usize = 64;
+
+// no 0/O or 1/I so the code can be read back from paper without mistakes
+const RECOVERY_CODE_CHARSET: &[u8; 32] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const RECOVERY_CODE_LENGTH: usize = 32;
+const RECOVERY_CODE_GROUP: usize = 4;
 
 #[derive(thiserror::Error, Debug)]
 pub enum CryptError {
 	#[error("Failed to create hash from password.")]
 	Hash(#[from] argon2::Error),
-	#[error("Failed to decode base64")]
+	#[error(
+		"The vault file is damaged, its encrypted data is not valid base64."
+	)]
 	Base64Decode(#[from] base64::DecodeError),
+	#[error("The vault file is damaged, its encrypted data was cut short.")]
+	Truncated(),
 	#[error("Failed to create crypto cipher, likely invalid length nonce")]
 	Cipher(#[from] crypto_common::InvalidLength),
 	#[error("Incorrect password.")]
 	Decryption(#[from] aead::Error),
+	#[error(
+		"The vault file has been tampered with or damaged, its settings don't match the encrypted data."
+	)]
+	HeaderTampered(),
 	#[error("Failed to decode utf8.")]
 	UTF8(#[from] std::str::Utf8Error),
 }
 
-pub fn decrypt_vault(
-	payload: String,
+#[derive(thiserror::Error, Debug)]
+pub enum KeyFileError {
+	#[error("This vault needs a key file to unlock.")]
+	Missing(),
+	#[error("Failed to read or write the key file.")]
+	Io(#[from] std::io::Error),
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum Cipher {
+	Aes256GcmSiv,
+	XChaCha20Poly1305,
+}
+
+impl std::fmt::Display for Cipher {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match *self {
+			Cipher::Aes256GcmSiv => write!(f, "AES-256-GCM-SIV"),
+			Cipher::XChaCha20Poly1305 => write!(f, "XChaCha20-Poly1305"),
+		}
+	}
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum KdfAlgorithm {
+	Argon2d,
+	Argon2i,
+	Argon2id,
+}
+
+impl std::fmt::Display for KdfAlgorithm {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match *self {
+			KdfAlgorithm::Argon2d => write!(f, "Argon2d"),
+			KdfAlgorithm::Argon2i => write!(f, "Argon2i"),
+			KdfAlgorithm::Argon2id => write!(f, "Argon2id"),
+		}
+	}
+}
+
+impl From<KdfAlgorithm> for Algorithm {
+	fn from(algorithm: KdfAlgorithm) -> Self {
+		match algorithm {
+			KdfAlgorithm::Argon2d => Algorithm::Argon2d,
+			KdfAlgorithm::Argon2i => Algorithm::Argon2i,
+			KdfAlgorithm::Argon2id => Algorithm::Argon2id,
+		}
+	}
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub struct KdfParams {
+	pub algorithm: KdfAlgorithm,
+	pub version: u32,
+	pub m_cost: u32,
+	pub t_cost: u32,
+	pub p_cost: u32,
+}
+
+impl Default for KdfParams {
+	fn default() -> Self {
+		KdfParams {
+			algorithm: KdfAlgorithm::Argon2id,
+			version: Version::V0x13 as u32,
+			m_cost: LEGACY_M_COST,
+			t_cost: LEGACY_T_COST,
+			p_cost: LEGACY_P_COST,
+		}
+	}
+}
+
+// the nonce is stored in front of the cyphertext, its size depends on the cipher
+fn decrypt_with<C: Aead + KeyInit>(
+	payload: &[u8],
 	hash: [u8; 32],
-) -> Result<String, CryptError> {
-	let cipher = Aes256GcmSiv::new_from_slice(hash.as_slice())?;
+	aad: &[u8],
+) -> Result<Vec<u8>, CryptError> {
+	let cipher = C::new_from_slice(hash.as_slice())?;
+
+	// anything shorter can't even hold the nonce and the authentication tag
+	if payload.len()
+		< <C as AeadCore>::NonceSize::USIZE + <C as AeadCore>::TagSize::USIZE
+	{
+		return Err(CryptError::Truncated());
+	}
+
+	let (nonce_bytes, cyphertext) =
+		payload.split_at(<C as AeadCore>::NonceSize::USIZE);
+	let nonce = Nonce::<C>::from_slice(nonce_bytes);
+
+	Ok(cipher.decrypt(
+		nonce,
+		Payload {
+			msg: cyphertext,
+			aad,
+		},
+	)?)
+}
+
+fn encrypt_with<C: Aead + KeyInit>(
+	payload: &[u8],
+	hash: [u8; 32],
+	aad: &[u8],
+) -> Result<Vec<u8>, CryptError> {
+	let cipher = C::new_from_slice(hash.as_slice())?;
+	let nonce = C::generate_nonce(&mut OsRng);
+
+	let ciphertext = cipher.encrypt(&nonce, Payload { msg: payload, aad })?;
+	Ok([nonce.as_slice(), ciphertext.as_slice()].concat())
+}
 
+fn decrypt(
+	payload: String,
+	hash: [u8; 32],
+	cipher: Cipher,
+	aad: &[u8],
+) -> Result<Vec<u8>, CryptError> {
 	let cyphertext_from_string =
 		general_purpose::STANDARD_NO_PAD.decode(payload)?;
-	let (nonce_bytes, cyphertext) = cyphertext_from_string.split_at(12);
-	let nonce = Nonce::from_slice(nonce_bytes);
 
-	let plaintext = cipher.decrypt(nonce, cyphertext)?;
+	match cipher {
+		Cipher::Aes256GcmSiv => {
+			decrypt_with::<Aes256GcmSiv>(&cyphertext_from_string, hash, aad)
+		},
+		Cipher::XChaCha20Poly1305 => {
+			decrypt_with::<XChaCha20Poly1305>(&cyphertext_from_string, hash, aad)
+		},
+	}
+}
+
+fn encrypt(
+	payload: &[u8],
+	hash: [u8; 32],
+	cipher: Cipher,
+	aad: &[u8],
+) -> Result<String, CryptError> {
+	let payload = match cipher {
+		Cipher::Aes256GcmSiv => encrypt_with::<Aes256GcmSiv>(payload, hash, aad)?,
+		Cipher::XChaCha20Poly1305 => {
+			encrypt_with::<XChaCha20Poly1305>(payload, hash, aad)?
+		},
+	};
+	let b64_payload =
+		general_purpose::STANDARD_NO_PAD.encode(payload).to_string();
+	Ok(b64_payload)
+}
+
+// the header is authenticated along with the contents so it can't be changed
+// without the key, vaults from before that have an empty header
+pub fn decrypt_vault(
+	payload: String,
+	hash: [u8; 32],
+	cipher: Cipher,
+	header: &str,
+) -> Result<String, CryptError> {
+	let plaintext = decrypt(payload, hash, cipher, header.as_bytes())?;
 	let utf8_string = from_utf8(plaintext.as_slice())?.to_string();
 	Ok(utf8_string)
 }
@@ -40,26 +220,276 @@ pub fn decrypt_vault(
 pub fn encrypt_vault(
 	payload: String,
 	hash: [u8; 32],
+	cipher: Cipher,
+	header: &str,
 ) -> Result<String, CryptError> {
-	let cipher = Aes256GcmSiv::new_from_slice(hash.as_slice())?;
-	let nonce = Aes256GcmSiv::generate_nonce(&mut OsRng);
+	encrypt(payload.as_bytes(), hash, cipher, header.as_bytes())
+}
 
-	let ciphertext = cipher.encrypt(&nonce, payload.as_bytes().as_ref())?;
-	let payload = [&nonce, ciphertext.as_slice()].concat();
-	let b64_payload =
-		general_purpose::STANDARD_NO_PAD.encode(payload).to_string();
-	Ok(b64_payload)
+// the random key the vault is encrypted with, never derived from a password
+pub fn generate_key() -> [u8; 32] {
+	let mut key = [0u8; 32];
+	OsRng.fill_bytes(&mut key);
+	key
+}
+
+// encrypt the vault key with the key of a key slot
+pub fn wrap_key(
+	key: &[u8; 32],
+	hash: [u8; 32],
+	cipher: Cipher,
+) -> Result<String, CryptError> {
+	encrypt(key, hash, cipher, &[])
+}
+
+pub fn unwrap_key(
+	wrapped: String,
+	hash: [u8; 32],
+	cipher: Cipher,
+) -> Result<[u8; 32], CryptError> {
+	let mut plaintext = decrypt(wrapped, hash, cipher, &[])?;
+	let key = <[u8; 32]>::try_from(plaintext.as_slice())
+		.map_err(|_| CryptError::Decryption(aead::Error));
+	plaintext.zeroize();
+	key
+}
+
+// formatted as groups like ABCD-EFGH-... so it's easy to write down
+pub fn generate_recovery_code() -> String {
+	let code = (0..RECOVERY_CODE_LENGTH)
+		.map(|_| {
+			RECOVERY_CODE_CHARSET[OsRng.gen_range(0..RECOVERY_CODE_CHARSET.len())]
+				as char
+		})
+		.collect::<Vec<char>>();
+
+	code
+		.chunks(RECOVERY_CODE_GROUP)
+		.map(|group| group.iter().collect::<String>())
+		.collect::<Vec<String>>()
+		.join("-")
+}
+
+// None when the input can't be a recovery code, dashes, spaces and case are
+// ignored so it can be typed in however it was written down
+pub fn normalize_recovery_code(input: &str) -> Option<String> {
+	let code = input
+		.chars()
+		.filter(|c| !c.is_whitespace() && *c != '-')
+		.map(|c| c.to_ascii_uppercase())
+		.collect::<String>();
+
+	if code.len() == RECOVERY_CODE_LENGTH
+		&& code.bytes().all(|c| RECOVERY_CODE_CHARSET.contains(&c))
+	{
+		Some(code)
+	} else {
+		None
+	}
 }
 
 pub fn password_hash(
 	password: String,
 	salt: String,
+	kdf: &KdfParams,
+	key_file: Option<&[u8; 32]>,
 ) -> Result<[u8; 32], CryptError> {
+	// the key file is appended to the password so we need both to get the key
+	let mut secret = password.into_bytes();
+	if let Some(key_file) = key_file {
+		secret.extend_from_slice(key_file);
+	}
+
 	let mut okm = [0u8; 32];
-	Argon2::default().hash_password_into(
-		password.as_bytes(),
-		salt.as_bytes(),
-		&mut okm,
-	)?;
+	let result = Argon2::new(
+		kdf.algorithm.into(),
+		Version::try_from(kdf.version)?,
+		Params::new(kdf.m_cost, kdf.t_cost, kdf.p_cost, Some(okm.len()))?,
+	)
+	.hash_password_into(&secret, salt.as_bytes(), &mut okm);
+	secret.zeroize();
+	result?;
 	Ok(okm)
 }
+
+// any file can be a key file, we only ever use its hash
+pub fn read_key_file(path: &str) -> Result<[u8; 32], KeyFileError> {
+	let mut contents = fs::read(path)?;
+	let hash = Sha256::digest(&contents).into();
+	contents.zeroize();
+	Ok(hash)
+}
+
+pub fn create_key_file(path: &str) -> Result<[u8; 32], KeyFileError> {
+	let mut contents = [0u8; KEY_FILE_SIZE];
+	OsRng.fill_bytes(&mut contents);
+	fs::write(path, contents)?;
+	let hash = Sha256::digest(contents).into();
+	contents.zeroize();
+	Ok(hash)
+}
+
+fn time_kdf(kdf: &KdfParams) -> Result<Duration, CryptError> {
+	let start = Instant::now();
+	password_hash(
+		String::from("calibration"),
+		String::from("calibration salt"),
+		kdf,
+		None,
+	)?;
+	Ok(start.elapsed())
+}
+
+// find argon2 parameters that take about the target time on this machine
+pub fn calibrate_kdf(target: Duration) -> Result<KdfParams, CryptError> {
+	let mut kdf = KdfParams {
+		m_cost: CALIBRATION_M_COST,
+		t_cost: 1,
+		..KdfParams::default()
+	};
+
+	// slow machines get less memory until a single pass fits into the target
+	let mut pass_time = time_kdf(&kdf)?;
+	while pass_time > target && kdf.m_cost / 2 >= LEGACY_M_COST {
+		kdf.m_cost /= 2;
+		pass_time = time_kdf(&kdf)?;
+	}
+
+	// every iteration is another pass over the memory
+	kdf.t_cost = ((target.as_secs_f64() / pass_time.as_secs_f64()).round()
+		as u32)
+		.clamp(1, CALIBRATION_MAX_T_COST);
+
+	Ok(kdf)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// argon2 with the real parameters takes ages in debug builds
+	fn cheap_kdf() -> KdfParams {
+		KdfParams {
+			m_cost: 8,
+			t_cost: 1,
+			p_cost: 1,
+			..KdfParams::default()
+		}
+	}
+
+	fn hash(password: &str, key_file: Option<&[u8; 32]>) -> [u8; 32] {
+		password_hash(
+			String::from(password),
+			String::from("a salt of some length"),
+			&cheap_kdf(),
+			key_file,
+		)
+		.unwrap()
+	}
+
+	#[test]
+	fn key_files_are_read_back_to_the_same_key() {
+		let dir = std::env::temp_dir()
+			.join(format!("vault_key_file_{}", OsRng.gen::<u64>()));
+		fs::create_dir_all(&dir).unwrap();
+		let path = dir.join("vault.key").to_string_lossy().to_string();
+
+		let created = create_key_file(&path).unwrap();
+		assert_eq!(fs::read(&path).unwrap().len(), KEY_FILE_SIZE);
+		assert_eq!(read_key_file(&path).unwrap(), created);
+		assert_ne!(create_key_file(&path).unwrap(), created);
+		assert!(matches!(
+			read_key_file(&dir.join("missing.key").to_string_lossy()),
+			Err(KeyFileError::Io(_))
+		));
+
+		let _ = fs::remove_dir_all(dir);
+	}
+
+	#[test]
+	fn key_files_change_the_derived_key() {
+		let (one, two) = ([1u8; 32], [2u8; 32]);
+		assert_eq!(hash("password", Some(&one)), hash("password", Some(&one)));
+		assert_ne!(hash("password", Some(&one)), hash("password", None));
+		assert_ne!(hash("password", Some(&one)), hash("password", Some(&two)));
+	}
+
+	#[test]
+	fn recovery_codes_are_normalized() {
+		let code = generate_recovery_code();
+		assert_eq!(
+			code.len(),
+			RECOVERY_CODE_LENGTH + RECOVERY_CODE_LENGTH / RECOVERY_CODE_GROUP - 1
+		);
+		let normalized = normalize_recovery_code(&code).unwrap();
+		assert_eq!(normalized, code.replace('-', ""));
+		assert_eq!(
+			normalize_recovery_code(&code.to_lowercase()).as_ref(),
+			Some(&normalized)
+		);
+		assert_eq!(
+			normalize_recovery_code(&code.replace('-', "  ")).as_ref(),
+			Some(&normalized)
+		);
+
+		// too short, or with letters that are left out because they look alike
+		assert_eq!(normalize_recovery_code("ABCD-EFGH"), None);
+		assert_eq!(
+			normalize_recovery_code(&format!("0{}", &normalized[1..])),
+			None
+		);
+		assert_eq!(normalize_recovery_code("my password"), None);
+	}
+
+	#[test]
+	fn wrapped_keys_only_unwrap_with_their_hash() {
+		let key = generate_key();
+		for cipher in [Cipher::Aes256GcmSiv, Cipher::XChaCha20Poly1305] {
+			let wrapped = wrap_key(&key, hash("one", None), cipher).unwrap();
+			assert_eq!(
+				unwrap_key(wrapped.clone(), hash("one", None), cipher).unwrap(),
+				key
+			);
+			assert!(matches!(
+				unwrap_key(wrapped, hash("two", None), cipher),
+				Err(CryptError::Decryption(_))
+			));
+		}
+	}
+
+	#[test]
+	fn headers_are_authenticated_with_the_contents() {
+		let key = generate_key();
+		for cipher in [Cipher::Aes256GcmSiv, Cipher::XChaCha20Poly1305] {
+			let cypher =
+				encrypt_vault(String::from("contents"), key, cipher, "version = 4")
+					.unwrap();
+			assert_eq!(
+				decrypt_vault(cypher.clone(), key, cipher, "version = 4").unwrap(),
+				"contents"
+			);
+			assert!(matches!(
+				decrypt_vault(cypher, key, cipher, "version = 5"),
+				Err(CryptError::Decryption(_))
+			));
+		}
+	}
+
+	#[test]
+	fn contents_only_decrypt_with_their_cipher() {
+		let key = generate_key();
+		let ciphers = [
+			(Cipher::Aes256GcmSiv, Cipher::XChaCha20Poly1305),
+			(Cipher::XChaCha20Poly1305, Cipher::Aes256GcmSiv),
+		];
+		for (cipher, other) in ciphers {
+			let cypher =
+				encrypt_vault(String::from("contents"), key, cipher, "").unwrap();
+			assert_eq!(
+				decrypt_vault(cypher.clone(), key, cipher, "").unwrap(),
+				"contents"
+			);
+			assert!(decrypt_vault(cypher, key, other, "").is_err());
+		}
+	}
+}
@@ -68,6 +68,7 @@ impl Environment {
 			db.set_db_path(config.general.read().db_path.clone());
 			db
 		};
+		db.set_backup_count(config.general.read().backup_count);
 
 		Environment {
 			config: Arc::new(config),
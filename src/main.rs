@@ -1,6 +1,7 @@
 // #![windows_subsystem = "windows"]
 
-use std::time::Duration;
+use chrono::{DateTime, Local, Utc};
+use std::time::{Duration, Instant};
 use zeroize::Zeroize;
 
 use floem::{
@@ -18,14 +19,19 @@ use floem::{
 	Application, IntoView, View,
 };
 
+mod card;
 pub mod config;
 pub mod db;
 mod encryption;
 mod env;
+mod migrations;
+mod otp;
 mod password_gen;
+mod storage;
 
 mod ui {
 	pub mod app_view;
+	pub mod clipboard;
 	pub mod colors;
 	pub mod keyboard;
 	pub mod details {
@@ -35,6 +41,7 @@ mod ui {
 		pub mod hidden_fields;
 		pub mod list_item;
 		pub mod new_field;
+		pub mod tag_editor;
 	}
 	pub mod history_view;
 	pub mod import {
@@ -50,10 +57,13 @@ mod ui {
 		pub mod settings_view;
 		pub mod shortcut;
 	}
+	pub mod sidebar_group;
+	pub mod trash_view;
 	pub mod window_management;
 	pub mod primitives {
 		pub mod button;
 		pub mod checkbox;
+		pub mod date_picker;
 		pub mod debounce;
 		pub mod file_input;
 		pub mod input_button_field;
@@ -70,9 +80,12 @@ mod ui {
 }
 
 use crate::{
+	db::KeySlotKind,
+	encryption::CryptError,
 	env::Environment,
 	ui::{
 		app_view::app_view,
+		clipboard::clear_clipboard,
 		keyboard::{
 			keycode_to_key, modifiersstate_to_keymodifier, Key, KeyModifier,
 		},
@@ -90,14 +103,56 @@ use crate::{
 pub const DEFAULT_DEBUG_PASSWORD: &str = "p";
 
 pub type TimeoutQueId = RwSignal<u8>;
+pub type BackupList = RwSignal<Vec<(String, u64)>>;
+
+// how long before locking we warn that the vault is about to lock
+const LOCK_WARNING: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy)]
+pub struct ActivitySignals {
+	pub last_activity: RwSignal<Instant>,
+	pub unlocked_at: RwSignal<Instant>,
+	pub warning_toast: RwSignal<Option<u8>>,
+}
+
+impl Default for ActivitySignals {
+	fn default() -> Self {
+		Self {
+			last_activity: create_rw_signal(Instant::now()),
+			unlocked_at: create_rw_signal(Instant::now()),
+			warning_toast: create_rw_signal(None),
+		}
+	}
+}
+
+impl ActivitySignals {
+	pub fn start(self) {
+		self.unlocked_at.set(Instant::now());
+		self.last_activity.set(Instant::now());
+		self.warning_toast.set(None);
+	}
+
+	pub fn record(self) {
+		self.last_activity.set(Instant::now());
+	}
+
+	fn hide_warning(self, toast_signals: ToastSignals) {
+		if let Some(id) = self.warning_toast.get() {
+			toast_signals.remove(id);
+			self.warning_toast.set(None);
+		}
+	}
+}
 
 pub fn create_lock_timeout() {
 	let env = use_context::<Environment>().expect("No env context provider");
 	let que = use_context::<Que>().expect("No que context provider");
 	let timeout_que_id =
 		use_context::<TimeoutQueId>().expect("No timeout_que_id context provider");
-
-	let timeout = env.config.general.read().db_timeout;
+	let activity =
+		use_context::<ActivitySignals>().expect("No activity context provider");
+	let toast_signals =
+		use_context::<ToastSignals>().expect("No toast_signals context provider");
 
 	let mut id = *que.lock.get().last().unwrap_or(&timeout_que_id.get());
 	if id == 255 {
@@ -108,12 +163,71 @@ pub fn create_lock_timeout() {
 	que.lock.update(|item| item.push(id));
 	timeout_que_id.set(id);
 
-	exec_after(Duration::from_secs_f32(timeout), move |_| {
-		if que.lock.get().contains(&id) {
-			que.lock.update(|item| item.retain(|ids| *ids != id));
+	check_lock_timeout(id, env, que, activity, toast_signals);
+}
+
+// the idle timeout counts from the last key or pointer event in any of our
+// windows, the max session length from the unlock and can't be extended
+fn check_lock_timeout(
+	id: u8,
+	env: Environment,
+	que: Que,
+	activity: ActivitySignals,
+	toast_signals: ToastSignals,
+) {
+	if !que.lock.get().contains(&id) {
+		return;
+	}
+
+	let idle_at = activity.last_activity.get()
+		+ Duration::from_secs_f32(env.config.general.read().db_timeout);
+	let max_session = env.config.general.read().max_session;
+	let (lock_at, is_idle) = match max_session
+		.map(|max| activity.unlocked_at.get() + Duration::from_secs_f32(max))
+	{
+		Some(session_at) if session_at < idle_at => (session_at, false),
+		_ => (idle_at, true),
+	};
+
+	let now = Instant::now();
+	if lock_at <= now {
+		que.lock.update(|item| item.retain(|ids| *ids != id));
+		activity.hide_warning(toast_signals);
 
-			lock_app();
+		lock_app();
+		return;
+	}
+
+	let remaining = lock_at - now;
+	let next_check = if remaining > LOCK_WARNING {
+		// there was activity since the warning went up
+		activity.hide_warning(toast_signals);
+		remaining - LOCK_WARNING
+	} else {
+		let text =
+			format!("The vault locks in {} seconds.", remaining.as_secs_f32().ceil());
+		match activity.warning_toast.get() {
+			Some(toast) => toast_signals.set_text(toast, text),
+			None => {
+				let toast = if is_idle {
+					toast_signals.add_with_action(
+						text,
+						remaining,
+						"Stay unlocked",
+						move || activity.record(),
+					)
+				} else {
+					toast_signals.add_timed(text, remaining)
+				};
+				activity.warning_toast.set(Some(toast));
+			},
 		}
+		// tick every second so the countdown stays current
+		remaining.min(Duration::from_secs(1))
+	};
+
+	exec_after(next_check, move |_| {
+		check_lock_timeout(id, env, que, activity, toast_signals);
 	});
 }
 
@@ -124,7 +238,9 @@ pub fn lock_app() {
 		use_context::<RwSignal<AppState>>().expect("No app_state context provider");
 
 	close_all_windows();
+	clear_clipboard();
 	que.unque_all_tooltips();
+	que.lock.set(Vec::new());
 	env.db.lock();
 	*env.db.vault_unlocked.write() = false;
 	app_state.set(AppState::PassPrompting);
@@ -140,6 +256,7 @@ pub enum AppState {
 fn main() {
 	let app_state = create_rw_signal(AppState::OnBoarding);
 	let timeout_que_id: TimeoutQueId = create_rw_signal(0);
+	let activity = ActivitySignals::default();
 
 	let has_config = Environment::has_config().is_ok();
 	let has_db = Environment::has_db();
@@ -165,6 +282,9 @@ fn main() {
 	provide_context(toast_signals);
 	provide_context(app_state);
 	provide_context(timeout_que_id);
+	provide_context(activity);
+	let backups: BackupList = create_rw_signal(env.db.get_backups());
+	provide_context(backups);
 
 	if has_config && !has_db {
 		toast_signals.add(String::from(
@@ -193,17 +313,53 @@ fn main() {
 		},
 		AppState::PassPrompting => {
 			if !password.get().is_empty() {
+				if let Some(backoff) = env_closure.config.unlock_backoff() {
+					untrack(|| {
+						toast_signals.add(format!(
+							"Too many failed attempts.\nTry again in {} seconds.",
+							backoff.as_secs().max(1)
+						));
+					});
+					return;
+				}
+
 				let decrypted = env_closure.db.decrypt_database(password.get());
 				match decrypted {
-					Ok(()) => {
+					Ok(kind) => {
 						untrack(|| {
 							password.update(|pass| pass.zeroize());
 							toast_signals.kill_all_toasts();
 							app_state.set(AppState::Ready);
+							if kind == KeySlotKind::RecoveryCode {
+								toast_signals.add(String::from(
+									"Unlocked with your recovery code.\nSet a new password in the settings with the recovery code as the old password.",
+								));
+							}
+							if let Some((count, last)) =
+								env_closure.config.take_failed_unlocks()
+							{
+								let datetime_utc: DateTime<Utc> =
+									DateTime::from_timestamp(last as i64, 0).unwrap_or_default();
+								let datetime_local: DateTime<Local> =
+									datetime_utc.with_timezone(&Local);
+								toast_signals.add(format!(
+									"{} failed unlock attempt{} since the last unlock.\nThe last one was on {}.",
+									count,
+									if count == 1 { "" } else { "s" },
+									datetime_local.format("%v %T")
+								));
+							}
 						});
 					},
 					Err(err) => {
 						untrack(|| {
+							// only wrong passwords and key files count, not broken files
+							if matches!(
+								err.downcast_ref::<CryptError>(),
+								Some(CryptError::Decryption(_))
+							) {
+								env_closure.config.add_failed_unlock();
+							}
 							toast_signals.add(err.to_string());
 						});
 					},
@@ -224,6 +380,7 @@ fn main() {
 					let config_debounce = env.config.clone();
 					let debounce = Debounce::default();
 
+					activity.start();
 					create_lock_timeout();
 
 					app_view(search_trigger)
@@ -259,6 +416,14 @@ fn main() {
 		.window(
 			move |_| {
 				let id = view.id();
+				// any input counts as activity for the idle lock
+				let view = view
+					.on_event_cont(EventListener::PointerMove, move |_| activity.record())
+					.on_event_cont(EventListener::PointerDown, move |_| activity.record())
+					.on_event_cont(EventListener::PointerWheel, move |_| {
+						activity.record()
+					})
+					.on_event_cont(EventListener::KeyDown, move |_| activity.record());
 				view.on_event_cont(EventListener::KeyDown, move |event| {
 					let key = match event {
 						Event::KeyDown(k) => match k.key.physical_key {
@@ -276,7 +441,9 @@ fn main() {
 					if key == env_shortcuts.config.general.read().shortcuts.lock.0
 						&& modifier == env_shortcuts.config.general.read().shortcuts.lock.1
 					{
+						clear_clipboard();
 						que.unque_all_tooltips();
+						que.lock.set(Vec::new());
 						env_shortcuts.db.lock();
 						*env_shortcuts.db.vault_unlocked.write() = false;
 						app_state.set(AppState::PassPrompting);
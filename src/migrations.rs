@@ -0,0 +1,378 @@
+use uuid::Uuid;
+
+use crate::encryption::{Cipher, KdfParams};
+
+// the version of the db file format this build reads and writes
+pub const DB_VERSION: u32 = 10;
+
+#[derive(thiserror::Error, Debug, Clone)]
+pub enum MigrationError {
+	#[error(
+		"This vault was saved by a newer version of Vault (format {0}, this version only supports up to format {DB_VERSION}). Please update Vault to open it."
+	)]
+	NewerVersion(u32),
+}
+
+// every migration moves a db file up by exactly one version
+struct Migration {
+	// the plain text header of the db file, run when the file is loaded
+	header: fn(&mut toml::Value),
+	// the decrypted contents, run after the vault was unlocked
+	contents: fn(&mut toml::Value),
+}
+
+// MIGRATIONS[n] upgrades a file from version n to version n + 1
+const MIGRATIONS: [Migration; DB_VERSION as usize] = [
+	// v0 -> v1
+	// ids turn into uuids as incrementing numbers collide after deleting or
+	// importing entries
+	Migration {
+		header: |_| {},
+		contents: migrate_ids,
+	},
+	// v1 -> v2
+	// the argon2 parameters are stored so a changed library default can't lock
+	// anyone out of their vault
+	Migration {
+		header: add_kdf_params,
+		contents: |_| {},
+	},
+	// v2 -> v3
+	// key slots are added on the first unlock as that needs the password, this
+	// version only keeps older builds from failing on them with a wrong password
+	Migration {
+		header: |_| {},
+		contents: |_| {},
+	},
+	// v3 -> v4
+	// the header is bound to the contents from now on, older files are read
+	// without it until they are saved again
+	Migration {
+		header: |_| {},
+		contents: |_| {},
+	},
+	// v4 -> v5
+	// the cipher became selectable, everything before was aes-gcm-siv
+	Migration {
+		header: add_cipher,
+		contents: |_| {},
+	},
+	// v5 -> v6
+	// one-time password, attachment, email, phone, PIN, date, number and card
+	// fields. Older builds can't read these kinds, the bump makes them ask for an
+	// update instead of calling the vault damaged
+	Migration {
+		header: |_| {},
+		contents: |_| {},
+	},
+	// v6 -> v7
+	// counter based one-time passwords keep the counters they used next to the
+	// field, older builds would drop them on save and hand out used codes again
+	Migration {
+		header: |_| {},
+		contents: |_| {},
+	},
+	// v7 -> v8
+	// entries can be put into nested groups, older builds would drop the groups
+	// on save and show every entry at the top level
+	Migration {
+		header: |_| {},
+		contents: |_| {},
+	},
+	// v8 -> v9
+	// entries can be tagged, older builds would drop the tags on save
+	Migration {
+		header: |_| {},
+		contents: |_| {},
+	},
+	// v9 -> v10
+	// entries can be pinned and remember when they were last used and created,
+	// older builds would drop all of that on save
+	Migration {
+		header: |_| {},
+		contents: add_created,
+	},
+];
+
+pub fn check_version(version: u32) -> Result<(), MigrationError> {
+	if version > DB_VERSION {
+		return Err(MigrationError::NewerVersion(version));
+	}
+	Ok(())
+}
+
+pub fn migrate_header(
+	version: u32,
+	header: &mut toml::Value,
+) -> Result<(), MigrationError> {
+	check_version(version)?;
+	for migration in MIGRATIONS.iter().skip(version as usize) {
+		(migration.header)(header);
+	}
+	Ok(())
+}
+
+pub fn migrate_contents(
+	version: u32,
+	contents: &mut toml::Value,
+) -> Result<(), MigrationError> {
+	check_version(version)?;
+	for migration in MIGRATIONS.iter().skip(version as usize) {
+		(migration.contents)(contents);
+	}
+	Ok(())
+}
+
+// swaps the numeric ids of entries and fields for random uuids
+fn migrate_ids(contents: &mut toml::Value) {
+	fn migrate_id(item: &mut toml::Value) {
+		if let Some(id) = item.get_mut("id") {
+			if id.is_integer() {
+				*id = toml::Value::String(Uuid::new_v4().to_string());
+			}
+		}
+	}
+
+	fn migrate_entry(entry: &mut toml::Value) {
+		migrate_id(entry);
+		if let Some(toml::Value::Array(fields)) = entry.get_mut("fields") {
+			fields.iter_mut().for_each(migrate_id);
+		}
+	}
+
+	if let Some(toml::Value::Array(entries)) = contents.get_mut("contents") {
+		entries.iter_mut().for_each(migrate_entry);
+	}
+
+	if let Some(toml::Value::Array(trash)) = contents.get_mut("trash") {
+		for trashed in trash.iter_mut() {
+			if let Some(entry) = trashed.get_mut("entry") {
+				migrate_entry(entry);
+			}
+		}
+	}
+}
+
+// stores the argon2 defaults the vault was created with
+fn add_kdf_params(header: &mut toml::Value) {
+	if let Some(toml::Value::Table(db)) = header.get_mut("db") {
+		if !db.contains_key("kdf") {
+			if let Ok(kdf) = toml::Value::try_from(KdfParams::default()) {
+				db.insert(String::from("kdf"), kdf);
+			}
+		}
+	}
+}
+
+// marks the header and every key slot as aes-gcm-siv
+fn add_cipher(header: &mut toml::Value) {
+	let cipher = match toml::Value::try_from(Cipher::Aes256GcmSiv) {
+		Ok(cipher) => cipher,
+		Err(_) => return,
+	};
+
+	if let Some(toml::Value::Table(db)) = header.get_mut("db") {
+		if !db.contains_key("cipher") {
+			db.insert(String::from("cipher"), cipher.clone());
+		}
+
+		if let Some(toml::Value::Array(key_slots)) = db.get_mut("key_slots") {
+			for key_slot in key_slots.iter_mut() {
+				if let toml::Value::Table(key_slot) = key_slot {
+					if !key_slot.contains_key("cipher") {
+						key_slot.insert(String::from("cipher"), cipher.clone());
+					}
+				}
+			}
+		}
+	}
+}
+
+// existing entries count as created with their oldest value as nothing else is
+// known about them
+fn add_created(contents: &mut toml::Value) {
+	fn oldest_value(entry: &toml::Value) -> i64 {
+		entry
+			.get("fields")
+			.and_then(|fields| fields.as_array())
+			.into_iter()
+			.flatten()
+			.filter_map(|field| field.get("value").and_then(|value| value.as_array()))
+			.flatten()
+			.filter_map(|value| {
+				value.get(0).and_then(|timestamp| timestamp.as_integer())
+			})
+			.filter(|timestamp| *timestamp > 0)
+			.min()
+			.unwrap_or(0)
+	}
+
+	fn add_to_entry(entry: &mut toml::Value) {
+		let created = oldest_value(entry);
+		if let toml::Value::Table(entry) = entry {
+			entry.entry("created").or_insert(toml::Value::Integer(created));
+		}
+	}
+
+	if let Some(toml::Value::Array(entries)) = contents.get_mut("contents") {
+		entries.iter_mut().for_each(add_to_entry);
+	}
+
+	if let Some(toml::Value::Array(trash)) = contents.get_mut("trash") {
+		for trashed in trash.iter_mut() {
+			if let Some(entry) = trashed.get_mut("entry") {
+				add_to_entry(entry);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde::Deserialize;
+
+	fn parse(content: &str) -> toml::Value {
+		toml::from_str(content).expect("test toml is valid")
+	}
+
+	#[test]
+	fn newer_versions_are_refused() {
+		assert!(check_version(0).is_ok());
+		assert!(check_version(DB_VERSION).is_ok());
+		assert!(matches!(
+			check_version(DB_VERSION + 1),
+			Err(MigrationError::NewerVersion(version)) if version == DB_VERSION + 1
+		));
+
+		let mut header = parse("[db]\nversion = 0");
+		assert!(migrate_header(DB_VERSION + 1, &mut header).is_err());
+		assert!(migrate_contents(DB_VERSION + 1, &mut header).is_err());
+	}
+
+	#[test]
+	fn numeric_ids_become_uuids() {
+		let mut contents = parse(
+			r#"
+			[[contents]]
+			id = 1
+			title = "Bank"
+			[[contents.fields]]
+			id = 2
+			title = "Password"
+
+			[[trash]]
+			deleted_at = 0
+			[trash.entry]
+			id = 3
+			title = "Old"
+			[[trash.entry.fields]]
+			id = 4
+			title = "Notes"
+			"#,
+		);
+		migrate_contents(0, &mut contents).unwrap();
+
+		let is_uuid = |value: &toml::Value| {
+			value.as_str().is_some_and(|id| Uuid::parse_str(id).is_ok())
+		};
+		let entry = &contents["contents"][0];
+		assert!(is_uuid(&entry["id"]));
+		assert!(is_uuid(&entry["fields"][0]["id"]));
+		let trashed = &contents["trash"][0]["entry"];
+		assert!(is_uuid(&trashed["id"]));
+		assert!(is_uuid(&trashed["fields"][0]["id"]));
+		assert_ne!(entry["id"], trashed["id"]);
+	}
+
+	#[test]
+	fn uuids_are_kept() {
+		let id = Uuid::new_v4().to_string();
+		let mut contents =
+			parse(&format!("[[contents]]\nid = \"{id}\"\ntitle = \"Bank\""));
+		migrate_contents(0, &mut contents).unwrap();
+		assert_eq!(contents["contents"][0]["id"].as_str(), Some(id.as_str()));
+	}
+
+	#[test]
+	fn kdf_params_are_added_to_old_headers_only() {
+		let mut header = parse("[db]\nversion = 1");
+		migrate_header(1, &mut header).unwrap();
+		let kdf = KdfParams::deserialize(header["db"]["kdf"].clone()).unwrap();
+		assert_eq!(kdf, KdfParams::default());
+
+		let mut header = parse(
+			"[db]\nversion = 1\n[db.kdf]\nalgorithm = \"Argon2id\"\nversion = 19\nm_cost = 1\nt_cost = 2\np_cost = 3",
+		);
+		migrate_header(1, &mut header).unwrap();
+		assert_eq!(header["db"]["kdf"]["m_cost"].as_integer(), Some(1));
+	}
+
+	#[test]
+	fn cipher_is_added_to_header_and_key_slots() {
+		let mut header = parse(
+			r#"
+			[db]
+			version = 4
+			[[db.key_slots]]
+			kind = "Password"
+			[[db.key_slots]]
+			kind = "RecoveryCode"
+			cipher = "XChaCha20Poly1305"
+			"#,
+		);
+		migrate_header(4, &mut header).unwrap();
+
+		let cipher = |value: &toml::Value| {
+			Cipher::deserialize(value.clone()).expect("cipher is valid")
+		};
+		assert_eq!(cipher(&header["db"]["cipher"]), Cipher::Aes256GcmSiv);
+		let slots = &header["db"]["key_slots"];
+		assert_eq!(cipher(&slots[0]["cipher"]), Cipher::Aes256GcmSiv);
+		assert_eq!(cipher(&slots[1]["cipher"]), Cipher::XChaCha20Poly1305);
+	}
+
+	#[test]
+	fn entries_are_created_with_their_oldest_value() {
+		let mut contents = parse(
+			r#"
+			[[contents]]
+			title = "Bank"
+			[[contents.fields]]
+			value = [[0, "default"], [1700000300, "new"]]
+			[[contents.fields]]
+			value = [[1700000100, "old"]]
+
+			[[contents]]
+			title = "Empty"
+			fields = []
+
+			[[contents]]
+			title = "Known"
+			created = 5
+			fields = []
+
+			[[trash]]
+			[trash.entry]
+			title = "Old"
+			[[trash.entry.fields]]
+			value = [[1600000000, "gone"]]
+			"#,
+		);
+		migrate_contents(9, &mut contents).unwrap();
+
+		let created = |entry: &toml::Value| entry["created"].as_integer();
+		assert_eq!(created(&contents["contents"][0]), Some(1700000100));
+		assert_eq!(created(&contents["contents"][1]), Some(0));
+		assert_eq!(created(&contents["contents"][2]), Some(5));
+		assert_eq!(created(&contents["trash"][0]["entry"]), Some(1600000000));
+	}
+
+	#[test]
+	fn current_files_are_left_alone() {
+		let mut header = parse("[db]\nversion = 1");
+		let before = header.clone();
+		migrate_header(DB_VERSION, &mut header).unwrap();
+		assert_eq!(header, before);
+	}
+}
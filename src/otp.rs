@@ -0,0 +1,415 @@
+use hmac::{digest::KeyInit, Hmac, Mac};
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+use std::time::{SystemTime, UNIX_EPOCH};
+use zeroize::Zeroize;
+
+const DEFAULT_DIGITS: u32 = 6;
+const DEFAULT_PERIOD: u64 = 30;
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+// steam guard codes are 5 characters of this alphabet every 30 seconds
+const STEAM_ALPHABET: &[u8; 26] = b"23456789BCDFGHJKMNPQRTVWXY";
+const STEAM_DIGITS: u32 = 5;
+
+#[derive(thiserror::Error, Debug)]
+pub enum OtpError {
+	#[error("The one-time password secret is not valid base32.")]
+	Secret(),
+	#[error(
+		"The one-time password link is not an otpauth://totp/, otpauth://hotp/ or steam:// link."
+	)]
+	Uri(),
+	#[error("The one-time password algorithm \"{0}\" is not supported.")]
+	Algorithm(String),
+	#[error("One-time passwords can only have 6 or 8 digits.")]
+	Digits(),
+	#[error(
+		"The one-time password period has to be a positive number of seconds."
+	)]
+	Period(),
+	#[error("The one-time password counter has to be a positive number.")]
+	Counter(),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OtpAlgorithm {
+	Sha1,
+	Sha256,
+	Sha512,
+}
+
+impl std::str::FromStr for OtpAlgorithm {
+	type Err = OtpError;
+
+	fn from_str(algorithm: &str) -> Result<Self, Self::Err> {
+		match algorithm.to_ascii_uppercase().as_str() {
+			"SHA1" => Ok(OtpAlgorithm::Sha1),
+			"SHA256" => Ok(OtpAlgorithm::Sha256),
+			"SHA512" => Ok(OtpAlgorithm::Sha512),
+			_ => Err(OtpError::Algorithm(String::from(algorithm))),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OtpKind {
+	Totp { period: u64 },
+	// the counter the first code is generated from, the ones used since are
+	// kept next to the field
+	Hotp { counter: u64 },
+	Steam,
+}
+
+// a field either holds an otpauth:// or steam:// link or just the base32
+// secret in which case the time based defaults most apps use apply
+pub struct Otp {
+	secret: Vec<u8>,
+	pub kind: OtpKind,
+	pub algorithm: OtpAlgorithm,
+	pub digits: u32,
+}
+
+impl Drop for Otp {
+	fn drop(&mut self) {
+		self.secret.zeroize();
+	}
+}
+
+impl Otp {
+	pub fn parse(value: &str) -> Result<Self, OtpError> {
+		let value = value.trim();
+		let lowercase = value.to_ascii_lowercase();
+
+		if let Some(secret) = lowercase.strip_prefix("steam://") {
+			return Ok(Otp {
+				secret: base32_decode(secret)?,
+				kind: OtpKind::Steam,
+				algorithm: OtpAlgorithm::Sha1,
+				digits: STEAM_DIGITS,
+			});
+		}
+
+		if !lowercase.starts_with("otpauth://") {
+			return Ok(Otp {
+				secret: base32_decode(value)?,
+				kind: OtpKind::Totp {
+					period: DEFAULT_PERIOD,
+				},
+				algorithm: OtpAlgorithm::Sha1,
+				digits: DEFAULT_DIGITS,
+			});
+		}
+
+		let is_hotp = lowercase.starts_with("otpauth://hotp/");
+		if !is_hotp && !lowercase.starts_with("otpauth://totp/") {
+			return Err(OtpError::Uri());
+		}
+
+		let mut secret = None;
+		let mut algorithm = OtpAlgorithm::Sha1;
+		let mut digits = DEFAULT_DIGITS;
+		let mut period = DEFAULT_PERIOD;
+		let mut counter = 0;
+		let mut is_steam = false;
+
+		let query = value.split_once('?').map(|(_, query)| query).unwrap_or("");
+		for (key, param) in query.split('&').filter_map(|pair| pair.split_once('='))
+		{
+			let param = url_escape::decode(param);
+			match key.to_ascii_lowercase().as_str() {
+				"secret" => secret = Some(base32_decode(&param)?),
+				"algorithm" => algorithm = param.parse()?,
+				"digits" => digits = param.parse().map_err(|_| OtpError::Digits())?,
+				"period" => period = param.parse().map_err(|_| OtpError::Period())?,
+				"counter" => {
+					counter = param.parse().map_err(|_| OtpError::Counter())?
+				},
+				"encoder" => is_steam = param.eq_ignore_ascii_case("steam"),
+				_ => {},
+			}
+		}
+
+		let kind = if is_hotp {
+			OtpKind::Hotp { counter }
+		} else if is_steam {
+			digits = STEAM_DIGITS;
+			OtpKind::Steam
+		} else {
+			OtpKind::Totp { period }
+		};
+
+		if kind != OtpKind::Steam && digits != 6 && digits != 8 {
+			return Err(OtpError::Digits());
+		}
+		if period == 0 {
+			return Err(OtpError::Period());
+		}
+
+		Ok(Otp {
+			secret: secret.ok_or(OtpError::Secret())?,
+			kind,
+			algorithm,
+			digits,
+		})
+	}
+
+	pub fn code(&self, counter: u64) -> String {
+		let mut binary = truncate(&self.secret, self.algorithm, counter);
+
+		if self.kind == OtpKind::Steam {
+			(0..self.digits)
+				.map(|_| {
+					let letter = STEAM_ALPHABET[binary as usize % STEAM_ALPHABET.len()];
+					binary /= STEAM_ALPHABET.len() as u32;
+					letter as char
+				})
+				.collect()
+		} else {
+			format!(
+				"{:0width$}",
+				binary as u64 % 10u64.pow(self.digits),
+				width = self.digits as usize
+			)
+		}
+	}
+
+	// RFC 6238, the time step is the counter
+	pub fn code_at(&self, time: u64) -> String {
+		match self.kind {
+			OtpKind::Totp { period } => self.code(time / period),
+			OtpKind::Steam => self.code(time / DEFAULT_PERIOD),
+			OtpKind::Hotp { counter } => self.code(counter),
+		}
+	}
+
+	// the current code and the seconds until the next one, counter based codes
+	// don't expire
+	pub fn now(&self) -> (String, u64) {
+		let time = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap_or_default()
+			.as_secs();
+		let remaining = match self.kind {
+			OtpKind::Totp { period } => period - time % period,
+			OtpKind::Steam => DEFAULT_PERIOD - time % DEFAULT_PERIOD,
+			OtpKind::Hotp { .. } => 0,
+		};
+		(self.code_at(time), remaining)
+	}
+}
+
+fn hmac<M: Mac + KeyInit>(key: &[u8], message: &[u8]) -> Vec<u8> {
+	let mut mac =
+		<M as KeyInit>::new_from_slice(key).expect("HMAC takes keys of any size");
+	mac.update(message);
+	mac.finalize().into_bytes().to_vec()
+}
+
+// RFC 4226, dynamic truncation picks 4 bytes of the HMAC at an offset taken
+// from its last byte
+fn truncate(secret: &[u8], algorithm: OtpAlgorithm, counter: u64) -> u32 {
+	let mut hash = match algorithm {
+		OtpAlgorithm::Sha1 => hmac::<Hmac<Sha1>>(secret, &counter.to_be_bytes()),
+		OtpAlgorithm::Sha256 => {
+			hmac::<Hmac<Sha256>>(secret, &counter.to_be_bytes())
+		},
+		OtpAlgorithm::Sha512 => {
+			hmac::<Hmac<Sha512>>(secret, &counter.to_be_bytes())
+		},
+	};
+
+	let offset = (hash[hash.len() - 1] & 0xf) as usize;
+	let binary = u32::from_be_bytes([
+		hash[offset] & 0x7f,
+		hash[offset + 1],
+		hash[offset + 2],
+		hash[offset + 3],
+	]);
+	hash.zeroize();
+	binary
+}
+
+// secrets are often shown in groups, in lowercase or with padding
+pub fn base32_decode(input: &str) -> Result<Vec<u8>, OtpError> {
+	let mut output = Vec::with_capacity(input.len() * 5 / 8);
+	let mut buffer: u64 = 0;
+	let mut bits = 0;
+
+	for c in
+		input.chars().filter(|c| !c.is_whitespace() && *c != '=' && *c != '-')
+	{
+		let value = BASE32_ALPHABET
+			.iter()
+			.position(|letter| *letter as char == c.to_ascii_uppercase())
+			.ok_or(OtpError::Secret())?;
+		buffer = (buffer << 5) | value as u64;
+		bits += 5;
+		if bits >= 8 {
+			bits -= 8;
+			output.push((buffer >> bits) as u8);
+			buffer &= (1 << bits) - 1;
+		}
+	}
+
+	if output.is_empty() {
+		return Err(OtpError::Secret());
+	}
+	Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// the secrets from the RFC test vectors, the key length matches the hash
+	const SHA1_SECRET: &[u8] = b"12345678901234567890";
+	const SHA256_SECRET: &[u8] = b"12345678901234567890123456789012";
+	const SHA512_SECRET: &[u8] =
+		b"1234567890123456789012345678901234567890123456789012345678901234";
+
+	fn otp(
+		secret: &[u8],
+		kind: OtpKind,
+		algorithm: OtpAlgorithm,
+		digits: u32,
+	) -> Otp {
+		Otp {
+			secret: secret.to_vec(),
+			kind,
+			algorithm,
+			digits,
+		}
+	}
+
+	#[test]
+	fn hotp_matches_rfc_4226() {
+		let hotp =
+			otp(SHA1_SECRET, OtpKind::Hotp { counter: 0 }, OtpAlgorithm::Sha1, 6);
+		let expected = [
+			"755224", "287082", "359152", "969429", "338314", "254676", "287922",
+			"162583", "399871", "520489",
+		];
+		for (counter, code) in expected.iter().enumerate() {
+			assert_eq!(hotp.code(counter as u64), *code);
+		}
+	}
+
+	#[test]
+	fn totp_matches_rfc_6238() {
+		let totp = |secret, algorithm| {
+			otp(secret, OtpKind::Totp { period: 30 }, algorithm, 8)
+		};
+		let sha1 = totp(SHA1_SECRET, OtpAlgorithm::Sha1);
+		let sha256 = totp(SHA256_SECRET, OtpAlgorithm::Sha256);
+		let sha512 = totp(SHA512_SECRET, OtpAlgorithm::Sha512);
+
+		let expected = [
+			(59, "94287082", "46119246", "90693936"),
+			(1111111109, "07081804", "68084774", "25091201"),
+			(1111111111, "14050471", "67062674", "99943326"),
+			(1234567890, "89005924", "91819424", "93441116"),
+			(2000000000, "69279037", "90698825", "38618901"),
+			(20000000000, "65353130", "77737706", "47863826"),
+		];
+		for (time, sha1_code, sha256_code, sha512_code) in expected {
+			assert_eq!(sha1.code_at(time), sha1_code);
+			assert_eq!(sha256.code_at(time), sha256_code);
+			assert_eq!(sha512.code_at(time), sha512_code);
+		}
+	}
+
+	#[test]
+	fn steam_codes_use_the_steam_alphabet() {
+		let steam = Otp::parse("steam://GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ").unwrap();
+		assert_eq!(steam.kind, OtpKind::Steam);
+		assert_eq!(steam.secret, SHA1_SECRET);
+
+		let code = steam.code_at(59);
+		assert_eq!(code.len(), STEAM_DIGITS as usize);
+		assert!(code.bytes().all(|letter| STEAM_ALPHABET.contains(&letter)));
+		// the same 30 second steps as a totp code
+		assert_eq!(code, steam.code_at(30));
+		assert_ne!(code, steam.code_at(60));
+
+		let encoder = Otp::parse(
+			"otpauth://totp/Steam:alice?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&encoder=steam",
+		)
+		.unwrap();
+		assert_eq!(encoder.kind, OtpKind::Steam);
+		assert_eq!(encoder.code_at(59), code);
+	}
+
+	#[test]
+	fn base32_matches_rfc_4648() {
+		assert_eq!(base32_decode("MY======").unwrap(), b"f");
+		assert_eq!(base32_decode("MZXQ====").unwrap(), b"fo");
+		assert_eq!(base32_decode("MZXW6===").unwrap(), b"foo");
+		assert_eq!(base32_decode("MZXW6YQ=").unwrap(), b"foob");
+		assert_eq!(base32_decode("MZXW6YTB").unwrap(), b"fooba");
+		assert_eq!(base32_decode("MZXW6YTBOI======").unwrap(), b"foobar");
+	}
+
+	#[test]
+	fn base32_accepts_how_secrets_are_shown() {
+		assert_eq!(base32_decode("mzxw 6ytb oi").unwrap(), b"foobar");
+		assert_eq!(base32_decode("MZXW-6YTB-OI").unwrap(), b"foobar");
+		assert!(matches!(base32_decode("MZXW1"), Err(OtpError::Secret())));
+		assert!(matches!(base32_decode("===="), Err(OtpError::Secret())));
+	}
+
+	#[test]
+	fn plain_secrets_use_the_defaults() {
+		let totp = Otp::parse(" GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ ").unwrap();
+		assert_eq!(totp.kind, OtpKind::Totp { period: 30 });
+		assert_eq!(totp.algorithm, OtpAlgorithm::Sha1);
+		assert_eq!(totp.digits, 6);
+		assert_eq!(totp.code_at(59), "287082");
+	}
+
+	#[test]
+	fn otpauth_uris_are_parsed() {
+		let totp = Otp::parse(
+			"otpauth://totp/Example:alice%40example.com?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&issuer=Example&algorithm=sha256&digits=8&period=60",
+		)
+		.unwrap();
+		assert_eq!(totp.kind, OtpKind::Totp { period: 60 });
+		assert_eq!(totp.algorithm, OtpAlgorithm::Sha256);
+		assert_eq!(totp.digits, 8);
+
+		let hotp = Otp::parse(
+			"OTPAUTH://HOTP/Example?counter=3&secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ",
+		)
+		.unwrap();
+		assert_eq!(hotp.kind, OtpKind::Hotp { counter: 3 });
+		assert_eq!(hotp.code_at(0), "969429");
+	}
+
+	#[test]
+	fn invalid_otpauth_uris_are_refused() {
+		let secret = "secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+		assert!(matches!(
+			Otp::parse(&format!("otpauth://push/Example?{secret}")),
+			Err(OtpError::Uri())
+		));
+		assert!(matches!(
+			Otp::parse("otpauth://totp/Example?issuer=Example"),
+			Err(OtpError::Secret())
+		));
+		assert!(matches!(
+			Otp::parse(&format!("otpauth://totp/Example?{secret}&algorithm=MD5")),
+			Err(OtpError::Algorithm(algorithm)) if algorithm == "MD5"
+		));
+		assert!(matches!(
+			Otp::parse(&format!("otpauth://totp/Example?{secret}&digits=7")),
+			Err(OtpError::Digits())
+		));
+		assert!(matches!(
+			Otp::parse(&format!("otpauth://totp/Example?{secret}&period=0")),
+			Err(OtpError::Period())
+		));
+		assert!(matches!(
+			Otp::parse(&format!("otpauth://hotp/Example?{secret}&counter=-1")),
+			Err(OtpError::Counter())
+		));
+	}
+}
@@ -0,0 +1,158 @@
+use anyhow::Result;
+use std::{
+	fs,
+	io::Write,
+	path::{Path, PathBuf},
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+const TMP_EXTENSION: &str = "tmp";
+const BACKUP_EXTENSION: &str = "bak";
+
+fn parent_dir(path: &Path) -> PathBuf {
+	match path.parent() {
+		Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+		_ => PathBuf::from("."),
+	}
+}
+
+fn file_name(path: &Path) -> String {
+	path
+		.file_name()
+		.map(|name| name.to_string_lossy().to_string())
+		.unwrap_or_default()
+}
+
+// we write into a temp file next to the target, flush it to disk and then
+// rename it over the original so a crash never leaves a half written file
+pub fn atomic_write(path: &str, contents: &[u8]) -> Result<()> {
+	let path = PathBuf::from(path);
+	let tmp_path =
+		parent_dir(&path).join(format!("{}.{}", file_name(&path), TMP_EXTENSION));
+
+	let mut tmp_file = fs::OpenOptions::new()
+		.write(true)
+		.create(true)
+		.truncate(true)
+		.open(&tmp_path)?;
+	if let Err(err) =
+		tmp_file.write_all(contents).and_then(|_| tmp_file.sync_all())
+	{
+		let _ = fs::remove_file(&tmp_path);
+		return Err(err.into());
+	}
+	drop(tmp_file);
+
+	fs::rename(&tmp_path, &path)?;
+
+	// persist the rename itself, not supported for directories on windows
+	#[cfg(unix)]
+	if let Ok(dir) = fs::File::open(parent_dir(&path)) {
+		let _ = dir.sync_all();
+	}
+
+	Ok(())
+}
+
+// backups live next to the db file as <file name>.<timestamp>.bak, sorted
+// newest first, a second one within the same second is <timestamp>.<n>.bak
+pub fn get_backups(db_path: &str) -> Vec<(String, u64)> {
+	let path = PathBuf::from(db_path);
+	let prefix = format!("{}.", file_name(&path));
+	let suffix = format!(".{}", BACKUP_EXTENSION);
+
+	let mut backups = match fs::read_dir(parent_dir(&path)) {
+		Ok(dir) => dir
+			.filter_map(|item| item.ok())
+			.filter_map(|item| {
+				let name = item.file_name().to_string_lossy().to_string();
+				let stamp = name.strip_prefix(&prefix)?.strip_suffix(&suffix)?;
+				let (timestamp, n) = match stamp.split_once('.') {
+					Some((timestamp, n)) => (timestamp, n.parse::<u64>().ok()?),
+					None => (stamp, 0),
+				};
+				Some((
+					item.path().to_string_lossy().to_string(),
+					timestamp.parse::<u64>().ok()?,
+					n,
+				))
+			})
+			.collect::<Vec<(String, u64, u64)>>(),
+		Err(_) => Vec::new(),
+	};
+
+	backups.sort_by_key(|backup| std::cmp::Reverse((backup.1, backup.2)));
+	backups.into_iter().map(|(path, timestamp, _)| (path, timestamp)).collect()
+}
+
+pub fn create_backup(db_path: &str, count: usize) -> Result<()> {
+	let path = PathBuf::from(db_path);
+	if count == 0 || !path.is_file() {
+		return Ok(());
+	}
+
+	let timestamp =
+		SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+	let backup_name = |stamp: String| {
+		parent_dir(&path).join(format!(
+			"{}.{}.{}",
+			file_name(&path),
+			stamp,
+			BACKUP_EXTENSION
+		))
+	};
+	let mut backup_path = backup_name(timestamp.to_string());
+	let mut n = 0;
+	while backup_path.exists() {
+		n += 1;
+		backup_path = backup_name(format!("{}.{}", timestamp, n));
+	}
+	atomic_write(&backup_path.to_string_lossy(), fs::read(&path)?.as_slice())?;
+
+	for (old_backup, _) in get_backups(db_path).into_iter().skip(count) {
+		let _ = fs::remove_file(old_backup);
+	}
+
+	Ok(())
+}
+
+pub fn restore_backup(
+	db_path: &str,
+	backup_path: &str,
+	count: usize,
+) -> Result<()> {
+	// read it first as rotating below might remove the backup we restore
+	let contents = fs::read(backup_path)?;
+	// the current state becomes a backup itself so a restore can be undone
+	create_backup(db_path, count)?;
+	atomic_write(db_path, contents.as_slice())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn backups_within_a_second_are_all_kept() {
+		let dir = std::env::temp_dir().join(format!(
+			"vault_backups_{}",
+			SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos()
+		));
+		fs::create_dir_all(&dir).unwrap();
+		let db_path = dir.join("vault_db.toml").to_string_lossy().to_string();
+
+		for content in ["first", "second", "third"] {
+			fs::write(&db_path, content).unwrap();
+			create_backup(&db_path, 2).unwrap();
+		}
+
+		let backups = get_backups(&db_path);
+		let contents = backups
+			.iter()
+			.map(|(path, _)| fs::read_to_string(path).unwrap())
+			.collect::<Vec<String>>();
+		let _ = fs::remove_dir_all(&dir);
+
+		assert_eq!(contents, vec!["third", "second"]);
+	}
+}
@@ -1,5 +1,10 @@
+use std::fs;
+use uuid::Uuid;
+
 use floem::{
 	event::{Event, EventListener},
+	file::FileDialogOptions,
+	file_action::save_as,
 	keyboard::{KeyCode, PhysicalKey},
 	kurbo::Size,
 	peniko::Color,
@@ -7,16 +12,17 @@ use floem::{
 		create_effect, create_rw_signal, provide_context, use_context, RwSignal,
 		Trigger,
 	},
-	style::{CursorStyle, Display, Position},
+	style::{CursorStyle, Display, FlexWrap, Position},
 	views::{
-		container, dyn_container, empty, label, scroll, virtual_stack, Decorators,
-		VirtualDirection, VirtualItemSize,
+		container, dyn_container, dyn_stack, empty, label, scroll, virtual_stack,
+		Decorators, VirtualDirection, VirtualItemSize,
 	},
 	IntoView,
 };
 
 use crate::{
-	config::{PresetFields, WindowSettings},
+	config::{EntryTemplates, PresetFields, WindowSettings},
+	db::{SidebarItem, SidebarSort, EXPIRY_WARNING_DAYS, TAG_PREFIX},
 	env::Environment,
 	lock_app,
 	ui::{
@@ -26,19 +32,24 @@ use crate::{
 			button::{icon_button, IconButton},
 			input_button_field::{input_button_field, InputButtonField},
 			que::Que,
+			select::select,
 			styles,
 			toast::{toast_view, ToastSignals},
 			tooltip::{tooltip_view, TooltipSignals},
 		},
 		settings::settings_view::settings_view,
+		sidebar_group::{sidebar_group, SidebarGroup, GROUP_INDENT},
+		trash_view::trash_view,
 		window_management::{opening_window, WindowSpec},
 	},
 };
 
 const SEARCHBAR_HEIGHT: f64 = 30.0;
+const MAX_TAG_FILTERS: usize = 12;
 
-pub type SidebarList = RwSignal<im::Vector<(usize, String, usize)>>;
+pub type SidebarList = RwSignal<im::Vector<(Uuid, String, usize)>>;
 pub type PresetFieldSignal = RwSignal<PresetFields>;
+pub type EntryTemplateSignal = RwSignal<EntryTemplates>;
 
 #[derive(Debug, Copy, Clone)]
 pub struct QueSettings {
@@ -69,18 +80,49 @@ pub fn app_view(search_trigger: Trigger) -> impl IntoView {
 	let field_presets: PresetFieldSignal =
 		create_rw_signal(env.config.get_field_presets());
 	provide_context(field_presets);
+	let entry_templates: EntryTemplateSignal =
+		create_rw_signal(env.config.get_entry_templates());
+	provide_context(entry_templates);
 
 	let env_search_reset = env.clone();
+	let env_trash = env.clone();
+	let env_sidebar = env.clone();
+	let env_tree = env.clone();
+	let env_scope = env.clone();
+	let env_group = env.clone();
+	let env_group_title = env.clone();
+	let env_group_export = env.clone();
+	let env_group_delete = env.clone();
+	let env_tags = env.clone();
+	let env_tag_filter = env.clone();
 	let config_sidebar_drag = env.config.clone();
 	let config_sidebar_double_click = env.config.clone();
+	let config_sidebar_sort = env.config.clone();
 
 	let sidebar_width =
 		create_rw_signal(env.config.general.read().window_settings.sidebar_width);
 	let is_sidebar_dragging = create_rw_signal(false);
+	let sidebar_sort =
+		create_rw_signal(env.config.general.read().window_settings.sidebar_sort);
+	let sort_value = create_rw_signal(
+		SidebarSort::all_values()
+			.iter()
+			.position(|sort| *sort == sidebar_sort.get_untracked())
+			.unwrap_or(0),
+	);
 	let active_tab = create_rw_signal(
-		list_sidebar_signal.get().get(0).unwrap_or(&(0, String::from(""), 0)).0,
+		list_sidebar_signal
+			.get()
+			.get(0)
+			.unwrap_or(&(Uuid::nil(), String::from(""), 0))
+			.0,
 	);
 	let search_text = create_rw_signal(String::from(""));
+	let template_value = create_rw_signal(0);
+	let collapsed_groups = create_rw_signal(Vec::<Uuid>::new());
+	let search_group = create_rw_signal(None::<Uuid>);
+	let dragged_entry = create_rw_signal(None::<Uuid>);
+	let all_tags = create_rw_signal(Vec::<(String, usize)>::new());
 	let sidebar_scrolled = create_rw_signal(false);
 	let main_scroll_to = create_rw_signal(0.0);
 
@@ -94,12 +136,15 @@ pub fn app_view(search_trigger: Trigger) -> impl IntoView {
 	provide_context(que_settings);
 	provide_context(tooltip_signals_settings);
 
-	let overflow_labels = create_rw_signal(vec![0]);
+	let overflow_labels = create_rw_signal(Vec::<Uuid>::new());
 
 	let delete_icon = include_str!("./icons/delete.svg");
 	let icon = create_rw_signal(String::from(""));
 	let settings_icon = include_str!("./icons/settings.svg");
 	let lock_icon = include_str!("./icons/lock.svg");
+	let trash_icon = include_str!("./icons/trash.svg");
+	let folder_icon = include_str!("./icons/folder.svg");
+	let export_icon = include_str!("./icons/download.svg");
 
 	let search_text_input_view = input_button_field(
 		InputButtonField {
@@ -113,7 +158,7 @@ pub fn app_view(search_trigger: Trigger) -> impl IntoView {
 			icon.set(String::from(""));
 			search_text.set(String::from(""));
 			list_sidebar_signal.update(
-				|list: &mut im::Vector<(usize, String, usize)>| {
+				|list: &mut im::Vector<(Uuid, String, usize)>| {
 					*list = env_search_reset
 						.db
 						.get_sidebar_list()
@@ -126,6 +171,15 @@ pub fn app_view(search_trigger: Trigger) -> impl IntoView {
 	);
 	let search_text_input_view_id = search_text_input_view.input_id;
 
+	// the search only looks inside the selected group
+	create_effect(move |_| {
+		let group = search_group.get();
+		let needle = search_text.get_untracked();
+		if !needle.is_empty() {
+			list_sidebar_signal.set(env_scope.db.search(&needle, group));
+		}
+	});
+
 	create_effect(move |_| {
 		search_trigger.track();
 		search_text_input_view_id.request_focus();
@@ -139,6 +193,23 @@ pub fn app_view(search_trigger: Trigger) -> impl IntoView {
 			.style(|s| {
 				s.font_size(12.0).padding(3.0).padding_left(10.0).color(C_TOP_TEXT)
 			}),
+		label(move || {
+			list_sidebar_signal.track();
+			match search_group.get() {
+				Some(id) => format!("in {} ✕", env_group_title.db.get_group_title(&id)),
+				None => String::from(""),
+			}
+		})
+		.on_click_stop(move |_| {
+			search_group.set(None);
+		})
+		.style(move |s| {
+			s.font_size(12.0)
+				.padding(3.0)
+				.color(C_FOCUS)
+				.cursor(CursorStyle::Pointer)
+				.apply_if(search_group.get().is_none(), |s| s.display(Display::None))
+		}),
 		search_text_input_view
 			.on_event_cont(EventListener::KeyDown, move |event| {
 				if search_text.get().is_empty() {
@@ -156,22 +227,181 @@ pub fn app_view(search_trigger: Trigger) -> impl IntoView {
 					&& !search_text.get().is_empty()
 				{
 					{
-						env.db.add(search_text.get());
+						let fields = entry_templates
+							.get()
+							.into_iter()
+							.find(|(id, _, _)| *id == template_value.get())
+							.map(|(_, _, fields)| fields)
+							.unwrap_or_default();
+						let new_id = env.db.add_from_template(search_text.get(), fields);
+						env.db.move_entry(&new_id, search_group.get());
 						let _ = env.db.save();
+						active_tab.set(new_id);
 					}
 
-					let search_list = env.db.get_sidebar_list();
-					active_tab.set(search_list[0].0);
-					list_sidebar_signal.set(search_list);
+					list_sidebar_signal.set(env.db.get_sidebar_list());
 					search_text.set(String::from(""));
 					icon.set(String::from(""));
+					template_value.set(0);
 				} else {
 					list_sidebar_signal.update(|list| {
-						*list = env.db.search(&search_text.get());
+						*list = env.db.search(&search_text.get(), search_group.get());
 					});
 				}
 			})
 			.style(|s| s.flex_grow(1.0)),
+		// new entries start out with the fields of the selected template
+		dyn_container(
+			move || entry_templates.get(),
+			move |entry_templates| {
+				if !entry_templates.iter().any(|(id, _, _)| *id == template_value.get())
+				{
+					template_value.set(0);
+				}
+
+				select(
+					template_value,
+					entry_templates
+						.iter()
+						.map(|(id, name, _)| (*id, name.clone()))
+						.collect(),
+					|_| {},
+				)
+				.into_any()
+			},
+		)
+		.on_event_cont(EventListener::PointerEnter, move |_| {
+			tooltip_signals.show(String::from("Template for new entries"));
+		})
+		.on_event_cont(EventListener::PointerLeave, move |_| {
+			tooltip_signals.hide();
+		}),
+		select(
+			sort_value,
+			SidebarSort::all_values().into_iter().enumerate().collect(),
+			move |idx| {
+				let sort =
+					SidebarSort::all_values().get(idx).copied().unwrap_or_default();
+				if sort != sidebar_sort.get_untracked() {
+					sidebar_sort.set(sort);
+					config_sidebar_sort.set_sidebar_sort(sort);
+				}
+			},
+		)
+		.on_event_cont(EventListener::PointerEnter, move |_| {
+			tooltip_signals.show(String::from("Sort entries by"));
+		})
+		.on_event_cont(EventListener::PointerLeave, move |_| {
+			tooltip_signals.hide();
+		}),
+		icon_button(
+			IconButton {
+				icon: String::from(folder_icon),
+				tooltip: String::from("Create a group"),
+				tooltip_signals,
+				..IconButton::default()
+			},
+			move |_| {
+				let title = if search_text.get().is_empty() {
+					String::from("New group")
+				} else {
+					search_text.get()
+				};
+				let parent = search_group.get();
+				env_group.db.add_group(title, parent);
+				let _ = env_group.db.save();
+
+				// the new group has to be visible
+				if let Some(parent) = parent {
+					collapsed_groups.update(|groups| groups.retain(|id| *id != parent));
+				}
+				search_text.set(String::from(""));
+				icon.set(String::from(""));
+				list_sidebar_signal.set(env_group.db.get_sidebar_list());
+			},
+		),
+		icon_button(
+			IconButton {
+				icon: String::from(export_icon),
+				tooltip: String::from("Export group"),
+				tooltip_signals,
+				..IconButton::default()
+			},
+			move |_| {
+				let Some(id) = search_group.get() else {
+					return;
+				};
+				let env_export = env_group_export.clone();
+				save_as(
+					FileDialogOptions::new()
+						.default_name(format!(
+							"{}.toml",
+							env_export.db.get_group_title(&id)
+						))
+						.title("Export group"),
+					move |file_info| {
+						if let Some(file) = file_info {
+							let exported =
+								env_export.db.export_group(&id).and_then(|exported| {
+									fs::write(&file.path[0], exported)?;
+									Ok(())
+								});
+							if exported.is_err() {
+								toast_signals
+									.add(String::from("The group could not be exported."));
+							}
+						}
+					},
+				);
+			},
+		)
+		.style(move |s| {
+			s.apply_if(search_group.get().is_none(), |s| s.display(Display::None))
+		}),
+		icon_button(
+			IconButton {
+				icon: String::from(delete_icon),
+				tooltip: String::from("Delete group, its entries move up a level"),
+				tooltip_signals,
+				..IconButton::default()
+			},
+			move |_| {
+				if let Some(id) = search_group.get() {
+					env_group_delete.db.delete_group(&id);
+					let _ = env_group_delete.db.save();
+					search_group.set(None);
+					list_sidebar_signal.set(env_group_delete.db.get_sidebar_list());
+					tooltip_signals.hide();
+				}
+			},
+		)
+		.style(move |s| {
+			s.apply_if(search_group.get().is_none(), |s| s.display(Display::None))
+		}),
+		icon_button(
+			IconButton {
+				icon: String::from(trash_icon),
+				tooltip: String::from("Open trash"),
+				tooltip_signals,
+				..IconButton::default()
+			},
+			move |_| {
+				let env_trash_window = env_trash.clone();
+				let que_trash = Que::default();
+
+				opening_window(
+					move || trash_view(que_trash, env_trash_window.clone()),
+					WindowSpec {
+						id: String::from("trash-window"),
+						title: String::from("Vault Trash"),
+					},
+					Size::new(350.0, 350.0),
+					move || {
+						que_trash.unque_all_tooltips();
+					},
+				);
+			},
+		),
 		icon_button(
 			IconButton {
 				icon: String::from(lock_icon),
@@ -215,68 +445,239 @@ pub fn app_view(search_trigger: Trigger) -> impl IntoView {
 				.padding_right(3)
 		});
 
-	let sidebar = scroll({
+	// any change to an entry might have changed its tags
+	create_effect(move |_| {
+		list_sidebar_signal.track();
+		let mut tags = env_tags.db.get_all_tags();
+		tags.truncate(MAX_TAG_FILTERS);
+		all_tags.set(tags);
+	});
+
+	let tag_filters = dyn_stack(
+		move || all_tags.get(),
+		move |(tag, count)| (tag.clone(), *count),
+		move |(tag, count)| {
+			let env = env_tag_filter.clone();
+			let token = format!("{}{}", TAG_PREFIX, tag);
+			let token_active = token.clone();
+
+			label(move || format!("{} {}", tag, count))
+				.on_click_stop(move |_| {
+					let mut words = search_text
+						.get()
+						.split_whitespace()
+						.map(String::from)
+						.collect::<Vec<String>>();
+					if words.contains(&token) {
+						words.retain(|word| *word != token);
+					} else {
+						words.push(token.clone());
+					}
+					search_text.set(words.join(" "));
+
+					if search_text.get().is_empty() {
+						icon.set(String::from(""));
+						list_sidebar_signal.set(env.db.get_sidebar_list());
+					} else {
+						icon.set(String::from(delete_icon));
+						list_sidebar_signal
+							.set(env.db.search(&search_text.get(), search_group.get()));
+					}
+				})
+				.style(move |s| {
+					styles::tag(s)
+						.cursor(CursorStyle::Pointer)
+						.hover(|s| s.border_color(C_FOCUS))
+						.apply_if(
+							search_text
+								.get()
+								.split_whitespace()
+								.any(|word| word == token_active),
+							|s| s.border_color(C_FOCUS).color(C_FOCUS),
+						)
+				})
+		},
+	)
+	.style(move |s| {
+		s.flex_row()
+			.flex_wrap(FlexWrap::Wrap)
+			.gap(4, 4)
+			.padding(5)
+			.width(sidebar_width.get() - 1.0)
+			.border_bottom(1.0)
+			.border_color(C_SIDE_BG_BORDER)
+			.apply_if(all_tags.get().is_empty(), |s| s.display(Display::None))
+	});
+
+	let sidebar_list = scroll({
 		virtual_stack(
 			VirtualDirection::Vertical,
 			VirtualItemSize::Fixed(Box::new(|| 21.0)),
-			move || list_sidebar_signal.get(),
+			move || {
+				env_tree.db.get_sidebar_tree(
+					&list_sidebar_signal.get(),
+					&collapsed_groups.get(),
+					!search_text.get().is_empty(),
+					sidebar_sort.get(),
+				)
+			},
 			move |item| item.clone(),
-			move |item| {
-				let title = item.1.clone();
-				container(
-					label(move || item.1.clone())
-						.style(|s| s.font_size(12.0).color(C_SIDE_TEXT))
-						.keyboard_navigatable()
-						.on_text_overflow(move |is_overflown| {
-							let mut labels = overflow_labels.get();
-							if is_overflown {
-								labels.push(item.0);
-							} else {
-								labels.retain(|i| *i != item.0);
-							}
-							overflow_labels.set(labels);
-						})
-						.on_event_cont(EventListener::PointerEnter, move |_| {
-							let labels = overflow_labels.get();
-							if labels.contains(&item.0) {
-								tooltip_signals.show(title.clone());
-							}
-						})
-						.on_event_cont(EventListener::PointerLeave, move |_| {
-							tooltip_signals.hide();
-						})
-						.on_click_stop(move |_| {
-							active_tab.set(item.0);
-							main_scroll_to.set(0.0);
-						})
-						.style(move |s| {
-							s.text_ellipsis()
-								.padding(10.0)
-								.padding_top(3.0)
-								.padding_bottom(3.0)
-								.width(sidebar_width.get())
-								.items_start()
-								.border_bottom(1.0)
-								.border_color(C_SIDE_BG_BORDER)
-								.color(C_SIDE_TEXT)
-								.focus_visible(|s| s.border(1).border_color(C_FOCUS))
-								.background(if let 0 = item.2 % 2 {
-									C_SIDE_BG
-								} else {
-									C_SIDE_BG_SELECTED.with_alpha_factor(0.2)
-								})
-								.apply_if(item.0 == active_tab.get(), |s| {
-									s.background(C_SIDE_BG_SELECTED)
-								})
-								.hover(|s| {
-									s.background(C_SIDE_BG_SELECTED.with_alpha_factor(0.6))
-										.apply_if(item.0 == active_tab.get(), |s| {
-											s.background(C_SIDE_BG_SELECTED)
-										})
-										.cursor(CursorStyle::Pointer)
-								})
-						}),
+			move |(idx, item)| match item {
+				SidebarItem::Section(title) => label(move || title.clone())
+					.style(move |s| {
+						s.items_end()
+							.height(21)
+							.width(sidebar_width.get())
+							.padding_left(10.0)
+							.padding_bottom(2.0)
+							.font_size(11.0)
+							.color(C_SIDE_TEXT_INACTIVE)
+							.border_bottom(1.0)
+							.border_color(C_SIDE_BG_BORDER)
+							.background(C_SIDE_BG)
+					})
+					.into_any(),
+				SidebarItem::Group(id, title, depth, is_collapsed) => sidebar_group(
+					SidebarGroup {
+						id,
+						title,
+						idx,
+						depth,
+						is_collapsed,
+						sidebar_width,
+						collapsed_groups,
+						search_group,
+						dragged_entry,
+					},
+					env_sidebar.clone(),
 				)
+				.into_any(),
+				SidebarItem::Entry(id, title, depth) => {
+					let title_tooltip = title.clone();
+					let env_expiry = env_sidebar.clone();
+					let env_drop = env_sidebar.clone();
+					let is_drop_target = create_rw_signal(false);
+
+					// cards that are about to expire get a dot
+					let expiry = create_rw_signal(None);
+					create_effect(move |_| {
+						list_sidebar_signal.track();
+						expiry.set(
+							env_expiry
+								.db
+								.get_card_expiry(&id)
+								.filter(|days| *days <= EXPIRY_WARNING_DAYS),
+						);
+					});
+
+					container((
+						label(move || title.clone())
+							.style(|s| s.font_size(12.0).color(C_SIDE_TEXT))
+							.keyboard_navigatable()
+							.on_text_overflow(move |is_overflown| {
+								let mut labels = overflow_labels.get();
+								if is_overflown {
+									labels.push(id);
+								} else {
+									labels.retain(|i| *i != id);
+								}
+								overflow_labels.set(labels);
+							})
+							.on_event_cont(EventListener::PointerEnter, move |_| {
+								let labels = overflow_labels.get();
+								if labels.contains(&id) {
+									tooltip_signals.show(title_tooltip.clone());
+								}
+							})
+							.on_event_cont(EventListener::PointerLeave, move |_| {
+								tooltip_signals.hide();
+							})
+							.on_click_stop(move |_| {
+								active_tab.set(id);
+								main_scroll_to.set(0.0);
+							})
+							.style(move |s| {
+								s.text_ellipsis()
+									.padding(10.0)
+									.padding_top(3.0)
+									.padding_bottom(3.0)
+									.padding_left(10.0 + GROUP_INDENT * depth as f64)
+									.width(sidebar_width.get())
+									.items_start()
+									.border_bottom(1.0)
+									.border_color(C_SIDE_BG_BORDER)
+									.color(C_SIDE_TEXT)
+									.focus_visible(|s| s.border(1).border_color(C_FOCUS))
+									.background(if let 0 = idx % 2 {
+										C_SIDE_BG
+									} else {
+										C_SIDE_BG_SELECTED.with_alpha_factor(0.2)
+									})
+									.apply_if(id == active_tab.get(), |s| {
+										s.background(C_SIDE_BG_SELECTED)
+									})
+									.apply_if(is_drop_target.get(), |s| s.border_color(C_FOCUS))
+									.hover(|s| {
+										s.background(C_SIDE_BG_SELECTED.with_alpha_factor(0.6))
+											.apply_if(id == active_tab.get(), |s| {
+												s.background(C_SIDE_BG_SELECTED)
+											})
+											.cursor(CursorStyle::Pointer)
+									})
+							}),
+						label(move || if expiry.get().is_some() { "●" } else { "" })
+							.on_event_cont(EventListener::PointerEnter, move |_| {
+								let tooltip = match expiry.get() {
+									Some(days) if days < 0 => String::from("A card has expired"),
+									Some(days) => format!("A card expires in {} days", days),
+									None => return,
+								};
+								tooltip_signals.show(tooltip);
+							})
+							.on_event_cont(EventListener::PointerLeave, move |_| {
+								tooltip_signals.hide();
+							})
+							.style(move |s| {
+								s.position(Position::Absolute)
+									.inset_right(8)
+									.inset_top(3)
+									.font_size(10.0)
+									.color(C_WARNING)
+									.apply_if(expiry.get().is_some_and(|days| days < 0), |s| {
+										s.color(C_ERROR)
+									})
+							}),
+					))
+					.style(|s| s.position(Position::Relative))
+					// entries are moved into a group by dropping them onto it
+					.draggable()
+					.on_event_cont(EventListener::DragStart, move |_| {
+						dragged_entry.set(Some(id));
+					})
+					// dropped onto an entry it joins the group of that entry
+					.on_event_cont(EventListener::DragEnter, move |_| {
+						if dragged_entry.get().is_some_and(|entry| entry != id) {
+							is_drop_target.set(true);
+						}
+					})
+					.on_event_cont(EventListener::DragLeave, move |_| {
+						is_drop_target.set(false);
+					})
+					.on_event_cont(EventListener::Drop, move |_| {
+						is_drop_target.set(false);
+						if let Some(entry) = dragged_entry.get() {
+							if entry != id {
+								env_drop
+									.db
+									.move_entry(&entry, env_drop.db.get_entry_group(&id));
+								let _ = env_drop.db.save();
+								list_sidebar_signal.update(|_| {});
+							}
+						}
+						dragged_entry.set(None);
+					})
+					.into_any()
+				},
 			},
 		)
 		.style(move |s| {
@@ -291,14 +692,21 @@ pub fn app_view(search_trigger: Trigger) -> impl IntoView {
 			sidebar_scrolled.set(false)
 		}
 	})
-	.style(move |s| {
-		s.z_index(1)
+	.style(|s| {
+		s.flex_grow(1.0)
+			.min_height(0)
+			.class(scroll::Handle, styles::scrollbar_styles)
+	});
+
+	let sidebar = (tag_filters, sidebar_list).style(move |s| {
+		s.flex_col()
+			.z_index(1)
+			.height_full()
 			.width(sidebar_width.get())
 			.border_right(1.0)
 			.border_top(1.0)
 			.border_color(C_SIDE_BG_BORDER)
 			.background(C_SIDE_BG)
-			.class(scroll::Handle, styles::scrollbar_styles)
 	});
 
 	let shadow_box_top = empty().style(move |s| {
@@ -344,6 +752,7 @@ pub fn app_view(search_trigger: Trigger) -> impl IntoView {
 		.dragging_style(|s| s.border_color(Color::TRANSPARENT))
 		.on_event_cont(EventListener::DragStart, move |_| {
 			is_sidebar_dragging.set(true);
+			dragged_entry.set(None);
 		})
 		.on_event_cont(EventListener::DragEnd, move |_| {
 			is_sidebar_dragging.set(false);
@@ -359,7 +768,7 @@ pub fn app_view(search_trigger: Trigger) -> impl IntoView {
 	let main_window = scroll(
 		dyn_container(
 			move || active_tab.get(),
-			move |active_tab| detail_view(active_tab, main_scroll_to).into_any(),
+			move |id| detail_view(id, active_tab, main_scroll_to).into_any(),
 		)
 		.style(|s| {
 			s.flex_col()
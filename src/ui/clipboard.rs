@@ -0,0 +1,119 @@
+use core::cell::{Cell, RefCell};
+use std::time::{Duration, Instant};
+use zeroize::Zeroize;
+
+use floem::{action::exec_after, Clipboard};
+
+use crate::{config::Config, ui::primitives::toast::ToastSignals};
+
+struct Copied {
+	id: u64,
+	value: String,
+	// there is no countdown when the clipboard is never cleared on its own
+	toast: Option<u8>,
+	toast_signals: ToastSignals,
+}
+
+thread_local! {
+	// what we last put on the clipboard so we never clear anything of the user's
+	static COPIED: RefCell<Option<Copied>> = const { RefCell::new(None) };
+	static COPY_ID: Cell<u64> = const { Cell::new(0) };
+}
+
+fn countdown_text(seconds: f32) -> String {
+	format!("Copied to clipboard.\nClearing it in {} seconds.", seconds)
+}
+
+pub fn copy_to_clipboard(
+	value: String,
+	config: &Config,
+	toast_signals: ToastSignals,
+) {
+	let timeout = config.general.read().clipboard_timeout;
+
+	// whatever we copied before is overwritten now
+	clear_copied();
+	if Clipboard::set_contents(value.clone()).is_err() {
+		return;
+	}
+
+	let id = COPY_ID.with(|copy_id| {
+		copy_id.set(copy_id.get() + 1);
+		copy_id.get()
+	});
+	// even without a timeout we remember the value so locking still clears it
+	let timeout = (timeout > 0).then(|| Duration::from_secs(timeout as u64));
+	let toast = timeout.map(|timeout| {
+		toast_signals
+			.add_timed(countdown_text(timeout.as_secs_f32().ceil()), timeout)
+	});
+
+	COPIED.with(|copied| {
+		*copied.borrow_mut() = Some(Copied {
+			id,
+			value,
+			toast,
+			toast_signals,
+		});
+	});
+
+	if let Some(timeout) = timeout {
+		countdown(id, Instant::now() + timeout);
+	}
+}
+
+fn countdown(id: u64, clear_at: Instant) {
+	let current = COPIED.with(|copied| {
+		copied
+			.borrow()
+			.as_ref()
+			.filter(|copied| copied.id == id)
+			.and_then(|copied| Some((copied.toast?, copied.toast_signals)))
+	});
+	let Some((toast, toast_signals)) = current else {
+		return;
+	};
+
+	let now = Instant::now();
+	if clear_at <= now {
+		clear_clipboard();
+		return;
+	}
+
+	let remaining = clear_at - now;
+	toast_signals.set_text(toast, countdown_text(remaining.as_secs_f32().ceil()));
+
+	exec_after(remaining.min(Duration::from_secs(1)), move |_| {
+		countdown(id, clear_at);
+	});
+}
+
+// only clears the clipboard if it still holds the value we copied
+pub fn clear_clipboard() {
+	let copied = COPIED.with(|copied| copied.borrow_mut().take());
+
+	if let Some(mut copied) = copied {
+		if let Ok(mut contents) = Clipboard::get_contents() {
+			if contents == copied.value {
+				let _ = Clipboard::set_contents(String::new());
+			}
+			contents.zeroize();
+		}
+		if let Some(toast) = copied.toast {
+			copied.toast_signals.remove(toast);
+		}
+		copied.value.zeroize();
+	}
+}
+
+// forget the last value without touching the clipboard
+fn clear_copied() {
+	let copied = COPIED.with(|copied| copied.borrow_mut().take());
+
+	if let Some(mut copied) = copied {
+		if let Some(toast) = copied.toast {
+			copied.toast_signals.remove(toast);
+		}
+		copied.value.zeroize();
+	}
+}
@@ -1,7 +1,10 @@
 use std::{rc::Rc, sync::Arc};
+use uuid::Uuid;
 use zeroize::Zeroize;
 
 use floem::{
+	file::FileDialogOptions,
+	file_action::{open_file, save_as},
 	kurbo::Size,
 	reactive::{create_effect, create_rw_signal, use_context, RwSignal},
 	views::{
@@ -12,13 +15,15 @@ use floem::{
 		},
 		empty, Decorators,
 	},
-	Clipboard, IntoView, ViewId,
+	IntoView, ViewId,
 };
 
 use crate::{
 	db::{Db, DbFields},
 	env::Environment,
 	ui::{
+		app_view::SidebarList,
+		clipboard::copy_to_clipboard,
 		details::detail_view::{
 			save_edit, SaveEdit, SECRET_MULTILINE_PLACEHOLDER, SECRET_PLACEHOLDER,
 		},
@@ -26,6 +31,7 @@ use crate::{
 		primitives::{
 			button::{icon_button, IconButton},
 			que::Que,
+			toast::ToastSignals,
 			tooltip::TooltipSignals,
 		},
 		window_management::{
@@ -39,7 +45,7 @@ pub fn empty_button_slot() -> impl IntoView {
 }
 
 pub struct EditButtonSlot {
-	pub id: usize,
+	pub id: Uuid,
 	pub field: DbFields,
 	pub switch: RwSignal<bool>,
 	pub is_hidden: bool,
@@ -116,7 +122,7 @@ pub fn edit_button_slot(param: EditButtonSlot) -> impl IntoView {
 					}
 					input_id.request_focus();
 				} else {
-					save_edit(SaveEdit {
+					if !save_edit(SaveEdit {
 						id,
 						field,
 						value: field_value,
@@ -125,7 +131,9 @@ pub fn edit_button_slot(param: EditButtonSlot) -> impl IntoView {
 						is_secret,
 						is_multiline,
 						input_id,
-					});
+					}) {
+						switch.set(true);
+					}
 				}
 			},
 		))
@@ -134,6 +142,7 @@ pub fn edit_button_slot(param: EditButtonSlot) -> impl IntoView {
 }
 
 pub struct ViewButtonSlot {
+	pub id: Uuid,
 	pub switch: RwSignal<bool>,
 	pub is_shown: bool,
 	pub is_multiline: bool,
@@ -145,6 +154,7 @@ pub fn view_button_slot(
 	getter: impl Fn() -> String + 'static,
 ) -> impl IntoView {
 	let ViewButtonSlot {
+		id,
 		switch,
 		is_shown,
 		is_multiline,
@@ -154,6 +164,9 @@ pub fn view_button_slot(
 	let tooltip_signals = use_context::<TooltipSignals>()
 		.expect("No tooltip_signals context provider");
 
+	let env = use_context::<Environment>().expect("No env context provider");
+	let list_sidebar_signal = use_context::<SidebarList>();
+
 	let see_icon = include_str!("../icons/see.svg");
 	let hide_icon = include_str!("../icons/hide.svg");
 
@@ -172,6 +185,7 @@ pub fn view_button_slot(
 				if switch.get() {
 					let data = getter();
 					field_value.set(data);
+					mark_used(&id, &env, list_sidebar_signal);
 				} else {
 					field_value.update(|field| field.zeroize());
 					field_value.set(if is_multiline {
@@ -189,10 +203,15 @@ pub fn view_button_slot(
 }
 
 pub fn clipboard_button_slot(
+	id: Uuid,
 	getter: impl Fn() -> String + 'static,
 ) -> impl IntoView {
 	let tooltip_signals = use_context::<TooltipSignals>()
 		.expect("No tooltip_signals context provider");
+	let toast_signals =
+		use_context::<ToastSignals>().expect("No toast_signals context provider");
+	let env = use_context::<Environment>().expect("No env context provider");
+	let list_sidebar_signal = use_context::<SidebarList>();
 
 	let clipboard_icon = include_str!("../icons/clipboard.svg");
 
@@ -205,13 +224,132 @@ pub fn clipboard_button_slot(
 		},
 		move |_| {
 			let data = getter();
-			let _ = Clipboard::set_contents(data);
+			copy_to_clipboard(data, &env.config, toast_signals);
+			mark_used(&id, &env, list_sidebar_signal);
+		},
+	)
+}
+
+// copying or revealing a value is what counts as using an entry, the history
+// window has no sidebar to refresh
+fn mark_used(
+	id: &Uuid,
+	env: &Environment,
+	list_sidebar_signal: Option<SidebarList>,
+) {
+	env.db.set_last_used(id);
+	let _ = env.db.save();
+	if let Some(list_sidebar_signal) = list_sidebar_signal {
+		list_sidebar_signal.update(|_| {});
+	}
+}
+
+pub struct ReplaceButtonSlot {
+	pub id: Uuid,
+	pub field: DbFields,
+	pub is_hidden: bool,
+	pub dates: RwSignal<Vec<(usize, u64)>>,
+	pub field_value: RwSignal<String>,
+}
+
+// attachments can't be edited, only swapped for another file
+pub fn replace_button_slot(param: ReplaceButtonSlot) -> impl IntoView {
+	let ReplaceButtonSlot {
+		id,
+		field,
+		is_hidden,
+		dates,
+		field_value,
+	} = param;
+
+	let tooltip_signals = use_context::<TooltipSignals>()
+		.expect("No tooltip_signals context provider");
+	let toast_signals =
+		use_context::<ToastSignals>().expect("No toast_signals context provider");
+	let env = use_context::<Environment>().expect("No env context provider");
+
+	let upload_icon = include_str!("../icons/upload.svg");
+
+	if is_hidden {
+		empty_button_slot().into_any()
+	} else {
+		container(icon_button(
+			IconButton {
+				icon: String::from(upload_icon),
+				tooltip: String::from("Replace the file"),
+				tooltip_signals,
+				..IconButton::default()
+			},
+			move |_| {
+				let env = env.clone();
+				tooltip_signals.hide();
+				open_file(
+					FileDialogOptions::new().title("Select attachment"),
+					move |file_info| {
+						if let Some(file) = file_info {
+							let path = file.path[0].to_string_lossy().to_string();
+							match env.db.replace_attachment(&id, &field, &path) {
+								Ok(()) => {
+									let _ = env.db.save();
+									field_value.set(env.db.get_last_by_field(&id, &field));
+									dates.set(env.db.get_history_dates(&id, &field));
+								},
+								Err(err) => {
+									toast_signals.add(err.to_string());
+								},
+							}
+						}
+					},
+				);
+			},
+		))
+		.into_any()
+	}
+}
+
+// n looks into the history of the field like Db::get_n_by_field
+pub fn save_attachment_button_slot(
+	id: Uuid,
+	field: DbFields,
+	n: usize,
+) -> impl IntoView {
+	let tooltip_signals = use_context::<TooltipSignals>()
+		.expect("No tooltip_signals context provider");
+	let toast_signals =
+		use_context::<ToastSignals>().expect("No toast_signals context provider");
+	let env = use_context::<Environment>().expect("No env context provider");
+
+	let download_icon = include_str!("../icons/download.svg");
+
+	icon_button(
+		IconButton {
+			icon: String::from(download_icon),
+			tooltip: String::from("Save file to disk"),
+			tooltip_signals,
+			..IconButton::default()
+		},
+		move |_| {
+			let env = env.clone();
+			tooltip_signals.hide();
+			save_as(
+				FileDialogOptions::new()
+					.default_name(env.db.get_n_by_field(&id, &field, n))
+					.title("Save attachment"),
+				move |file_info| {
+					if let Some(file) = file_info {
+						let path = file.path[0].to_string_lossy().to_string();
+						if let Err(err) = env.db.save_attachment(&id, &field, n, &path) {
+							toast_signals.add(err.to_string());
+						}
+					}
+				},
+			);
 		},
 	)
 }
 
 pub struct HistoryButtonSlot {
-	pub id: usize,
+	pub id: Uuid,
 	pub field: DbFields,
 	pub dates: RwSignal<Vec<(usize, u64)>>,
 	pub is_shown: bool,
@@ -296,7 +434,7 @@ pub fn history_button_slot(param: HistoryButtonSlot) -> impl IntoView {
 }
 
 pub struct DeleteButtonSlot {
-	pub id: usize,
+	pub id: Uuid,
 	pub field: DbFields,
 	pub hidden_field_list: RwSignal<im::Vector<DbFields>>,
 	pub field_list: RwSignal<im::Vector<DbFields>>,
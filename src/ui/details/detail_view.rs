@@ -1,4 +1,5 @@
 use std::rc::Rc;
+use uuid::Uuid;
 use zeroize::Zeroize;
 
 use floem::{
@@ -17,7 +18,7 @@ use floem::{
 };
 
 use crate::{
-	db::DbFields,
+	db::{DbFields, DynFieldKind},
 	env::Environment,
 	ui::{
 		app_view::{PresetFieldSignal, SidebarList},
@@ -25,8 +26,13 @@ use crate::{
 			hidden_fields::{hidden_fields, HiddeFields},
 			list_item::{list_item, ListItem},
 			new_field::new_field,
+			tag_editor::tag_editor,
+		},
+		primitives::{
+			button::{icon_button, IconButton},
+			toast::ToastSignals,
+			tooltip::TooltipSignals,
 		},
-		primitives::tooltip::TooltipSignals,
 	},
 };
 
@@ -40,7 +46,7 @@ pub const MULTILINE_HEIGHT: f64 = 165.0;
 pub const DETAILS_MIN_WIDTH: f64 = 600.0;
 
 pub struct SaveEdit {
-	pub id: usize,
+	pub id: Uuid,
 	pub field: DbFields,
 	pub value: RwSignal<String>,
 	pub doc: Rc<dyn Document>,
@@ -50,7 +56,8 @@ pub struct SaveEdit {
 	pub input_id: ViewId,
 }
 
-pub fn save_edit(params: SaveEdit) {
+// returns false when the value was rejected so the field stays in edit mode
+pub fn save_edit(params: SaveEdit) -> bool {
 	let SaveEdit {
 		id,
 		field,
@@ -65,31 +72,48 @@ pub fn save_edit(params: SaveEdit) {
 	let env = use_context::<Environment>().expect("No env context provider");
 	let list_sidebar_signal = use_context::<SidebarList>()
 		.expect("No list_sidebar_signal context provider");
+	let toast_signals =
+		use_context::<ToastSignals>().expect("No toast_signals context provider");
 
-	let field_value = if is_multiline {
+	let mut field_value = if is_multiline {
 		String::from(doc.text())
 	} else {
 		value.get()
 	};
 
-	let last_val = env.db.get_last_by_field(&id, &field);
+	let kind = env.db.get_field_kind(&id, &field);
+	if let Err(err) = kind.validate(&field_value) {
+		// the rejected input stays in the field to be fixed, secrets are hidden
+		// again once the field is left
+		toast_signals.add(err.to_string());
+		field_value.zeroize();
+		input_id.request_focus();
+		return false;
+	}
+
+	let mut last_val = env.db.get_last_by_field(&id, &field);
 	if last_val != field_value {
 		env.db.edit_field(id, &field, field_value.clone());
 		let _ = env.db.save();
 		if field == DbFields::Title {
 			let new_list = env.db.get_sidebar_list();
 			list_sidebar_signal.update(
-				|list: &mut im::Vector<(usize, String, usize)>| {
+				|list: &mut im::Vector<(Uuid, String, usize)>| {
 					*list = new_list;
 				},
 			);
+		} else if kind == DynFieldKind::CardExpiry {
+			// the sidebar shows which cards expire soon
+			list_sidebar_signal.update(|_| {});
 		}
 
 		dates.set(env.db.get_history_dates(&id, &field));
 		input_id.request_focus();
 	}
 
+	last_val.zeroize();
 	if is_secret {
+		field_value.zeroize();
 		match is_multiline {
 			true => {
 				value.set(String::from(SECRET_MULTILINE_PLACEHOLDER));
@@ -107,9 +131,15 @@ pub fn save_edit(params: SaveEdit) {
 	} else if is_multiline {
 		value.set(field_value);
 	}
+
+	true
 }
 
-pub fn detail_view(id: usize, main_scroll_to: RwSignal<f32>) -> impl IntoView {
+pub fn detail_view(
+	id: Uuid,
+	active_tab: RwSignal<Uuid>,
+	main_scroll_to: RwSignal<f32>,
+) -> impl IntoView {
 	let env = use_context::<Environment>().expect("No env context provider");
 	let tooltip_signals = use_context::<TooltipSignals>()
 		.expect("No tooltip_signals context provider");
@@ -121,6 +151,12 @@ pub fn detail_view(id: usize, main_scroll_to: RwSignal<f32>) -> impl IntoView {
 	let is_overflowing = create_rw_signal(false);
 
 	let password_icon = include_str!("../icons/password.svg");
+	let trash_icon = include_str!("../icons/trash.svg");
+	let star_icon = include_str!("../icons/star.svg");
+	let star_filled_icon = include_str!("../icons/star_filled.svg");
+
+	let env_delete = env.clone();
+	let env_favorite = env.clone();
 
 	let field_list: im::Vector<DbFields> = env.db.get_visible_fields(&id).into();
 	let field_list = create_rw_signal(field_list);
@@ -129,6 +165,7 @@ pub fn detail_view(id: usize, main_scroll_to: RwSignal<f32>) -> impl IntoView {
 		env.db.get_hidden_fields(&id).into();
 	let hidden_field_len = create_rw_signal(hidden_field_list.len());
 	let hidden_field_list = create_rw_signal(hidden_field_list);
+	let is_favorite = create_rw_signal(env.db.is_favorite(&id));
 
 	(
 		(
@@ -139,7 +176,7 @@ pub fn detail_view(id: usize, main_scroll_to: RwSignal<f32>) -> impl IntoView {
 					.get()
 					.iter()
 					.find(|item| item.0 == id)
-					.unwrap_or(&(0, String::from("Details"), 0))
+					.unwrap_or(&(Uuid::nil(), String::from("Details"), 0))
 					.1
 					.clone()
 			})
@@ -153,7 +190,7 @@ pub fn detail_view(id: usize, main_scroll_to: RwSignal<f32>) -> impl IntoView {
 							.get()
 							.iter()
 							.find(|item| item.0 == id)
-							.unwrap_or(&(0, String::from("Details"), 0))
+							.unwrap_or(&(Uuid::nil(), String::from("Details"), 0))
 							.1
 							.clone(),
 					);
@@ -163,6 +200,40 @@ pub fn detail_view(id: usize, main_scroll_to: RwSignal<f32>) -> impl IntoView {
 				tooltip_signals.hide();
 			})
 			.style(|s| s.text_ellipsis().font_size(24.0).max_width_full()),
+			icon_button(
+				IconButton {
+					icon: String::from(star_icon),
+					icon2: Some(String::from(star_filled_icon)),
+					tooltip: String::from("Pin entry to the sidebar"),
+					tooltip2: Some(String::from("Unpin entry")),
+					switch: Some(is_favorite),
+					tooltip_signals,
+					..IconButton::default()
+				},
+				move |_| {
+					env_favorite.db.set_favorite(&id, is_favorite.get());
+					let _ = env_favorite.db.save();
+					list_sidebar_signal.update(|_| {});
+				},
+			),
+			icon_button(
+				IconButton {
+					icon: String::from(trash_icon),
+					tooltip: String::from("Move entry to trash"),
+					tooltip_signals,
+					..IconButton::default()
+				},
+				move |_| {
+					tooltip_signals.hide();
+					env_delete.db.delete_entry(&id);
+					let _ = env_delete.db.save();
+
+					let new_list = env_delete.db.get_sidebar_list();
+					active_tab
+						.set(new_list.get(0).map(|item| item.0).unwrap_or(Uuid::nil()));
+					list_sidebar_signal.set(new_list);
+				},
+			),
 		)
 			.style(|s| {
 				s.flex()
@@ -175,6 +246,7 @@ pub fn detail_view(id: usize, main_scroll_to: RwSignal<f32>) -> impl IntoView {
 					.margin_top(15)
 					.margin_bottom(20)
 			}),
+		tag_editor(id),
 		(
 			list_item(ListItem {
 				id,
@@ -1,3 +1,5 @@
+use uuid::Uuid;
+
 use floem::{
 	reactive::{create_rw_signal, use_context, RwSignal},
 	style::Display,
@@ -17,7 +19,7 @@ use crate::{
 };
 
 pub struct HiddeFields {
-	pub id: usize,
+	pub id: Uuid,
 	pub hidden_field_list: RwSignal<im::Vector<DbFields>>,
 	pub field_list: RwSignal<im::Vector<DbFields>>,
 	pub hidden_field_len: RwSignal<usize>,
@@ -1,9 +1,16 @@
-use std::time::Instant;
+use std::{
+	cell::Cell,
+	rc::Rc,
+	sync::Arc,
+	time::{Duration, Instant},
+};
 use url_escape;
+use uuid::Uuid;
 use webbrowser;
 use zeroize::Zeroize;
 
 use floem::{
+	action::exec_after,
 	event::{Event, EventListener},
 	keyboard::{KeyCode, PhysicalKey},
 	reactive::{create_rw_signal, use_context, RwSignal},
@@ -19,16 +26,19 @@ use floem::{
 };
 
 use crate::{
-	db::{DbFields, DynFieldKind},
+	card::{days_until_expiry, detect_brand, mask_number},
+	db::{days_until, Db, DbFields, DynFieldKind, EXPIRY_WARNING_DAYS},
 	env::Environment,
+	otp::{Otp, OtpKind},
 	password_gen::generate_password,
 	ui::{
 		colors::*,
 		details::{
 			button_slots::{
 				clipboard_button_slot, delete_button_slot, edit_button_slot,
-				history_button_slot, view_button_slot, DeleteButtonSlot,
-				EditButtonSlot, HistoryButtonSlot, ViewButtonSlot,
+				history_button_slot, replace_button_slot, save_attachment_button_slot,
+				view_button_slot, DeleteButtonSlot, EditButtonSlot, HistoryButtonSlot,
+				ReplaceButtonSlot, ViewButtonSlot,
 			},
 			detail_view::{
 				save_edit, SaveEdit, INPUT_LINE_WIDTH, LINE_WIDTH, MULTILINE_HEIGHT,
@@ -38,6 +48,7 @@ use crate::{
 		},
 		primitives::{
 			button::{icon_button, IconButton},
+			date_picker::date_picker,
 			input_button_field::{input_button_field, InputButtonField},
 			input_field::input_field,
 			multiline_input_field::multiline_input_field,
@@ -59,8 +70,134 @@ pub fn replace_consecutive_newlines(input: String) -> String {
 	output
 }
 
+// the current code and how long it's still valid, counter based codes show
+// the ones generated from the given counters
+pub fn otp_label(mut value: String, counters: &[u64]) -> String {
+	let group = |code: String| {
+		if code.len() == 6 || code.len() == 8 {
+			code
+				.as_bytes()
+				.chunks(code.len() / 2)
+				.map(|chunk| String::from_utf8_lossy(chunk).to_string())
+				.collect::<Vec<String>>()
+				.join(" ")
+		} else {
+			code
+		}
+	};
+
+	let label = match Otp::parse(&value) {
+		Ok(otp) => match otp.kind {
+			OtpKind::Hotp { .. } if counters.is_empty() => {
+				String::from("No code yet, copy to generate one")
+			},
+			OtpKind::Hotp { .. } => counters
+				.iter()
+				.map(|counter| {
+					format!("{}  (counter {})", group(otp.code(*counter)), counter)
+				})
+				.collect::<Vec<String>>()
+				.join(",  "),
+			OtpKind::Totp { .. } | OtpKind::Steam => {
+				let (code, remaining) = otp.now();
+				format!("{}  ({}s)", group(code), remaining)
+			},
+		},
+		Err(err) => err.to_string(),
+	};
+	value.zeroize();
+	label
+}
+
+// file name and size, the contents never leave the vault unless saved to disk
+pub fn attachment_label(name: String, size: Option<usize>) -> String {
+	match size {
+		Some(size) if size >= 1024 * 1024 => {
+			format!("{}  ({:.1} MB)", name, size as f64 / 1024.0 / 1024.0)
+		},
+		Some(size) if size >= 1024 => {
+			format!("{}  ({:.1} KB)", name, size as f64 / 1024.0)
+		},
+		Some(size) => format!("{}  ({} bytes)", name, size),
+		None => name,
+	}
+}
+
+// days until a date or card expiry field is due
+fn days_left(kind: &DynFieldKind, value: &str) -> Option<i64> {
+	match kind {
+		DynFieldKind::Date => days_until(value),
+		DynFieldKind::CardExpiry => days_until_expiry(value),
+		_ => None,
+	}
+}
+
+// dates that passed or are about to get a note, the color comes on top
+pub fn date_label(value: String, days: Option<i64>) -> String {
+	match days {
+		Some(days) if days < 0 => format!("{}  (expired)", value),
+		Some(0) => format!("{}  (expires today)", value),
+		Some(1) => format!("{}  (expires tomorrow)", value),
+		Some(days) if days <= EXPIRY_WARNING_DAYS => {
+			format!("{}  (expires in {} days)", value, days)
+		},
+		_ => value,
+	}
+}
+
+// only the last four digits and the brand, the number itself stays hidden
+pub fn card_label(mut value: String) -> String {
+	let label = match detect_brand(&value) {
+		Some(brand) => format!("{}  ({})", mask_number(&value), brand),
+		None => mask_number(&value),
+	};
+	value.zeroize();
+	label
+}
+
+// what clicking on a field opens
+fn field_link(kind: &DynFieldKind, value: &str) -> Option<String> {
+	match kind {
+		DynFieldKind::Url => Some(url_escape::encode_fragment(value).to_string()),
+		DynFieldKind::Email => Some(format!("mailto:{}", value.trim())),
+		DynFieldKind::Phone => Some(format!(
+			"tel:{}",
+			value
+				.chars()
+				.filter(|c| c.is_ascii_digit() || *c == '+')
+				.collect::<String>()
+		)),
+		_ => None,
+	}
+}
+
+// runs every second for as long as the field is on screen
+fn refresh_otp(
+	label: RwSignal<String>,
+	alive: Rc<Cell<bool>>,
+	id: Uuid,
+	field: DbFields,
+	db: Arc<Db>,
+) {
+	if !alive.get() || !*db.vault_unlocked.read() {
+		return;
+	}
+
+	if let DbFields::Fields(field_id) = field {
+		// otp_label zeroizes the secret once the code is generated
+		let counters = db.get_hotp_counters(&id, &field, 0);
+		label.set(otp_label(
+			db.get_last_field_value(&id, &field_id),
+			&counters[counters.len().saturating_sub(1)..],
+		));
+	}
+	exec_after(Duration::from_secs(1), move |_| {
+		refresh_otp(label, alive, id, field, db);
+	});
+}
+
 pub struct ListItem {
-	pub id: usize,
+	pub id: Uuid,
 	pub field: DbFields,
 	pub hidden_field_list: RwSignal<im::Vector<DbFields>>,
 	pub field_list: RwSignal<im::Vector<DbFields>>,
@@ -100,10 +237,20 @@ pub fn list_item(param: ListItem) -> impl IntoView {
 	let title_value = create_rw_signal(field_title.clone());
 	let dyn_field_kind = env.db.get_field_kind(&id, &field);
 	let is_secret = match dyn_field_kind {
-		DynFieldKind::TextLine | DynFieldKind::MultiLine | DynFieldKind::Url => {
-			false
-		},
-		DynFieldKind::TextLineSecret | DynFieldKind::MultiLineSecret => true,
+		DynFieldKind::TextLine
+		| DynFieldKind::MultiLine
+		| DynFieldKind::Url
+		| DynFieldKind::Attachment
+		| DynFieldKind::Email
+		| DynFieldKind::Phone
+		| DynFieldKind::Date
+		| DynFieldKind::Number
+		| DynFieldKind::CardExpiry => false,
+		DynFieldKind::TextLineSecret
+		| DynFieldKind::MultiLineSecret
+		| DynFieldKind::Totp
+		| DynFieldKind::Pin
+		| DynFieldKind::CardNumber => true,
 	};
 
 	let is_multiline = matches!(
@@ -122,7 +269,22 @@ pub fn list_item(param: ListItem) -> impl IntoView {
 	};
 
 	let is_dyn_field = matches!(field, DbFields::Fields(_));
-	let is_url_field = matches!(dyn_field_kind, DynFieldKind::Url);
+	let is_link_field = matches!(
+		dyn_field_kind,
+		DynFieldKind::Url | DynFieldKind::Email | DynFieldKind::Phone
+	);
+	let is_date_field = matches!(dyn_field_kind, DynFieldKind::Date);
+	let has_expiry =
+		matches!(dyn_field_kind, DynFieldKind::Date | DynFieldKind::CardExpiry);
+	let is_card_number_field = matches!(dyn_field_kind, DynFieldKind::CardNumber);
+	let is_otp_field = matches!(dyn_field_kind, DynFieldKind::Totp);
+	let is_attachment_field = matches!(dyn_field_kind, DynFieldKind::Attachment);
+
+	let otp = create_rw_signal(String::from(""));
+	let otp_alive = Rc::new(Cell::new(true));
+	if is_otp_field {
+		refresh_otp(otp, otp_alive.clone(), id, field, env.db.clone());
+	}
 
 	let revert_icon = include_str!("../icons/revert.svg");
 	let generate_icon = include_str!("../icons/generate.svg");
@@ -132,6 +294,10 @@ pub fn list_item(param: ListItem) -> impl IntoView {
 	let env_title = env.clone();
 	let env_view_button = env.clone();
 	let env_history = env.clone();
+	let env_label = env.clone();
+	let link_kind = dyn_field_kind.clone();
+	let label_kind = dyn_field_kind.clone();
+	let style_kind = dyn_field_kind.clone();
 
 	let multiline_input = multiline_input_field(field_value.get());
 	let field_doc = create_rw_signal(multiline_input.doc());
@@ -141,7 +307,9 @@ pub fn list_item(param: ListItem) -> impl IntoView {
 	let generator_input = input_field(generator_entropy_value);
 	let generator_input_id = generator_input.id();
 
-	let input = if is_multiline {
+	let input = if is_date_field {
+		date_picker(field_value).into_any()
+	} else if is_multiline {
 		(
 			container(multiline_input).style(styles::multiline),
 			icon_button(
@@ -213,7 +381,7 @@ pub fn list_item(param: ListItem) -> impl IntoView {
 				if key == PhysicalKey::Code(KeyCode::Enter) {
 					edit_button_switch.set(false);
 					env_submit.db.edit_field_title(&id, &field, title_value.get());
-					save_edit(SaveEdit {
+					if !save_edit(SaveEdit {
 						id,
 						field,
 						value: field_value,
@@ -222,13 +390,20 @@ pub fn list_item(param: ListItem) -> impl IntoView {
 						is_secret,
 						is_multiline,
 						input_id,
-					});
+					}) {
+						edit_button_switch.set(true);
+					}
 				}
 			})
 			.into_any()
 	};
 
-	let generate_slot = if is_secret {
+	// generated passwords are no good as one-time password secrets, PINs or
+	// card numbers
+	let generate_slot = if is_secret
+		&& !is_otp_field
+		&& !matches!(dyn_field_kind, DynFieldKind::Pin | DynFieldKind::CardNumber)
+	{
 		let start_time = Instant::now();
 
 		(
@@ -392,7 +567,7 @@ pub fn list_item(param: ListItem) -> impl IntoView {
 					env_title.db.edit_field_title(&id, &field, title_value.get());
 					let _ = env_title.db.save();
 				}
-				save_edit(SaveEdit {
+				if !save_edit(SaveEdit {
 					id,
 					field,
 					value: field_value,
@@ -401,15 +576,46 @@ pub fn list_item(param: ListItem) -> impl IntoView {
 					is_secret,
 					is_multiline,
 					input_id,
-				})
+				}) {
+					edit_button_switch.set(true);
+				}
 			},
 		),
 		(
 			input_line,
 			scroll(
-				label(move || replace_consecutive_newlines(field_value.get())).style(
-					|s| s.padding_bottom(3).font_family(String::from("Monospace")),
-				),
+				label(move || {
+					// the secret itself is only shown when asked for
+					if is_otp_field && !view_button_switch.get() {
+						otp.get()
+					} else if is_attachment_field {
+						attachment_label(
+							field_value.get(),
+							env_label.db.get_attachment_size(&id, &field, 0),
+						)
+					} else if is_card_number_field && !view_button_switch.get() {
+						// saving an edit changes the dates
+						dates.track();
+						card_label(env_label.db.get_last_by_field(&id, &field))
+					} else if has_expiry {
+						let value = field_value.get();
+						let days = days_left(&label_kind, &value);
+						date_label(value, days)
+					} else {
+						replace_consecutive_newlines(field_value.get())
+					}
+				})
+				.style(|s| s.padding_bottom(3).font_family(String::from("Monospace")))
+				.style(move |s| {
+					let days = days_left(&style_kind, &field_value.get());
+					s.apply_if(days.is_some_and(|days| days < 0), |s| s.color(C_ERROR))
+						.apply_if(
+							days
+								.is_some_and(|days| (0..=EXPIRY_WARNING_DAYS).contains(&days)),
+							|s| s.color(C_WARNING),
+						)
+				})
+				.on_cleanup(move || otp_alive.set(false)),
 			)
 			.style(move |s| {
 				s.flex_grow(1.0)
@@ -423,36 +629,73 @@ pub fn list_item(param: ListItem) -> impl IntoView {
 					.apply_if(edit_button_switch.get(), |s| s.display(Display::None))
 					.apply_if(is_multiline, |s| s.height(MULTILINE_HEIGHT))
 					.hover(|s| {
-						s.apply_if(is_url_field, |s| {
+						s.apply_if(is_link_field, |s| {
 							s.color(C_FOCUS).cursor(CursorStyle::Pointer)
 						})
 					})
 			})
 			.on_click_cont(move |_| {
-				if is_url_field {
-					let _ =
-						webbrowser::open(&url_escape::encode_fragment(&field_value.get()));
+				if let Some(link) = field_link(&link_kind, &field_value.get()) {
+					let _ = webbrowser::open(&link);
 				}
 			}),
 		)
 			.style(|s| s.width(INPUT_LINE_WIDTH)),
-		edit_button_slot(EditButtonSlot {
-			id,
-			field,
-			switch: edit_button_switch,
-			is_hidden,
-			is_secret,
-			is_multiline,
-			input_id,
-			dates,
-			field_value,
-			multiline_field_value: field_doc,
-			reset_text,
-			view_button_switch,
-		}),
-		clipboard_button_slot(move || env.db.get_last_by_field(&id, &field)),
+		if is_attachment_field {
+			replace_button_slot(ReplaceButtonSlot {
+				id,
+				field,
+				is_hidden,
+				dates,
+				field_value,
+			})
+			.into_any()
+		} else {
+			edit_button_slot(EditButtonSlot {
+				id,
+				field,
+				switch: edit_button_switch,
+				is_hidden,
+				is_secret,
+				is_multiline,
+				input_id,
+				dates,
+				field_value,
+				multiline_field_value: field_doc,
+				reset_text,
+				view_button_switch,
+			})
+			.into_any()
+		},
+		if is_attachment_field {
+			save_attachment_button_slot(id, field, 0).into_any()
+		} else {
+			clipboard_button_slot(id, move || {
+				let mut value = env.db.get_last_by_field(&id, &field);
+				if !is_otp_field {
+					return value;
+				}
+
+				let code = match Otp::parse(&value) {
+					Ok(otp) => match otp.kind {
+						// every code is only good once so the counter moves on right away
+						OtpKind::Hotp { counter } => {
+							let counter = env.db.next_hotp_counter(&id, &field, counter);
+							let _ = env.db.save();
+							otp.code(counter)
+						},
+						OtpKind::Totp { .. } | OtpKind::Steam => otp.now().0,
+					},
+					Err(_) => String::from(""),
+				};
+				value.zeroize();
+				code
+			})
+			.into_any()
+		},
 		view_button_slot(
 			ViewButtonSlot {
+				id,
 				switch: view_button_switch,
 				is_shown: is_secret,
 				is_multiline,
@@ -1,7 +1,9 @@
 use std::rc::Rc;
+use uuid::Uuid;
 
 use floem::{
 	event::{Event, EventListener},
+	file::FileDialogOptions,
 	keyboard::{KeyCode, PhysicalKey},
 	reactive::{create_rw_signal, use_context, RwSignal},
 	style::{AlignItems, Display},
@@ -17,21 +19,25 @@ use crate::{
 	env::Environment,
 	ui::primitives::{
 		button::{icon_button, IconButton},
+		date_picker::date_picker,
+		file_input::file_input,
 		input_field::input_field,
 		multiline_input_field::multiline_input_field,
 		select::select,
 		styles,
+		toast::ToastSignals,
 		tooltip::TooltipSignals,
 	},
 };
 
 struct SaveNewField {
-	pub id: usize,
+	pub id: Uuid,
 	pub kind: RwSignal<DynFieldKind>,
 	pub preset_value: RwSignal<usize>,
 	pub title_value: RwSignal<String>,
 	pub field_value: RwSignal<String>,
 	pub multiline_field_value: RwSignal<Rc<dyn Document>>,
+	pub attachment_path: RwSignal<Vec<String>>,
 	pub field_list: RwSignal<im::Vector<DbFields>>,
 }
 
@@ -43,24 +49,53 @@ fn save_new_field(params: SaveNewField) {
 		title_value,
 		field_value,
 		multiline_field_value,
+		attachment_path,
 		field_list,
 	} = params;
 
 	let env = use_context::<Environment>().expect("No env context provider");
 	let tooltip_signals = use_context::<TooltipSignals>()
 		.expect("No tooltip_signals context provider");
+	let toast_signals =
+		use_context::<ToastSignals>().expect("No toast_signals context provider");
 
 	let value = match kind.get() {
 		DynFieldKind::Url
 		| DynFieldKind::TextLine
-		| DynFieldKind::TextLineSecret => field_value.get(),
+		| DynFieldKind::TextLineSecret
+		| DynFieldKind::Totp
+		| DynFieldKind::Email
+		| DynFieldKind::Phone
+		| DynFieldKind::Pin
+		| DynFieldKind::Date
+		| DynFieldKind::Number
+		| DynFieldKind::CardNumber
+		| DynFieldKind::CardExpiry => field_value.get(),
 		DynFieldKind::MultiLine | DynFieldKind::MultiLineSecret => {
 			String::from(multiline_field_value.get().text())
 		},
+		DynFieldKind::Attachment => {
+			attachment_path.get().first().cloned().unwrap_or_default()
+		},
 	};
 
+	if let Err(err) = kind.get().validate(&value) {
+		toast_signals.add(err.to_string());
+		return;
+	}
+
 	if !title_value.get().is_empty() && !value.is_empty() {
-		let new_field = env.db.add_field(&id, kind.get(), title_value.get(), value);
+		let new_field = if kind.get() == DynFieldKind::Attachment {
+			match env.db.add_attachment(&id, title_value.get(), &value) {
+				Ok(new_field) => new_field,
+				Err(err) => {
+					toast_signals.add(err.to_string());
+					return;
+				},
+			}
+		} else {
+			env.db.add_field(&id, kind.get(), title_value.get(), value)
+		};
 		let _ = env.db.save();
 		let mut field_list_db = env.db.get_visible_fields(&id);
 		field_list_db.push(new_field);
@@ -69,11 +104,12 @@ fn save_new_field(params: SaveNewField) {
 		preset_value.set(0);
 		title_value.set(String::from(""));
 		field_value.set(String::from(""));
+		attachment_path.set(Vec::new());
 	}
 }
 
 pub fn new_field(
-	id: usize,
+	id: Uuid,
 	field_presets: RwSignal<PresetFields>,
 	field_list: RwSignal<im::Vector<DbFields>>,
 	main_scroll_to: RwSignal<f32>,
@@ -88,6 +124,7 @@ pub fn new_field(
 	let kind = create_rw_signal(DynFieldKind::default());
 	let kind_signal = create_rw_signal(0);
 	let multiline_doc = create_rw_signal(text_editor("").doc());
+	let attachment_path = create_rw_signal(Vec::new());
 
 	let add_icon = include_str!("../icons/add.svg");
 	let minus_icon = include_str!("../icons/minus.svg");
@@ -169,6 +206,7 @@ pub fn new_field(
 							title_value,
 							field_value,
 							multiline_field_value: multiline_doc,
+							attachment_path,
 							field_list,
 						});
 						title_input_id.request_focus();
@@ -186,7 +224,14 @@ pub fn new_field(
 					match selected_kind {
 						DynFieldKind::Url
 						| DynFieldKind::TextLine
-						| DynFieldKind::TextLineSecret => input_field(field_value)
+						| DynFieldKind::TextLineSecret
+						| DynFieldKind::Totp
+						| DynFieldKind::Email
+						| DynFieldKind::Phone
+						| DynFieldKind::Pin
+						| DynFieldKind::Number
+						| DynFieldKind::CardNumber
+						| DynFieldKind::CardExpiry => input_field(field_value)
 							.placeholder("Value of field")
 							.style(move |s| s.width(177))
 							.on_event_cont(EventListener::KeyDown, move |event| {
@@ -212,6 +257,7 @@ pub fn new_field(
 										title_value,
 										field_value,
 										multiline_field_value: multiline_doc,
+										attachment_path,
 										field_list,
 									});
 									title_input_id.request_focus();
@@ -227,6 +273,26 @@ pub fn new_field(
 								.style(|s| s.width(177).height(150))
 								.into_any()
 						},
+						DynFieldKind::Date => {
+							date_picker(field_value).style(|s| s.width(177)).into_any()
+						},
+						DynFieldKind::Attachment => file_input(
+							attachment_path,
+							String::from("Select file..."),
+							FileDialogOptions::new().title("Select attachment"),
+							move |file| {
+								// the file name is a good title if there is none yet
+								if title_value.get().is_empty() {
+									if let Some(name) =
+										file.path[0].file_name().and_then(|name| name.to_str())
+									{
+										title_value.set(String::from(name));
+									}
+								}
+							},
+						)
+						.style(|s| s.width(177))
+						.into_any(),
 					}
 				},
 			)
@@ -255,6 +321,7 @@ pub fn new_field(
 						title_value,
 						field_value,
 						multiline_field_value: multiline_doc,
+						attachment_path,
 						field_list,
 					});
 				},
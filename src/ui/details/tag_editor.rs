@@ -0,0 +1,92 @@
+use uuid::Uuid;
+
+use floem::{
+	event::{Event, EventListener},
+	keyboard::{KeyCode, PhysicalKey},
+	reactive::{create_rw_signal, use_context},
+	style::{CursorStyle, FlexWrap},
+	views::{dyn_stack, label, Decorators},
+	IntoView,
+};
+
+use crate::{
+	env::Environment,
+	ui::{
+		app_view::SidebarList,
+		colors::*,
+		primitives::{input_field::input_field, styles},
+	},
+};
+
+pub fn tag_editor(id: Uuid) -> impl IntoView {
+	let env = use_context::<Environment>().expect("No env context provider");
+	let list_sidebar_signal = use_context::<SidebarList>()
+		.expect("No list_sidebar_signal context provider");
+
+	let tags: im::Vector<String> = env.db.get_tags(&id).into();
+	let tags = create_rw_signal(tags);
+	let new_tag = create_rw_signal(String::from(""));
+
+	let env_remove = env.clone();
+
+	(
+		dyn_stack(
+			move || tags.get(),
+			move |tag| tag.clone(),
+			move |tag| {
+				let env = env_remove.clone();
+				let tag_remove = tag.clone();
+
+				(
+					label(move || tag.clone()),
+					label(|| "✕")
+						.on_click_stop(move |_| {
+							env.db.remove_tag(&id, &tag_remove);
+							let _ = env.db.save();
+							tags.set(env.db.get_tags(&id).into());
+							// the tag filters in the sidebar count them
+							list_sidebar_signal.update(|_| {});
+						})
+						.style(|s| {
+							s.color(C_SIDE_TEXT_INACTIVE)
+								.cursor(CursorStyle::Pointer)
+								.hover(|s| s.color(C_ERROR))
+						}),
+				)
+					.style(styles::tag)
+			},
+		)
+		.style(|s| s.flex_row().flex_wrap(FlexWrap::Wrap).gap(4, 4)),
+		input_field(new_tag)
+			.placeholder("Add tag")
+			.on_event_cont(EventListener::KeyDown, move |event| {
+				let key = match event {
+					Event::KeyDown(k) => k.key.physical_key,
+					_ => PhysicalKey::Code(KeyCode::F35),
+				};
+
+				if key == PhysicalKey::Code(KeyCode::Escape) {
+					new_tag.set(String::from(""));
+				}
+
+				if key == PhysicalKey::Code(KeyCode::Enter)
+					&& !new_tag.get().trim().is_empty()
+				{
+					env.db.add_tag(&id, &new_tag.get());
+					let _ = env.db.save();
+					tags.set(env.db.get_tags(&id).into());
+					new_tag.set(String::from(""));
+					list_sidebar_signal.update(|_| {});
+				}
+			})
+			.style(|s| s.width(80).font_size(11.0)),
+	)
+		.style(|s| {
+			s.flex_row()
+				.flex_wrap(FlexWrap::Wrap)
+				.items_center()
+				.gap(4, 4)
+				.max_width_pct(90.0)
+				.margin_bottom(15)
+		})
+}
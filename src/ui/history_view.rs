@@ -1,5 +1,7 @@
 use chrono::{DateTime, Local, Utc};
 use std::sync::Arc;
+use uuid::Uuid;
+use zeroize::Zeroize;
 
 use floem::{
 	event::{Event, EventListener},
@@ -13,14 +15,18 @@ use floem::{
 
 use crate::{
 	db::{Db, DbFields, DynFieldKind},
+	otp::{Otp, OtpKind},
 	ui::{
 		colors::*,
 		details::{
-			button_slots::{clipboard_button_slot, view_button_slot, ViewButtonSlot},
+			button_slots::{
+				clipboard_button_slot, save_attachment_button_slot, view_button_slot,
+				ViewButtonSlot,
+			},
 			detail_view::{
 				MULTILINE_HEIGHT, SECRET_MULTILINE_PLACEHOLDER, SECRET_PLACEHOLDER,
 			},
-			list_item::replace_consecutive_newlines,
+			list_item::{attachment_label, otp_label, replace_consecutive_newlines},
 		},
 		primitives::{
 			styles,
@@ -34,7 +40,7 @@ const PADDING: f64 = 10.0;
 
 fn history_line(
 	idx: usize,
-	id: usize,
+	id: Uuid,
 	field: DbFields,
 	date: u64,
 	tooltip_signals: TooltipSignals,
@@ -44,10 +50,20 @@ fn history_line(
 
 	let dyn_field_kind = db.get_field_kind(&id, &field);
 	let is_secret = match dyn_field_kind {
-		DynFieldKind::TextLine | DynFieldKind::MultiLine | DynFieldKind::Url => {
-			false
-		},
-		DynFieldKind::TextLineSecret | DynFieldKind::MultiLineSecret => true,
+		DynFieldKind::TextLine
+		| DynFieldKind::MultiLine
+		| DynFieldKind::Url
+		| DynFieldKind::Attachment
+		| DynFieldKind::Email
+		| DynFieldKind::Phone
+		| DynFieldKind::Date
+		| DynFieldKind::Number
+		| DynFieldKind::CardExpiry => false,
+		DynFieldKind::TextLineSecret
+		| DynFieldKind::MultiLineSecret
+		| DynFieldKind::Totp
+		| DynFieldKind::Pin
+		| DynFieldKind::CardNumber => true,
 	};
 
 	let is_multiline = matches!(
@@ -65,6 +81,29 @@ fn history_line(
 		create_rw_signal(db.get_last_by_field(&id, &field))
 	};
 
+	// counter based one-time passwords show which counter produced which code
+	let hotp_label = if dyn_field_kind == DynFieldKind::Totp {
+		let mut value = db.get_n_by_field(&id, &field, idx);
+		let is_hotp = Otp::parse(&value)
+			.is_ok_and(|otp| matches!(otp.kind, OtpKind::Hotp { .. }));
+		let hotp_label = is_hotp.then(|| {
+			otp_label(value.clone(), &db.get_hotp_counters(&id, &field, idx))
+		});
+		value.zeroize();
+		hotp_label
+	} else {
+		None
+	};
+
+	// every version of an attachment can be saved on its own
+	let is_attachment = dyn_field_kind == DynFieldKind::Attachment;
+	if is_attachment {
+		field_value.set(attachment_label(
+			db.get_n_by_field(&id, &field, idx),
+			db.get_attachment_size(&id, &field, idx),
+		));
+	}
+
 	let db_view_button = db.clone();
 
 	let datetime_utc: DateTime<Utc> =
@@ -84,13 +123,16 @@ fn history_line(
 			}),
 		container(
 			scroll(
-				label(move || replace_consecutive_newlines(field_value.get().clone()))
-					.style(|s| s.font_family(String::from("Monospace")))
-					.style(move |s| {
-						s.apply_if(is_multiline, |s| {
-							s.padding_top(PADDING).padding_bottom(PADDING)
-						})
-					}),
+				label(move || match &hotp_label {
+					Some(hotp_label) if !view_button_switch.get() => hotp_label.clone(),
+					_ => replace_consecutive_newlines(field_value.get().clone()),
+				})
+				.style(|s| s.font_family(String::from("Monospace")))
+				.style(move |s| {
+					s.apply_if(is_multiline, |s| {
+						s.padding_top(PADDING).padding_bottom(PADDING)
+					})
+				}),
 			)
 			.style(move |s| {
 				s.flex_grow(1.0)
@@ -102,6 +144,7 @@ fn history_line(
 		.style(|s| s.flex_grow(1.0).width(80)),
 		view_button_slot(
 			ViewButtonSlot {
+				id,
 				switch: view_button_switch,
 				is_shown: is_secret,
 				is_multiline,
@@ -109,7 +152,12 @@ fn history_line(
 			},
 			move || db_view_button.get_n_by_field(&id, &field, idx),
 		),
-		clipboard_button_slot(move || db.get_n_by_field(&id, &field, idx)),
+		if is_attachment {
+			save_attachment_button_slot(id, field, idx).into_any()
+		} else {
+			clipboard_button_slot(id, move || db.get_n_by_field(&id, &field, idx))
+				.into_any()
+		},
 	)
 		.style(move |s| {
 			s.flex()
@@ -131,7 +179,7 @@ fn history_line(
 }
 
 pub fn history_view(
-	id: usize,
+	id: Uuid,
 	field: DbFields,
 	dates: Vec<(usize, u64)>,
 	tooltip_signals: TooltipSignals,
@@ -1,4 +1,5 @@
 use url_escape;
+use uuid::Uuid;
 use webbrowser;
 
 use floem::{
@@ -25,7 +26,7 @@ use crate::{
 	},
 };
 
-pub fn import_detail_view(id: usize, db: Db, que: Que) -> impl IntoView {
+pub fn import_detail_view(id: Uuid, db: Db, que: Que) -> impl IntoView {
 	let tooltip_signals = TooltipSignals::new(que);
 	provide_context(tooltip_signals);
 
@@ -86,8 +87,18 @@ pub fn import_detail_view(id: usize, db: Db, que: Que) -> impl IntoView {
 				let is_secret = match dyn_field_kind {
 					DynFieldKind::TextLine
 					| DynFieldKind::MultiLine
-					| DynFieldKind::Url => false,
-					DynFieldKind::TextLineSecret | DynFieldKind::MultiLineSecret => true,
+					| DynFieldKind::Url
+					| DynFieldKind::Attachment
+					| DynFieldKind::Email
+					| DynFieldKind::Phone
+					| DynFieldKind::Date
+					| DynFieldKind::Number
+					| DynFieldKind::CardExpiry => false,
+					DynFieldKind::TextLineSecret
+					| DynFieldKind::MultiLineSecret
+					| DynFieldKind::Totp
+					| DynFieldKind::Pin
+					| DynFieldKind::CardNumber => true,
 				};
 				let is_url_field = matches!(dyn_field_kind, DynFieldKind::Url);
 
@@ -1,8 +1,10 @@
+use uuid::Uuid;
+
 use floem::{
 	event::{Event, EventListener},
 	kurbo::Size,
 	reactive::{create_rw_signal, provide_context, use_context, RwSignal},
-	style::{CursorStyle, Position},
+	style::{CursorStyle, Display, Position},
 	views::virtual_stack,
 	views::{
 		container, label, scroll, Decorators, VirtualDirection, VirtualItemSize,
@@ -11,7 +13,7 @@ use floem::{
 };
 
 use crate::{
-	db::Db,
+	db::{Db, MergeChange},
 	env::Environment,
 	ui::{
 		colors::*,
@@ -23,16 +25,24 @@ use crate::{
 			styles,
 			tooltip::{tooltip_view, TooltipSignals},
 		},
-		settings::database::import,
+		settings::database::{import, merge},
 		window_management::{closing_window, opening_window, WindowSpec},
 	},
 };
 
 const TOP_HEIGHT: f32 = 50.0;
+const CHANGE_WIDTH: f32 = 60.0;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ImportMode {
+	Import,
+	Merge,
+}
 
 fn import_line(
-	item: (usize, bool),
-	import_items: RwSignal<im::Vector<(usize, bool)>>,
+	item: (Uuid, bool),
+	import_items: RwSignal<im::Vector<(Uuid, bool)>>,
+	change: Option<(String, MergeChange)>,
 	db: Db,
 ) -> impl IntoView {
 	let tooltip_signals = use_context::<TooltipSignals>()
@@ -41,8 +51,22 @@ fn import_line(
 	let does_overflow = create_rw_signal(false);
 	let show_detail_window = create_rw_signal(false);
 
-	let entry = db.get_by_id(&item.0);
-	let full_title = entry.title.clone();
+	// deleted entries only exist in the trash of the other vault
+	let title = match &change {
+		Some((title, _)) => title.clone(),
+		None => db.get_by_id(&item.0).title,
+	};
+	let full_title = title.clone();
+	let has_details = !matches!(change, Some((_, MergeChange::Deleted)));
+	let change_text =
+		change.as_ref().map(|(_, change)| change.to_string()).unwrap_or_default();
+	let is_conflict = matches!(change, Some((_, MergeChange::Conflict)));
+	let has_change = change.is_some();
+	let title_inset = if has_change {
+		29.5 + 5.0 + 10.0 + CHANGE_WIDTH
+	} else {
+		29.5 + 5.0 + 10.0
+	};
 
 	let detail_icon = include_str!("../icons/detail.svg");
 	let no_detail_icon = include_str!("../icons/no_detail.svg");
@@ -59,16 +83,14 @@ fn import_line(
 		checkbox(move || item.1).on_update(move |state| {
 			update_checkbox(item.0, state);
 		}),
-		entry
-			.title
-			.clone()
-			.style(|s| {
+		title
+			.style(move |s| {
 				s.position(Position::Absolute)
 					.margin_top(1)
 					.text_ellipsis()
 					.inset_left(16 + 5 + 10)
 					.cursor(CursorStyle::Pointer)
-					.inset_right(29.5 + 5.0 + 10.0)
+					.inset_right(title_inset)
 			})
 			.on_text_overflow(move |is_overflown| {
 				does_overflow.set(is_overflown);
@@ -84,76 +106,117 @@ fn import_line(
 			.on_click_cont(move |_| {
 				update_checkbox(item.0, !item.1);
 			}),
-		container(icon_button(
-			IconButton {
-				icon: String::from(detail_icon),
-				icon2: Some(String::from(no_detail_icon)),
-				tooltip: String::from("See details"),
-				tooltip2: Some(String::from("Close details")),
-				switch: Some(show_detail_window),
-				tooltip_signals,
-				..IconButton::default()
-			},
-			move |_| {
-				let window_id = format!("import-detail-window-{}", item.0);
-				let que_import_detail = Que::default();
-
-				if show_detail_window.get() {
-					let db_detail = db.clone();
-
-					opening_window(
-						move || {
-							import_detail_view(item.0, db_detail.clone(), que_import_detail)
-						},
-						WindowSpec {
-							id: window_id,
-							title: String::from("Import Detail"),
-						},
-						Size::new(400.0, 320.0),
-						move || {
-							que_import_detail.unque_all_tooltips();
-							show_detail_window.set(false);
-						},
-					);
-				} else {
-					closing_window(window_id, move || ());
-				}
-			},
-		))
-		.style(|s| s.position(Position::Absolute).inset_right(10)),
+		(
+			change_text.style(move |s| {
+				s.width(CHANGE_WIDTH)
+					.font_size(9.0)
+					.justify_end()
+					.color(C_SIDE_TEXT_INACTIVE)
+					.apply_if(is_conflict, |s| s.color(C_ERROR))
+					.apply_if(!has_change, |s| s.display(Display::None))
+			}),
+			container(icon_button(
+				IconButton {
+					icon: String::from(detail_icon),
+					icon2: Some(String::from(no_detail_icon)),
+					tooltip: String::from("See details"),
+					tooltip2: Some(String::from("Close details")),
+					switch: Some(show_detail_window),
+					tooltip_signals,
+					..IconButton::default()
+				},
+				move |_| {
+					let window_id = format!("import-detail-window-{}", item.0);
+					let que_import_detail = Que::default();
+
+					if show_detail_window.get() {
+						let db_detail = db.clone();
+
+						opening_window(
+							move || {
+								import_detail_view(item.0, db_detail.clone(), que_import_detail)
+							},
+							WindowSpec {
+								id: window_id,
+								title: String::from("Import Detail"),
+							},
+							Size::new(400.0, 320.0),
+							move || {
+								que_import_detail.unque_all_tooltips();
+								show_detail_window.set(false);
+							},
+						);
+					} else {
+						closing_window(window_id, move || ());
+					}
+				},
+			))
+			.style(move |s| s.apply_if(!has_details, |s| s.display(Display::None))),
+		)
+			.style(|s| {
+				s.position(Position::Absolute).inset_right(10).gap(5, 0).items_center()
+			}),
 	)
 		.style(|s| s.height(30).padding_left(10).width_full().items_center())
 }
 
-pub fn import_view(db: Db, que: Que, env: Environment) -> impl IntoView {
+pub fn import_view(
+	db: Db,
+	mode: ImportMode,
+	que: Que,
+	env: Environment,
+) -> impl IntoView {
 	let tooltip_signals = TooltipSignals::new(que);
 	provide_context(tooltip_signals);
 
 	let select_all = create_rw_signal(true);
 
-	let import_items = db
-		.get_sidebar_list()
-		.into_iter()
-		.map(|(id, _title, _idx)| (id, true))
-		.collect::<im::Vector<(usize, bool)>>();
+	// when merging we only list what would actually change
+	let changes = match mode {
+		ImportMode::Import => im::Vector::new(),
+		ImportMode::Merge => env.db.merge_preview(&db),
+	};
+
+	let import_items = match mode {
+		ImportMode::Import => db
+			.get_sidebar_list()
+			.into_iter()
+			.map(|(id, _title, _idx)| (id, true))
+			.collect::<im::Vector<(Uuid, bool)>>(),
+		ImportMode::Merge => changes
+			.iter()
+			.map(|(id, _title, _change)| (*id, true))
+			.collect::<im::Vector<(Uuid, bool)>>(),
+	};
 	let import_items = create_rw_signal(import_items.clone());
 
 	let db_import = db.clone();
 
+	let (heading, action) = match mode {
+		ImportMode::Import => ("Importing", "Import"),
+		ImportMode::Merge => ("Merging", "Merge"),
+	};
+
 	let import_view = (
 		(
-			"Importing".style(|s| s.font_size(21.0).margin_bottom(3)),
+			heading.style(|s| s.font_size(21.0).margin_bottom(3)),
 			container(
 				label(move || {
 					format!(
-						" Import {} ",
+						" {} {} ",
+						action,
 						import_items.get().iter().filter(|&(_, b)| *b).count()
 					)
 				})
 				.keyboard_navigatable()
 				.style(styles::button)
-				.on_click_cont(move |_| {
-					import(import_items.get(), db_import.clone(), env.clone());
+				.on_click_cont(move |_| match mode {
+					ImportMode::Import => {
+						import(import_items.get(), db_import.clone(), env.clone());
+					},
+					ImportMode::Merge => {
+						merge(import_items.get(), db_import.clone(), env.clone());
+					},
 				}),
 			)
 			.style(|s| s.width_full().justify_end()),
@@ -186,12 +249,27 @@ pub fn import_view(db: Db, que: Que, env: Environment) -> impl IntoView {
 					.style(styles::button),
 				)
 				.style(|s| s.margin_left(10).margin_top(10)),
+				"Nothing to merge, this vault is up to date".style(move |s| {
+					s.padding_horiz(10)
+						.color(C_MAIN_TEXT_INACTIVE)
+						.display(Display::None)
+						.apply_if(
+							mode == ImportMode::Merge && import_items.get().is_empty(),
+							|s| s.display(Display::Flex),
+						)
+				}),
 				virtual_stack(
 					VirtualDirection::Vertical,
 					VirtualItemSize::Fixed(Box::new(|| 30.0)),
 					move || import_items.get(),
 					move |item| *item,
-					move |item| import_line(item, import_items, db.clone()),
+					move |item| {
+						let change = changes
+							.iter()
+							.find(|(id, _, _)| *id == item.0)
+							.map(|(_, title, change)| (title.clone(), *change));
+						import_line(item, import_items, change, db.clone())
+					},
 				)
 				.style(|s| s.width_full().margin_bottom(10)),
 			)
@@ -1,36 +1,114 @@
+use std::{thread, time::Duration};
 use zeroize::Zeroize;
 
 use floem::{
 	event::{Event, EventListener},
+	ext_event::create_ext_action,
 	keyboard::{KeyCode, PhysicalKey},
-	reactive::{create_rw_signal, use_context, RwSignal},
-	style::Position,
-	views::Decorators,
+	reactive::{create_rw_signal, use_context, RwSignal, Scope},
+	style::{Display, Position},
+	views::{container, label, Decorators},
 	IntoView,
 };
 
-use crate::ui::{
-	colors::*,
-	primitives::{
-		logo::logo,
-		password_field::password_field,
-		toast::{toast_view, ToastSignals},
+use crate::{
+	encryption::{calibrate_kdf, CryptError, KdfParams},
+	env::Environment,
+	ui::{
+		colors::*,
+		primitives::{
+			button::button,
+			logo::logo,
+			password_field::password_field,
+			select::select,
+			toast::{toast_view, ToastSignals},
+		},
 	},
 };
 
-fn save(
+// the benchmark runs argon2 over and over so it gets its own thread to keep the
+// window responsive, on_done runs back on the ui thread
+fn calibrate(
+	unlock_time: RwSignal<usize>,
+	is_calibrating: RwSignal<bool>,
+	toast_signals: ToastSignals,
+	on_done: impl FnOnce(Option<KdfParams>) + 'static,
+) {
+	if is_calibrating.get_untracked() {
+		return;
+	}
+	is_calibrating.set(true);
+
+	let target = unlock_time.get_untracked();
+	let send = create_ext_action(
+		Scope::new(),
+		move |result: Result<KdfParams, CryptError>| {
+			is_calibrating.set(false);
+			// the unlock time was changed while we were measuring
+			if unlock_time.get_untracked() != target {
+				return;
+			}
+			match result {
+				Ok(params) => on_done(Some(params)),
+				Err(err) => {
+					toast_signals.add(err.to_string());
+					on_done(None);
+				},
+			}
+		},
+	);
+	thread::spawn(move || {
+		send(calibrate_kdf(Duration::from_millis(target as u64)));
+	});
+}
+
+struct Save {
 	password: RwSignal<String>,
 	new_password_value: RwSignal<String>,
 	repeat_password_value: RwSignal<String>,
+	unlock_time: RwSignal<usize>,
+	kdf: RwSignal<Option<KdfParams>>,
+	is_calibrating: RwSignal<bool>,
 	toast_signals: ToastSignals,
-) {
-	if new_password_value.get() == repeat_password_value.get() {
+}
+
+fn save(param: Save) {
+	let Save {
+		password,
+		new_password_value,
+		repeat_password_value,
+		unlock_time,
+		kdf,
+		is_calibrating,
+		toast_signals,
+	} = param;
+
+	if new_password_value.get() != repeat_password_value.get() {
+		toast_signals.add(String::from("The passwords are not the same"));
+		return;
+	}
+
+	let env = use_context::<Environment>().expect("No env context provider");
+	let finish = move |params: Option<KdfParams>| {
+		if let Some(params) = params {
+			env.db.config_db.write().kdf = params;
+		}
+
 		toast_signals.kill_all_toasts();
 		password.set(new_password_value.get());
 		new_password_value.update(|pass| pass.zeroize());
 		repeat_password_value.update(|pass| pass.zeroize());
-	} else {
-		toast_signals.add(String::from("The passwords are not the same"));
+	};
+
+	// we calibrate for the chosen unlock time if that hasn't happened yet
+	match kdf.get() {
+		Some(params) => finish(Some(params)),
+		None => {
+			calibrate(unlock_time, is_calibrating, toast_signals, move |params| {
+				kdf.set(params);
+				finish(params);
+			})
+		},
 	}
 }
 
@@ -40,6 +118,18 @@ pub fn onboard_view(password: RwSignal<String>) -> impl IntoView {
 
 	let new_password_value = create_rw_signal(String::from(""));
 	let repeat_password_value = create_rw_signal(String::from(""));
+	let unlock_time = create_rw_signal(1000);
+	let kdf = create_rw_signal(None);
+	let is_calibrating = create_rw_signal(false);
+	let save_params = move || Save {
+		password,
+		new_password_value,
+		repeat_password_value,
+		unlock_time,
+		kdf,
+		is_calibrating,
+		toast_signals,
+	};
 
 	let password_input = password_field(new_password_value, "Create a password");
 	let input_id = password_input.input_id;
@@ -57,12 +147,7 @@ pub fn onboard_view(password: RwSignal<String>) -> impl IntoView {
 				};
 
 				if key == PhysicalKey::Code(KeyCode::Enter) {
-					save(
-						password,
-						new_password_value,
-						repeat_password_value,
-						toast_signals,
-					);
+					save(save_params());
 					input_id.request_focus();
 				}
 			})
@@ -75,16 +160,51 @@ pub fn onboard_view(password: RwSignal<String>) -> impl IntoView {
 				};
 
 				if key == PhysicalKey::Code(KeyCode::Enter) {
-					save(
-						password,
-						new_password_value,
-						repeat_password_value,
-						toast_signals,
-					);
+					save(save_params());
 					input_id.request_focus();
 				}
 			})
 			.style(|s| s.width(250)),
+		(
+			"Unlock time".style(|s| s.width(80)),
+			select(
+				unlock_time,
+				vec![
+					(250, "0.25 sec"),
+					(500, "0.5 sec"),
+					(1000, "1 sec"),
+					(2000, "2 sec"),
+				],
+				move |_| {
+					// a different target needs a new benchmark
+					kdf.set(None);
+				},
+			),
+			container(button("Calibrate").on_click_cont(move |_| {
+				calibrate(unlock_time, is_calibrating, toast_signals, move |params| {
+					kdf.set(params);
+				});
+			})),
+		)
+			.style(|s| s.width(250).gap(5, 0).items_center().margin_top(10)),
+		label(move || match kdf.get() {
+			_ if is_calibrating.get() => {
+				String::from("Measuring how fast this computer is...")
+			},
+			Some(params) => format!(
+				"{} MiB memory and {} iterations",
+				params.m_cost / 1024,
+				params.t_cost
+			),
+			None => String::from(""),
+		})
+		.style(move |s| {
+			s.color(C_SIDE_TEXT_INACTIVE)
+				.font_size(11.0)
+				.apply_if(kdf.get().is_none() && !is_calibrating.get(), |s| {
+					s.display(Display::None)
+				})
+		}),
 	)
 		.style(|s| {
 			s.flex_col()
@@ -2,19 +2,25 @@ use zeroize::Zeroize;
 
 use floem::{
 	event::{Event, EventListener},
+	file::FileDialogOptions,
 	keyboard::{KeyCode, PhysicalKey},
 	reactive::{create_rw_signal, use_context, RwSignal},
-	style::Position,
+	style::{Display, Position},
 	views::Decorators,
 	IntoView,
 };
 
-use crate::ui::{
-	colors::*,
-	primitives::{
-		logo::logo,
-		password_field::password_field,
-		toast::{toast_view, ToastSignals},
+use crate::{
+	encryption::read_key_file,
+	env::Environment,
+	ui::{
+		colors::*,
+		primitives::{
+			file_input::file_input,
+			logo::logo,
+			password_field::password_field,
+			toast::{toast_view, ToastSignals},
+		},
 	},
 };
 
@@ -22,7 +28,16 @@ pub fn password_view(password: RwSignal<String>) -> impl IntoView {
 	let toast_signals =
 		use_context::<ToastSignals>().expect("No toast_signals context provider");
 
+	let env = use_context::<Environment>().expect("No env context provider");
+
 	let value = create_rw_signal(String::from(""));
+	let key_file_path = create_rw_signal(Vec::new());
+	let needs_key_file = env.db.config_db.read().key_file;
+
+	// a broken file can't be unlocked so we say so right away
+	if let Some(err) = env.db.load_error() {
+		toast_signals.add(err.to_string());
+	}
 
 	let input = password_field(value, "Enter password");
 	let input_id = input.input_id;
@@ -41,6 +56,16 @@ pub fn password_view(password: RwSignal<String>) -> impl IntoView {
 				};
 
 				if key == PhysicalKey::Code(KeyCode::Enter) {
+					if let Some(path) = key_file_path.get_untracked().first() {
+						match read_key_file(path) {
+							Ok(key_file) => env.db.set_key_file(Some(key_file)),
+							Err(err) => {
+								toast_signals.add(err.to_string());
+								return;
+							},
+						}
+					}
+
 					let password_entered = value.get_untracked();
 					value.update(|pass| pass.zeroize());
 					password.set(password_entered);
@@ -48,6 +73,17 @@ pub fn password_view(password: RwSignal<String>) -> impl IntoView {
 				}
 			})
 			.style(|s| s.width(250)),
+		file_input(
+			key_file_path,
+			String::from("Select key file..."),
+			FileDialogOptions::new().title("Select your key file"),
+			move |_| {
+				input_id.request_focus();
+			},
+		)
+		.style(move |s| {
+			s.width(250).apply_if(!needs_key_file, |s| s.display(Display::None))
+		}),
 	)
 		.style(|s| {
 			s.flex_col()
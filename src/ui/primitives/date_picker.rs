@@ -0,0 +1,180 @@
+use chrono::{Datelike, Duration, Local, Months, NaiveDate};
+
+use floem::{
+	event::{Event, EventListener},
+	keyboard::{KeyCode, PhysicalKey},
+	reactive::{create_rw_signal, RwSignal},
+	style::{CursorStyle, Display, Position},
+	views::{
+		dyn_container, h_stack_from_iter, label, v_stack_from_iter, Decorators,
+	},
+	IntoView,
+};
+
+use crate::{
+	db::DATE_FORMAT,
+	ui::{colors::*, primitives::styles},
+};
+
+const WEEKDAYS: [&str; 7] = ["Mo", "Tu", "We", "Th", "Fr", "Sa", "Su"];
+const DAY_WIDTH: f64 = 25.0;
+
+// the month a date is in, today's month if there is no date yet
+fn month_of(value: &str) -> NaiveDate {
+	let date = NaiveDate::parse_from_str(value.trim(), DATE_FORMAT)
+		.unwrap_or_else(|_| Local::now().date_naive());
+	date.with_day(1).unwrap_or(date)
+}
+
+fn nav_button(
+	text: &'static str,
+	on_click: impl Fn() + 'static,
+) -> impl IntoView {
+	label(move || text)
+		.keyboard_navigatable()
+		.on_click_stop(move |_| on_click())
+		.style(|s| {
+			s.width(DAY_WIDTH)
+				.justify_center()
+				.border_radius(2)
+				.hover(|s| {
+					s.background(C_FOCUS.with_alpha_factor(0.2))
+						.cursor(CursorStyle::Pointer)
+				})
+				.focus_visible(|s| s.outline(1).outline_color(C_FOCUS))
+		})
+}
+
+fn month_view(
+	month: NaiveDate,
+	value: RwSignal<String>,
+	is_open: RwSignal<bool>,
+) -> impl IntoView {
+	let today = Local::now().date_naive();
+	// weeks start on monday so the first row may begin in the last month
+	let first =
+		month - Duration::days(month.weekday().num_days_from_monday() as i64);
+
+	v_stack_from_iter((0..6).map(move |week| {
+		h_stack_from_iter((0..7).map(move |weekday| {
+			let day = first + Duration::days(week * 7 + weekday);
+			let is_selected =
+				move || value.get() == day.format(DATE_FORMAT).to_string();
+
+			label(move || day.day().to_string())
+				.keyboard_navigatable()
+				.on_click_stop(move |_| {
+					value.set(day.format(DATE_FORMAT).to_string());
+					is_open.set(false);
+				})
+				.style(move |s| {
+					s.width(DAY_WIDTH)
+						.justify_center()
+						.padding_vert(2)
+						.border_radius(2)
+						.apply_if(day.month() != month.month(), |s| {
+							s.color(C_MAIN_TEXT_INACTIVE)
+						})
+						.apply_if(day == today, |s| s.border(1).border_color(C_FOCUS))
+						.apply_if(is_selected(), |s| s.background(C_FOCUS).color(C_MAIN_BG))
+						.hover(|s| {
+							s.background(C_FOCUS.with_alpha_factor(0.2))
+								.cursor(CursorStyle::Pointer)
+						})
+						.focus_visible(|s| s.outline(1).outline_color(C_FOCUS))
+				})
+		}))
+	}))
+}
+
+// a button with the date that opens a month calendar, the value is kept in
+// the same format the date fields are stored in
+pub fn date_picker(value: RwSignal<String>) -> impl IntoView {
+	let is_open = create_rw_signal(false);
+	let shown_month = create_rw_signal(month_of(&value.get()));
+
+	let height = 25;
+
+	(
+		label(move || {
+			let date = value.get();
+			if date.is_empty() {
+				String::from("Select date...")
+			} else {
+				date
+			}
+		})
+		.keyboard_navigatable()
+		.on_click_cont(move |_| {
+			if !is_open.get() {
+				shown_month.set(month_of(&value.get()));
+			}
+			is_open.set(!is_open.get());
+		})
+		.on_event_cont(EventListener::KeyDown, move |event| {
+			let key = match event {
+				Event::KeyDown(k) => k.key.physical_key,
+				_ => PhysicalKey::Code(KeyCode::F35),
+			};
+
+			if key == PhysicalKey::Code(KeyCode::Escape) {
+				is_open.set(false);
+			}
+		})
+		.style(styles::button)
+		.style(move |s| s.width_full().height(height).padding_left(8)),
+		(
+			(
+				nav_button("«", move || {
+					shown_month.update(|month| *month = *month - Months::new(12));
+				}),
+				nav_button("‹", move || {
+					shown_month.update(|month| *month = *month - Months::new(1));
+				}),
+				label(move || shown_month.get().format("%B %Y").to_string())
+					.style(|s| s.flex_grow(1.0).justify_center()),
+				nav_button("›", move || {
+					shown_month.update(|month| *month = *month + Months::new(1));
+				}),
+				nav_button("»", move || {
+					shown_month.update(|month| *month = *month + Months::new(12));
+				}),
+			)
+				.style(|s| s.width_full().items_center().margin_bottom(5)),
+			h_stack_from_iter(WEEKDAYS.into_iter().map(|weekday| {
+				label(move || weekday).style(|s| {
+					s.width(DAY_WIDTH)
+						.justify_center()
+						.font_size(9.0)
+						.color(C_SIDE_TEXT_INACTIVE)
+				})
+			})),
+			dyn_container(
+				move || shown_month.get(),
+				move |month| month_view(month, value, is_open).into_any(),
+			),
+		)
+			.on_click_stop(|_| {})
+			.style(move |s| {
+				s.flex_col()
+					.position(Position::Absolute)
+					.inset_top(0)
+					.margin_top(height + 5)
+					.padding(5)
+					.width(DAY_WIDTH * 7.0 + 10.0)
+					.background(C_MAIN_BG)
+					.box_shadow_blur(4)
+					.box_shadow_color(C_SHADOW_2)
+					.box_shadow_spread(2)
+					.box_shadow_h_offset(2)
+					.box_shadow_v_offset(2)
+					.border_radius(3)
+					.border(1)
+					.border_color(C_TOP_TEXT)
+					.z_index(200)
+					.display(Display::None)
+					.apply_if(is_open.get(), |s| s.display(Display::Flex))
+			}),
+	)
+		.style(|s| s.flex_col().position(Position::Relative))
+}
@@ -7,7 +7,10 @@ use floem::{
 	IntoView,
 };
 
-use crate::ui::{app_view::TooltipSignalsSettings, primitives::styles};
+use crate::ui::{
+	app_view::TooltipSignalsSettings,
+	primitives::{styles, tooltip::TooltipSignals},
+};
 
 pub fn file_input<F>(
 	value: RwSignal<Vec<String>>,
@@ -18,9 +21,12 @@ pub fn file_input<F>(
 where
 	F: Fn(FileInfo) + 'static + Copy,
 {
-	let tooltip_signals = use_context::<TooltipSignalsSettings>()
-		.expect("No tooltip_signals context provider")
-		.inner;
+	// outside of the settings window we use the tooltips of the main window
+	let tooltip_signals = match use_context::<TooltipSignalsSettings>() {
+		Some(tooltip_signals_settings) => tooltip_signals_settings.inner,
+		None => use_context::<TooltipSignals>()
+			.expect("No tooltip_signals context provider"),
+	};
 
 	let title = create_rw_signal(input_label.clone());
 	let input_label_effect = input_label.clone();
@@ -47,6 +47,18 @@ pub fn settings_line(s: Style) -> Style {
 		.gap(0, 5)
 }
 
+pub fn tag(s: Style) -> Style {
+	s.padding_horiz(6)
+		.padding_vert(1)
+		.border(1)
+		.border_radius(8)
+		.border_color(C_TOP_TEXT.with_alpha_factor(0.4))
+		.font_size(11.0)
+		.color(C_TOP_TEXT)
+		.items_center()
+		.gap(4, 0)
+}
+
 pub fn multiline(s: Style) -> Style {
 	s.size_full().padding(5).border_radius(2).border(1).border_color(C_TOP_TEXT)
 }
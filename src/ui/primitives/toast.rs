@@ -1,24 +1,43 @@
-use std::{panic::catch_unwind, time::Duration};
+use std::{panic::catch_unwind, rc::Rc, time::Duration};
 
 use floem::{
 	action::exec_after,
 	animate::animation,
 	reactive::{create_rw_signal, RwSignal},
-	style::{FlexDirection, Position},
-	views::{container, dyn_stack, empty, scroll, svg, Decorators},
+	style::{Display, FlexDirection, Position},
+	views::{container, dyn_stack, empty, label, scroll, svg, Decorators},
 	IntoView,
 };
 
 use crate::ui::{
 	colors::*,
-	primitives::{que::Que, styles},
+	primitives::{button::button, que::Que, styles},
 };
 
-const DISMISS_TIMEOUT: u64 = 10;
+const DISMISS_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone)]
+pub struct Toast {
+	pub id: u8,
+	pub text: String,
+	pub timeout: Duration,
+	pub action: Option<(&'static str, Rc<dyn Fn()>)>,
+}
+
+impl std::fmt::Debug for Toast {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		f.debug_struct("Toast")
+			.field("id", &self.id)
+			.field("text", &self.text)
+			.field("timeout", &self.timeout)
+			.field("action", &self.action.as_ref().map(|(name, _)| name))
+			.finish()
+	}
+}
 
 #[derive(Debug, Copy, Clone)]
 pub struct ToastSignals {
-	pub toasts: RwSignal<Vec<(u8, String)>>,
+	pub toasts: RwSignal<Vec<Toast>>,
 	pub que: Que,
 }
 
@@ -40,22 +59,65 @@ impl ToastSignals {
 	}
 
 	pub fn add(self, text: String) -> u8 {
+		self.add_toast(text, DISMISS_TIMEOUT, None)
+	}
+
+	// a toast with a button, it goes away once the button was clicked
+	pub fn add_with_action(
+		self,
+		text: String,
+		timeout: Duration,
+		action_label: &'static str,
+		on_action: impl Fn() + 'static,
+	) -> u8 {
+		self.add_toast(text, timeout, Some((action_label, Rc::new(on_action))))
+	}
+
+	pub fn add_timed(self, text: String, timeout: Duration) -> u8 {
+		self.add_toast(text, timeout, None)
+	}
+
+	fn add_toast(
+		self,
+		text: String,
+		timeout: Duration,
+		action: Option<(&'static str, Rc<dyn Fn()>)>,
+	) -> u8 {
 		let id = self.que.toast.get().last().unwrap_or(&0) + 1;
-		self.toasts.update(|item| item.push((id, text.clone())));
+		self.toasts.update(|item| {
+			item.push(Toast {
+				id,
+				text,
+				timeout,
+				action,
+			})
+		});
 		self.que.toast.update(|item| item.push(id));
 
-		exec_after(Duration::from_secs(DISMISS_TIMEOUT), move |_| {
+		exec_after(timeout, move |_| {
 			if self.que.toast.get().contains(&id) {
-				self.unque_toast(id);
-				// make sure we don't execute toasts after a view has been destroyed (window closed)
-				let _ = catch_unwind(|| {
-					self.toasts.update(|item| item.retain(|(ids, _)| *ids != id));
-				});
+				self.remove(id);
 			}
 		});
 
 		id
 	}
+
+	pub fn set_text(self, id: u8, text: String) {
+		self.toasts.update(|item| {
+			if let Some(toast) = item.iter_mut().find(|toast| toast.id == id) {
+				toast.text = text;
+			}
+		});
+	}
+
+	pub fn remove(self, id: u8) {
+		self.unque_toast(id);
+		// make sure we don't execute toasts after a view has been destroyed (window closed)
+		let _ = catch_unwind(|| {
+			self.toasts.update(|item| item.retain(|toast| toast.id != id));
+		});
+	}
 }
 
 pub fn toast_view(toast_signals: ToastSignals) -> impl IntoView {
@@ -64,8 +126,11 @@ pub fn toast_view(toast_signals: ToastSignals) -> impl IntoView {
 	scroll(
 		dyn_stack(
 			move || toast_signals.toasts.get(),
-			move |toasts| toasts.clone(),
+			move |toast| toast.id,
 			move |toast| {
+				let id = toast.id;
+				let has_action = toast.action.is_some();
+				let action = toast.action;
 				(
 					container(
 						svg(move || String::from(alert_icon))
@@ -80,7 +145,30 @@ pub fn toast_view(toast_signals: ToastSignals) -> impl IntoView {
 							.justify_center()
 					}),
 					(
-						toast.1.clone().style(|s| s.width_full().height_full().padding(5)),
+						label(move || {
+							toast_signals
+								.toasts
+								.get()
+								.iter()
+								.find(|toast| toast.id == id)
+								.map(|toast| toast.text.clone())
+								.unwrap_or_default()
+						})
+						.style(|s| s.width_full().height_full().padding(5)),
+						container(
+							button(action.as_ref().map(|(name, _)| *name).unwrap_or(""))
+								.on_click_cont(move |_| {
+									if let Some((_, on_action)) = &action {
+										on_action();
+									}
+									toast_signals.remove(id);
+								}),
+						)
+						.style(move |s| {
+							s.padding(5)
+								.padding_top(0)
+								.apply_if(!has_action, |s| s.display(Display::None))
+						}),
 						empty()
 							.style(|s| {
 								s.width(0).height(2).background(C_ERROR.with_alpha_factor(0.7))
@@ -89,7 +177,7 @@ pub fn toast_view(toast_signals: ToastSignals) -> impl IntoView {
 								animation()
 									.width(|| 200.0 - 35.0 + 3.0)
 									.ease_in_out()
-									.duration(Duration::from_secs(DISMISS_TIMEOUT)),
+									.duration(toast.timeout),
 							),
 					)
 						.style(|s| {
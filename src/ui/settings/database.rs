@@ -1,4 +1,7 @@
+use chrono::{DateTime, Local, Utc};
 use std::fs;
+use uuid::Uuid;
+use zeroize::Zeroize;
 
 use floem::{
 	event::{Event, EventListener},
@@ -8,7 +11,7 @@ use floem::{
 	kurbo::Size,
 	reactive::{create_rw_signal, use_context, RwSignal},
 	style::{CursorStyle, Display},
-	views::{container, label, slider::slider, svg, Decorators},
+	views::{container, dyn_stack, label, slider::slider, svg, Decorators},
 	IntoView,
 };
 
@@ -16,13 +19,15 @@ use crate::{
 	config::DB_FILE_NAME,
 	create_lock_timeout,
 	db::Db,
+	encryption::Cipher,
 	env::Environment,
+	lock_app,
 	ui::{
 		app_view::{
 			QueSettings, SidebarList, ToastSignalsSettings, TooltipSignalsSettings,
 		},
 		colors::*,
-		import::import_view::import_view,
+		import::import_view::{import_view, ImportMode},
 		primitives::{
 			button::{button, icon_button, IconButton},
 			file_input::file_input,
@@ -34,6 +39,7 @@ use crate::{
 		},
 		window_management::{closing_window, opening_window, WindowSpec},
 	},
+	BackupList,
 };
 
 const MIN: f32 = 60.0; // 1min
@@ -69,6 +75,34 @@ fn human_readable(seconds: f32) -> String {
 	String::from(result.trim())
 }
 
+// in hours, the first option of the select is no limit
+const MAX_SESSIONS: [f32; 4] = [1.0, 4.0, 8.0, 24.0];
+
+const CIPHERS: [Cipher; 2] = [Cipher::Aes256GcmSiv, Cipher::XChaCha20Poly1305];
+
+fn change_cipher(
+	cipher: RwSignal<usize>,
+	password: RwSignal<String>,
+	success: RwSignal<bool>,
+	env: Environment,
+) {
+	let toast_signals = use_context::<ToastSignalsSettings>()
+		.expect("No toast_signals context provider")
+		.inner;
+
+	success.set(false);
+	match env.db.change_cipher(password.get(), CIPHERS[cipher.get()]) {
+		Err(e) => {
+			toast_signals.add(e.to_string());
+		},
+		Ok(()) => {
+			password.update(|pass| pass.zeroize());
+			toast_signals.kill_all_toasts();
+			success.set(true);
+		},
+	}
+}
+
 fn export(file: FileInfo, env: Environment) {
 	match fs::write(file.path[0].clone(), env.db.export().unwrap()) {
 		Ok(_) => {},
@@ -77,7 +111,7 @@ fn export(file: FileInfo, env: Environment) {
 }
 
 pub fn import(
-	import_list: im::Vector<(usize, bool)>,
+	import_list: im::Vector<(Uuid, bool)>,
 	import_db: Db,
 	env: Environment,
 ) {
@@ -86,34 +120,7 @@ pub fn import(
 
 	for &(import_id, is_selected) in &import_list {
 		if is_selected {
-			let import_entry = import_db.get_by_id(&import_id);
-			let new_id = env.db.add(import_entry.title);
-
-			import_db.get_fields(&import_id).iter().for_each(
-				|(import_field, is_visible)| {
-					let name = import_db.get_name_of_field(&import_id, import_field);
-					let kind = import_db.get_field_kind(&import_id, import_field);
-
-					let mut history: Vec<(u64, String)> = import_db
-						.get_history(&import_id, import_field)
-						.unwrap_or_default()
-						.into_iter()
-						.collect();
-
-					let last = history.len() - 1;
-					let value = import_db.get_n_by_field(&import_id, import_field, last);
-
-					let field = env.db.add_field(&new_id, kind, name, value);
-					if !is_visible {
-						env.db.edit_field_visbility(&new_id, &field, false);
-					}
-
-					history.reverse();
-					history.iter().skip(1).for_each(|(_, value)| {
-						env.db.edit_field(new_id, &field, value.clone());
-					})
-				},
-			);
+			env.db.import_entry(&import_db, &import_id);
 		}
 	}
 
@@ -122,27 +129,70 @@ pub fn import(
 	closing_window(String::from("import-window"), || ());
 }
 
+pub fn merge(
+	merge_list: im::Vector<(Uuid, bool)>,
+	merge_db: Db,
+	env: Environment,
+) {
+	let list_sidebar_signal = use_context::<SidebarList>()
+		.expect("No list_sidebar_signal context provider");
+	let toast_signals =
+		use_context::<ToastSignals>().expect("No toast_signals context provider");
+
+	// leave out everything that was unchecked in the preview
+	for &(merge_id, is_selected) in &merge_list {
+		if !is_selected {
+			merge_db.contents.write().retain(|entry| entry.id != merge_id);
+			merge_db.trash.write().retain(|trashed| trashed.entry.id != merge_id);
+		}
+	}
+
+	let conflicts = env.db.merge(&merge_db);
+
+	let _ = env.save();
+	list_sidebar_signal.set(env.db.get_sidebar_list());
+	if !conflicts.is_empty() {
+		toast_signals.add(format!(
+			"Merged with conflicts, check the history for the other values:\n{}",
+			conflicts
+				.iter()
+				.map(|conflict| conflict.to_string())
+				.collect::<Vec<String>>()
+				.join("\n")
+		));
+	}
+	closing_window(String::from("merge-window"), || ());
+}
+
 fn import_window(
 	import_path: RwSignal<Vec<String>>,
 	import_password: RwSignal<String>,
+	mode: ImportMode,
 	toast_signals: ToastSignals,
 	env: Environment,
 ) {
+	let (window_id, window_title) = match mode {
+		ImportMode::Import => ("import-window", "Import into Vault"),
+		ImportMode::Merge => ("merge-window", "Merge into Vault"),
+	};
+
 	if !import_path.get().is_empty() {
 		let imported_db = Db::load(import_path.get()[0].clone());
 		let decrypted = imported_db.decrypt_database(import_password.get());
 		match decrypted {
-			Ok(()) => {
+			Ok(_) => {
 				import_path.set(Vec::new());
 				import_password.set(String::from(""));
 
 				let que_import = Que::default();
 
 				opening_window(
-					move || import_view(imported_db.clone(), que_import, env.clone()),
+					move || {
+						import_view(imported_db.clone(), mode, que_import, env.clone())
+					},
 					WindowSpec {
-						id: String::from("import-window"),
-						title: String::from("Import into Vault"),
+						id: String::from(window_id),
+						title: String::from(window_title),
 					},
 					Size::new(300.0, 350.0),
 					move || {
@@ -180,18 +230,49 @@ pub fn database_view() -> impl IntoView {
 	let timeout_backup = create_rw_signal(db_timeout);
 	let timeout = create_rw_signal(convert_timeout_2_pct(db_timeout));
 	let snap = create_rw_signal(0);
+	let max_session = create_rw_signal(
+		env
+			.config
+			.general
+			.read()
+			.max_session
+			.and_then(|seconds| {
+				MAX_SESSIONS.iter().position(|hours| hours * 60.0 * 60.0 == seconds)
+			})
+			.map_or(0, |idx| idx + 1),
+	);
 	let show_dbpath_label = create_rw_signal(false);
 	let db_path = create_rw_signal(env.config.general.read().db_path.clone());
 	let db_path_reset =
 		create_rw_signal(env.config.general.read().db_path.clone());
 	let import_path = create_rw_signal(Vec::new());
 	let import_password = create_rw_signal(String::from(""));
+	let merge_path = create_rw_signal(Vec::new());
+	let merge_password = create_rw_signal(String::from(""));
+	let backup_count = create_rw_signal(env.config.general.read().backup_count);
+	let backups =
+		use_context::<BackupList>().expect("No backups context provider");
+	let db_cipher = env.db.config_db.read().cipher;
+	let cipher = create_rw_signal(
+		CIPHERS.iter().position(|item| *item == db_cipher).unwrap_or(0),
+	);
+	let cipher_password = create_rw_signal(String::from(""));
+	let cipher_success = create_rw_signal(false);
+	let integrity_success = create_rw_signal(false);
 
 	let env_dbpath_reset = env.clone();
 	let env_dbpath_save = env.clone();
 	let env_export = env.clone();
+	let env_backup_count = env.clone();
+	let env_restore = env.clone();
 	let env_import_enter = env.clone();
 	let env_import_click = env.clone();
+	let env_merge_enter = env.clone();
+	let env_merge_click = env.clone();
+	let env_cipher_enter = env.clone();
+	let env_cipher_click = env.clone();
+	let env_verify = env.clone();
+	let env_max_session = env.clone();
 
 	let all_snaps = [
 		Snap::NoSnaping,
@@ -313,6 +394,29 @@ pub fn database_view() -> impl IntoView {
 						}),
 				)
 					.style(|s| s.gap(5, 0).items_center()),
+				(
+					"Lock at the latest after",
+					select(
+						max_session,
+						vec![
+							(0, "No limit"),
+							(1, "1 hour"),
+							(2, "4 hours"),
+							(3, "8 hours"),
+							(4, "24 hours"),
+						],
+						move |idx| {
+							let seconds =
+								idx.checked_sub(1).map(|idx| MAX_SESSIONS[idx] * 60.0 * 60.0);
+							if seconds != env_max_session.config.general.read().max_session {
+								env_max_session.config.set_max_session(seconds);
+								que.lock.set(Vec::new()); // invalidate the current timeout
+								create_lock_timeout();
+							}
+						},
+					),
+				)
+					.style(|s| s.gap(5, 0).items_center().margin_top(5)),
 			)
 				.style(|s| s.flex_col()),
 			"Database location".style(|s| s.margin_top(20)),
@@ -394,6 +498,75 @@ pub fn database_view() -> impl IntoView {
 					.style(|s| s.width(200).gap(5, 0)),
 			)
 				.style(|s| s.flex_col().margin_top(20).gap(0, 5)),
+			"Encryption".style(|s| s.margin_top(20)),
+			(
+				select(
+					cipher,
+					CIPHERS
+						.iter()
+						.enumerate()
+						.map(|(idx, cipher)| (idx, cipher.to_string()))
+						.collect(),
+					move |_| cipher_success.set(false),
+				),
+				password_field(cipher_password, "Password").on_event_cont(
+					EventListener::KeyDown,
+					move |event| {
+						let key = match event {
+							Event::KeyDown(k) => k.key.physical_key,
+							_ => PhysicalKey::Code(KeyCode::F35),
+						};
+
+						if key == PhysicalKey::Code(KeyCode::Enter) {
+							change_cipher(
+								cipher,
+								cipher_password,
+								cipher_success,
+								env_cipher_enter.clone(),
+							);
+						}
+					},
+				),
+				(
+					container(button("Convert vault").on_click_cont(move |_| {
+						change_cipher(
+							cipher,
+							cipher_password,
+							cipher_success,
+							env_cipher_click.clone(),
+						);
+					})),
+					"Vault converted".style(move |s| {
+						s.color(C_SUCCESS)
+							.display(Display::None)
+							.apply_if(cipher_success.get(), |s| s.display(Display::Flex))
+					}),
+				)
+					.style(|s| s.items_center().gap(5, 0)),
+				(
+					container(button("Verify vault integrity").on_click_cont(
+						move |_| {
+							integrity_success.set(false);
+							match env_verify.db.verify_integrity() {
+								Ok(()) => {
+									toast_signals.kill_all_toasts();
+									integrity_success.set(true);
+								},
+								Err(err) => {
+									toast_signals.add(err.to_string());
+								},
+							}
+						},
+					)),
+					"No problems found".style(move |s| {
+						s.color(C_SUCCESS)
+							.display(Display::None)
+							.apply_if(integrity_success.get(), |s| s.display(Display::Flex))
+					}),
+				)
+					.style(|s| s.items_center().gap(5, 0).margin_top(10)),
+			)
+				.style(|s| s.flex_col().width(200).margin_top(20).gap(0, 5)),
 			"Backup data".style(|s| s.margin_top(20)),
 			container(
 				(
@@ -418,6 +591,64 @@ pub fn database_view() -> impl IntoView {
 					}),
 			)
 			.style(|s| s.margin_top(20)),
+			"Keep backups".style(|s| s.margin_top(20)),
+			container(select(
+				backup_count,
+				vec![
+					(0, "None"),
+					(1, "1"),
+					(3, "3"),
+					(5, "5"),
+					(10, "10"),
+					(20, "20"),
+				],
+				move |count| {
+					if count != env_backup_count.config.general.read().backup_count {
+						env_backup_count.config.set_backup_count(count);
+						env_backup_count.db.set_backup_count(count);
+					}
+				},
+			))
+			.style(|s| s.margin_top(20).width(200)),
+			"Restore backup".style(|s| s.margin_top(20)),
+			(
+				"No backups yet".style(move |s| {
+					s.color(C_MAIN_TEXT_INACTIVE)
+						.display(Display::None)
+						.apply_if(backups.get().is_empty(), |s| s.display(Display::Flex))
+				}),
+				dyn_stack(
+					move || backups.get(),
+					move |(path, _)| path.clone(),
+					move |(path, timestamp)| {
+						let env_restore = env_restore.clone();
+						let datetime_utc: DateTime<Utc> =
+							DateTime::from_timestamp(timestamp as i64, 0).unwrap_or_default();
+						let datetime_local: DateTime<Local> =
+							datetime_utc.with_timezone(&Local);
+
+						(
+							label(move || datetime_local.format("%v %T").to_string())
+								.style(|s| s.flex_grow(1.0)),
+							button("Restore").style(|s| s.height(25)).on_click_cont(
+								move |_| match env_restore.db.restore_backup(&path) {
+									Ok(()) => {
+										// the restored vault needs to be unlocked again
+										lock_app();
+									},
+									Err(err) => {
+										backups.set(env_restore.db.get_backups());
+										toast_signals.add(err.to_string());
+									},
+								},
+							),
+						)
+							.style(|s| s.width(200).items_center().gap(5, 0))
+					},
+				)
+				.style(|s| s.flex_col().gap(0, 5)),
+			)
+				.style(|s| s.flex_col().margin_top(20)),
 			"Importing data".style(|s| s.margin_top(20)),
 			(
 				file_input(
@@ -443,6 +674,7 @@ pub fn database_view() -> impl IntoView {
 							import_window(
 								import_path,
 								import_password,
+								ImportMode::Import,
 								toast_signals,
 								env_import_enter.clone(),
 							);
@@ -453,12 +685,56 @@ pub fn database_view() -> impl IntoView {
 					import_window(
 						import_path,
 						import_password,
+						ImportMode::Import,
 						toast_signals,
 						env_import_click.clone(),
 					);
 				})),
 			)
 				.style(|s| s.flex_col().margin_top(20).gap(0, 5)),
+			"Merge another vault".style(|s| s.margin_top(20)),
+			(
+				file_input(
+					merge_path,
+					String::from("Select vault to merge..."),
+					FileDialogOptions::new()
+						.allowed_types(vec![FileSpec {
+							name: "backup",
+							extensions: &["backup", "vault", "toml"],
+						}])
+						.title("Select vault to merge"),
+					move |_| {},
+				)
+				.style(|s| s.width(200)),
+				password_field(merge_password, "Enter password for that vault")
+					.on_event_cont(EventListener::KeyDown, move |event| {
+						let key = match event {
+							Event::KeyDown(k) => k.key.physical_key,
+							_ => PhysicalKey::Code(KeyCode::F35),
+						};
+
+						if key == PhysicalKey::Code(KeyCode::Enter) {
+							import_window(
+								merge_path,
+								merge_password,
+								ImportMode::Merge,
+								toast_signals,
+								env_merge_enter.clone(),
+							);
+						}
+					})
+					.style(|s| s.width(200)),
+				container(button("Merge").on_click_cont(move |_| {
+					import_window(
+						merge_path,
+						merge_password,
+						ImportMode::Merge,
+						toast_signals,
+						env_merge_click.clone(),
+					);
+				})),
+			)
+				.style(|s| s.flex_col().margin_top(20).gap(0, 5)),
 		)
 			.style(styles::settings_line)
 			.style(|s| s.flex_col()),
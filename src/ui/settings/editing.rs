@@ -4,18 +4,21 @@ use floem::{
 	reactive::{create_effect, create_rw_signal, use_context, RwSignal},
 	style::{CursorStyle, Display},
 	views::{
-		container, empty, label, slider::slider, virtual_stack, Decorators,
-		VirtualDirection, VirtualItemSize,
+		container, dyn_container, empty, label, slider::slider, v_stack_from_iter,
+		virtual_stack, Decorators, VirtualDirection, VirtualItemSize,
 	},
 	IntoView, View,
 };
 
 use crate::{
-	config::PresetFields,
+	config::{EntryTemplates, PresetFields, TemplateFields},
 	db::DynFieldKind,
 	env::Environment,
 	ui::{
-		app_view::{PresetFieldSignal, TooltipSignalsSettings},
+		app_view::{
+			EntryTemplateSignal, PresetFieldSignal, ToastSignalsSettings,
+			TooltipSignalsSettings,
+		},
 		colors::*,
 		details::button_slots::empty_button_slot,
 		primitives::{
@@ -176,6 +179,323 @@ fn preset_line(
 		.style(|s| s.gap(5, 0).items_center().padding_bottom(5))
 }
 
+fn get_template(
+	id: usize,
+	entry_templates: RwSignal<EntryTemplates>,
+) -> (String, TemplateFields) {
+	entry_templates
+		.get()
+		.into_iter()
+		.find(|(template_id, _, _)| *template_id == id)
+		.map(|(_, name, fields)| (name, fields))
+		.unwrap_or_default()
+}
+
+fn save_template(
+	id: usize,
+	name: String,
+	fields: TemplateFields,
+	entry_templates: RwSignal<EntryTemplates>,
+	env: Environment,
+) {
+	if !name.is_empty() {
+		let templates = env.config.edit_entry_template(id, name, fields);
+		let _ = env.config.save();
+		entry_templates.set(templates);
+	}
+}
+
+fn template_field_line(
+	id: usize,
+	idx: usize,
+	field: (String, String, DynFieldKind),
+	tooltip_signals: TooltipSignals,
+	entry_templates: RwSignal<EntryTemplates>,
+	env: Environment,
+) -> impl IntoView {
+	let toast_signals = use_context::<ToastSignalsSettings>()
+		.expect("No toast_signals context provider")
+		.inner;
+
+	let (title, value, kind) = field;
+	let title_value = create_rw_signal(title.clone());
+	let field_value = create_rw_signal(value.clone());
+	let kind_value = create_rw_signal(kind.clone());
+	let kind_id = DynFieldKind::all_values()
+		.into_iter()
+		.position(|this_kind| this_kind == kind)
+		.unwrap_or(0);
+	let kind_signal = create_rw_signal(kind_id);
+
+	let delete_icon = include_str!("../icons/delete.svg");
+	let save_icon = include_str!("../icons/save.svg");
+
+	let env_delete = env.clone();
+
+	let save_field = move || {
+		if title_value.get().is_empty() {
+			return;
+		}
+		if let Err(err) = kind_value.get().validate(&field_value.get()) {
+			toast_signals.add(err.to_string());
+			return;
+		}
+
+		let (name, mut fields) = get_template(id, entry_templates);
+		if let Some(field) = fields.get_mut(idx) {
+			*field = (title_value.get(), field_value.get(), kind_value.get());
+		}
+		save_template(id, name, fields, entry_templates, env.clone());
+	};
+	let save_field_enter = save_field.clone();
+
+	let save_on_enter = move |event: &Event| {
+		let key = match event {
+			Event::KeyDown(k) => k.key.physical_key,
+			_ => PhysicalKey::Code(KeyCode::F35),
+		};
+
+		if key == PhysicalKey::Code(KeyCode::Enter) {
+			save_field_enter();
+		}
+	};
+
+	(
+		input_field(title_value)
+			.placeholder("Title of field")
+			.on_event_cont(EventListener::KeyDown, save_on_enter.clone())
+			.style(|s| s.width(100)),
+		input_field(field_value)
+			.placeholder("Default value")
+			.on_event_cont(EventListener::KeyDown, save_on_enter)
+			.style(|s| s.width(110)),
+		select(
+			kind_signal,
+			DynFieldKind::all_values().into_iter().enumerate().collect(),
+			move |id| {
+				let selected =
+					DynFieldKind::all_values().into_iter().nth(id).unwrap_or_default();
+				kind_value.set(selected);
+			},
+		),
+		(
+			icon_button(
+				IconButton {
+					icon: String::from(delete_icon),
+					tooltip: String::from("Remove field from template"),
+					tooltip_signals,
+					..IconButton::default()
+				},
+				move |_| {
+					let (name, mut fields) = get_template(id, entry_templates);
+					if idx < fields.len() {
+						fields.remove(idx);
+					}
+					save_template(id, name, fields, entry_templates, env_delete.clone());
+					tooltip_signals.hide();
+				},
+			)
+			.style(|s| s.margin_right(5)),
+			container(
+				icon_button(
+					IconButton {
+						icon: String::from(save_icon),
+						tooltip: String::from("Save to database"),
+						tooltip_signals,
+						..IconButton::default()
+					},
+					move |_| save_field(),
+				)
+				.style(move |s| {
+					s.display(Display::None).apply_if(
+						title_value.get() != title
+							|| field_value.get() != value
+							|| kind_value.get() != kind,
+						|s| s.display(Display::Flex),
+					)
+				}),
+			)
+			.style(|s| s.width(30)),
+		),
+	)
+		.style(|s| s.gap(5, 0).items_center().padding_bottom(5))
+}
+
+fn entry_templates_view(tooltip_signals: TooltipSignals) -> impl IntoView {
+	let env = use_context::<Environment>().expect("No env context provider");
+	let entry_templates = use_context::<EntryTemplateSignal>()
+		.expect("No entry_templates context provider");
+
+	let template_value = create_rw_signal(0);
+	let name_value = create_rw_signal(String::from(""));
+	let field_title_value = create_rw_signal(String::from(""));
+	let field_kind_value = create_rw_signal(DynFieldKind::default());
+	let field_kind_signal = create_rw_signal(0);
+
+	let add_icon = include_str!("../icons/add.svg");
+	let delete_icon = include_str!("../icons/delete.svg");
+	let save_icon = include_str!("../icons/save.svg");
+
+	let env_fields = env.clone();
+	let env_name = env.clone();
+	let env_add = env.clone();
+	let env_delete = env.clone();
+	let env_new_field = env.clone();
+
+	create_effect(move |_| {
+		name_value.set(get_template(template_value.get(), entry_templates).0);
+	});
+
+	let add_field = move || {
+		if !field_title_value.get().is_empty() {
+			let id = template_value.get();
+			let (name, mut fields) = get_template(id, entry_templates);
+			fields.push((
+				field_title_value.get(),
+				String::new(),
+				field_kind_value.get(),
+			));
+			save_template(id, name, fields, entry_templates, env_new_field.clone());
+			field_title_value.set(String::from(""));
+		}
+	};
+	let add_field_enter = add_field.clone();
+
+	(
+		(
+			dyn_container(
+				move || entry_templates.get(),
+				move |templates| {
+					if !templates.iter().any(|(id, _, _)| *id == template_value.get()) {
+						template_value.set(0);
+					}
+
+					select(
+						template_value,
+						templates.iter().map(|(id, name, _)| (*id, name.clone())).collect(),
+						|_| {},
+					)
+					.into_any()
+				},
+			),
+			input_field(name_value)
+				.placeholder("Name of template")
+				.on_event_cont(EventListener::KeyDown, move |event| {
+					let key = match event {
+						Event::KeyDown(k) => k.key.physical_key,
+						_ => PhysicalKey::Code(KeyCode::F35),
+					};
+
+					if key == PhysicalKey::Code(KeyCode::Enter) {
+						let id = template_value.get();
+						let fields = get_template(id, entry_templates).1;
+						save_template(
+							id,
+							name_value.get(),
+							fields,
+							entry_templates,
+							env_name.clone(),
+						);
+					}
+				})
+				.style(|s| s.width(120)),
+			icon_button(
+				IconButton {
+					icon: String::from(delete_icon),
+					tooltip: String::from("Delete template"),
+					tooltip_signals,
+					..IconButton::default()
+				},
+				move |_| {
+					let templates =
+						env_delete.config.delete_entry_template(template_value.get());
+					let _ = env_delete.config.save();
+					entry_templates.set(templates);
+					tooltip_signals.hide();
+				},
+			)
+			// an empty entry is always there to fall back on
+			.style(move |s| {
+				s.apply_if(template_value.get() == 0, |s| s.display(Display::None))
+			}),
+			icon_button(
+				IconButton {
+					icon: String::from(add_icon),
+					tooltip: String::from("Add a new template"),
+					tooltip_signals,
+					..IconButton::default()
+				},
+				move |_| {
+					let templates =
+						env_add.config.add_entry_template(String::from("New template"));
+					let _ = env_add.config.save();
+					let new_id = templates.last().map(|item| item.0).unwrap_or(0);
+					entry_templates.set(templates);
+					template_value.set(new_id);
+				},
+			),
+		)
+			.style(|s| s.gap(5, 0).items_center().margin_top(20)),
+		dyn_container(
+			move || (template_value.get(), entry_templates.get()),
+			move |(id, _)| {
+				let env = env_fields.clone();
+				v_stack_from_iter(
+					get_template(id, entry_templates).1.into_iter().enumerate().map(
+						move |(idx, field)| {
+							template_field_line(
+								id,
+								idx,
+								field,
+								tooltip_signals,
+								entry_templates,
+								env.clone(),
+							)
+						},
+					),
+				)
+				.into_any()
+			},
+		)
+		.style(|s| s.margin_top(15)),
+		(
+			input_field(field_title_value)
+				.placeholder("Title of new field")
+				.on_event_cont(EventListener::KeyDown, move |event| {
+					let key = match event {
+						Event::KeyDown(k) => k.key.physical_key,
+						_ => PhysicalKey::Code(KeyCode::F35),
+					};
+
+					if key == PhysicalKey::Code(KeyCode::Enter) {
+						add_field_enter();
+					}
+				})
+				.style(|s| s.width(100)),
+			select(
+				field_kind_signal,
+				DynFieldKind::all_values().into_iter().enumerate().collect(),
+				move |id| {
+					let selected =
+						DynFieldKind::all_values().into_iter().nth(id).unwrap_or_default();
+					field_kind_value.set(selected);
+				},
+			),
+			icon_button(
+				IconButton {
+					icon: String::from(save_icon),
+					tooltip: String::from("Add field to template"),
+					tooltip_signals,
+					..IconButton::default()
+				},
+				move |_| add_field(),
+			),
+		)
+			.style(|s| s.gap(5, 0).items_center()),
+	)
+		.style(|s| s.flex_col())
+}
+
 fn convert_pct_2_letter_count(pct: f32) -> usize {
 	(((MAX / 100.0) * pct) + MIN).round() as usize
 }
@@ -404,6 +724,8 @@ pub fn editing_view() -> impl IntoView {
 				.style(|s| s.margin_top(10)),
 			)
 				.style(|s| s.flex_col()),
+			"Entry templates",
+			entry_templates_view(tooltip_signals),
 		)
 			.style(|s| s.flex_col().margin_bottom(60).min_width(440))
 			.style(styles::settings_line),
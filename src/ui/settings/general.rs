@@ -2,6 +2,8 @@ use zeroize::Zeroize;
 
 use floem::{
 	event::{Event, EventListener},
+	file::FileDialogOptions,
+	file_action::save_as,
 	keyboard::{KeyCode, PhysicalKey},
 	reactive::{create_rw_signal, use_context, RwSignal},
 	style::{CursorStyle, Display},
@@ -10,13 +12,18 @@ use floem::{
 };
 
 use crate::{
+	db::KeySlotKind,
+	encryption::{create_key_file, read_key_file, KdfAlgorithm, KdfParams},
 	env::Environment,
 	ui::{
 		app_view::{ToastSignalsSettings, TooltipSignalsSettings},
 		colors::*,
 		primitives::{
 			button::{button, icon_button, IconButton},
+			file_input::file_input,
+			input_field::input_field,
 			password_field::password_field,
+			select::select,
 			styles,
 		},
 	},
@@ -59,6 +66,143 @@ fn change_password(
 	}
 }
 
+const KDF_ALGORITHMS: [KdfAlgorithm; 3] = [
+	KdfAlgorithm::Argon2id,
+	KdfAlgorithm::Argon2i,
+	KdfAlgorithm::Argon2d,
+];
+
+#[derive(Clone, Copy)]
+struct KdfSignals {
+	algorithm: RwSignal<usize>,
+	version: RwSignal<usize>,
+	m_cost: RwSignal<String>,
+	t_cost: RwSignal<String>,
+	p_cost: RwSignal<String>,
+	password: RwSignal<String>,
+	success: RwSignal<bool>,
+}
+
+fn change_kdf(kdf_signals: KdfSignals) {
+	let toast_signals = use_context::<ToastSignalsSettings>()
+		.expect("No toast_signals context provider")
+		.inner;
+	let env = use_context::<Environment>().expect("No env context provider");
+
+	kdf_signals.success.set(false);
+	let costs = (
+		kdf_signals.m_cost.get().trim().parse::<u32>(),
+		kdf_signals.t_cost.get().trim().parse::<u32>(),
+		kdf_signals.p_cost.get().trim().parse::<u32>(),
+	);
+
+	if let (Ok(m_cost), Ok(t_cost), Ok(p_cost)) = costs {
+		let kdf = KdfParams {
+			algorithm: KDF_ALGORITHMS[kdf_signals.algorithm.get()],
+			version: kdf_signals.version.get() as u32,
+			m_cost,
+			t_cost,
+			p_cost,
+		};
+
+		match env.db.change_kdf(kdf_signals.password.get(), kdf) {
+			Err(e) => {
+				toast_signals.add(e.to_string());
+			},
+			Ok(()) => {
+				kdf_signals.password.update(|pass| pass.zeroize());
+				toast_signals.kill_all_toasts();
+				kdf_signals.success.set(true);
+			},
+		}
+	} else {
+		toast_signals.add(String::from(
+			"Memory, iterations and parallelism have to be whole numbers",
+		));
+	}
+}
+
+#[derive(Clone, Copy)]
+struct KeyFileSignals {
+	path: RwSignal<Vec<String>>,
+	password: RwSignal<String>,
+	enabled: RwSignal<bool>,
+	success: RwSignal<bool>,
+}
+
+fn change_key_file(key_file_signals: KeyFileSignals, remove: bool) {
+	let toast_signals = use_context::<ToastSignalsSettings>()
+		.expect("No toast_signals context provider")
+		.inner;
+	let env = use_context::<Environment>().expect("No env context provider");
+
+	key_file_signals.success.set(false);
+	let key_file = if remove {
+		None
+	} else {
+		match key_file_signals.path.get().first() {
+			Some(path) => match read_key_file(path) {
+				Ok(key_file) => Some(key_file),
+				Err(e) => {
+					toast_signals.add(e.to_string());
+					return;
+				},
+			},
+			None => {
+				toast_signals.add(String::from("Select or create a key file first"));
+				return;
+			},
+		}
+	};
+
+	match env.db.change_key_file(key_file_signals.password.get(), key_file) {
+		Err(e) => {
+			toast_signals.add(e.to_string());
+		},
+		Ok(()) => {
+			key_file_signals.password.update(|pass| pass.zeroize());
+			key_file_signals.path.set(Vec::new());
+			key_file_signals.enabled.set(!remove);
+			toast_signals.kill_all_toasts();
+			key_file_signals.success.set(true);
+		},
+	}
+}
+
+#[derive(Clone, Copy)]
+struct RecoverySignals {
+	password: RwSignal<String>,
+	code: RwSignal<String>,
+	enabled: RwSignal<bool>,
+}
+
+fn change_recovery_code(recovery_signals: RecoverySignals, remove: bool) {
+	let toast_signals = use_context::<ToastSignalsSettings>()
+		.expect("No toast_signals context provider")
+		.inner;
+	let env = use_context::<Environment>().expect("No env context provider");
+
+	recovery_signals.code.update(|code| code.zeroize());
+	let password = recovery_signals.password.get();
+	let result = if remove {
+		env.db.remove_recovery_code(password).map(|_| String::from(""))
+	} else {
+		env.db.create_recovery_code(password)
+	};
+
+	match result {
+		Err(e) => {
+			toast_signals.add(e.to_string());
+		},
+		Ok(code) => {
+			recovery_signals.password.update(|pass| pass.zeroize());
+			recovery_signals.enabled.set(!remove);
+			recovery_signals.code.set(code);
+			toast_signals.kill_all_toasts();
+		},
+	}
+}
+
 fn convert_pct_2_letter_count(pct: f32) -> usize {
 	(((MAX / 100.0) * pct) + MIN).round() as usize
 }
@@ -89,11 +233,14 @@ pub fn general_view() -> impl IntoView {
 	let revert_icon = include_str!("../icons/revert.svg");
 
 	let env_salt = env.clone();
+	let env_clipboard = env.clone();
 
 	let old_password = create_rw_signal(String::from(""));
 	let new_password = create_rw_signal(String::from(""));
 	let new_password_check = create_rw_signal(String::from(""));
 	let success = create_rw_signal(false);
+	let clipboard_timeout =
+		create_rw_signal(env.config.general.read().clipboard_timeout);
 
 	let db_salt_letter_count_pct = convert_letter_count_2_pct(
 		env.db.config_db.read().salt_letter_count as f32,
@@ -101,6 +248,35 @@ pub fn general_view() -> impl IntoView {
 	let salt_letter_count_pct = create_rw_signal(db_salt_letter_count_pct);
 	let salt_letter_count_pct_backup = create_rw_signal(db_salt_letter_count_pct);
 
+	let kdf = env.db.config_db.read().kdf;
+	let kdf_signals = KdfSignals {
+		algorithm: create_rw_signal(
+			KDF_ALGORITHMS
+				.iter()
+				.position(|algorithm| *algorithm == kdf.algorithm)
+				.unwrap_or(0),
+		),
+		version: create_rw_signal(kdf.version as usize),
+		m_cost: create_rw_signal(kdf.m_cost.to_string()),
+		t_cost: create_rw_signal(kdf.t_cost.to_string()),
+		p_cost: create_rw_signal(kdf.p_cost.to_string()),
+		password: create_rw_signal(String::from("")),
+		success: create_rw_signal(false),
+	};
+
+	let key_file_signals = KeyFileSignals {
+		path: create_rw_signal(Vec::new()),
+		password: create_rw_signal(String::from("")),
+		enabled: create_rw_signal(env.db.config_db.read().key_file),
+		success: create_rw_signal(false),
+	};
+
+	let recovery_signals = RecoverySignals {
+		password: create_rw_signal(String::from("")),
+		code: create_rw_signal(String::from("")),
+		enabled: create_rw_signal(env.db.has_key_slot(KeySlotKind::RecoveryCode)),
+	};
+
 	let debug_settings_slot = if std::env::var("DEBUG").is_ok() {
 		let is_encrypted = create_rw_signal(env.db.config_db.read().encrypted);
 
@@ -158,10 +334,33 @@ pub fn general_view() -> impl IntoView {
 		empty().into_any()
 	};
 
+	let clipboard_slot = ((
+		"Clear clipboard after",
+		container(select(
+			clipboard_timeout,
+			vec![
+				(0, "Never"),
+				(10, "10 seconds"),
+				(20, "20 seconds"),
+				(30, "30 seconds"),
+				(60, "1 minute"),
+				(120, "2 minutes"),
+			],
+			move |seconds| {
+				if seconds != env_clipboard.config.general.read().clipboard_timeout {
+					env_clipboard.config.set_clipboard_timeout(seconds);
+				}
+			},
+		))
+		.style(|s| s.margin_bottom(20)),
+	)
+		.style(styles::settings_line),)
+		.style(|s| s.flex_col().width_full());
+
 	let change_password_slot = ((
 		"Change Password",
 		(
-			password_field(old_password, "Old Password")
+			password_field(old_password, "Old Password or Recovery Code")
 				.on_event_cont(EventListener::KeyDown, move |event| {
 					let key = match event {
 						Event::KeyDown(k) => k.key.physical_key,
@@ -294,10 +493,210 @@ pub fn general_view() -> impl IntoView {
 				.style(|s| s.items_center().gap(5, 0)),
 		)
 			.style(|s| s.flex_col()),
+		"Key derivation".style(|s| s.margin_top(20)),
+		(
+			(
+				select(
+					kdf_signals.algorithm,
+					KDF_ALGORITHMS
+						.iter()
+						.enumerate()
+						.map(|(idx, algorithm)| (idx, algorithm.to_string()))
+						.collect(),
+					move |_| {},
+				),
+				select(
+					kdf_signals.version,
+					vec![(0x13, "Version 19"), (0x10, "Version 16")],
+					move |_| {},
+				),
+			)
+				.style(|s| s.width(250).gap(5, 0)),
+			(
+				"Memory (KiB)".style(|s| s.width(95)),
+				input_field(kdf_signals.m_cost).style(|s| s.width(150)),
+			)
+				.style(|s| s.items_center()),
+			(
+				"Iterations".style(|s| s.width(95)),
+				input_field(kdf_signals.t_cost).style(|s| s.width(150)),
+			)
+				.style(|s| s.items_center()),
+			(
+				"Parallelism".style(|s| s.width(95)),
+				input_field(kdf_signals.p_cost).style(|s| s.width(150)),
+			)
+				.style(|s| s.items_center()),
+			password_field(kdf_signals.password, "Password")
+				.on_event_cont(EventListener::KeyDown, move |event| {
+					let key = match event {
+						Event::KeyDown(k) => k.key.physical_key,
+						_ => PhysicalKey::Code(KeyCode::F35),
+					};
+					if key == PhysicalKey::Code(KeyCode::Enter) {
+						change_kdf(kdf_signals);
+					}
+				})
+				.style(|s| s.width(250)),
+		)
+			.style(|s| s.flex_col().gap(0, 5).margin_top(20)),
+		empty(),
+		(
+			container("Vault re-encrypted successfully".style(move |s| {
+				s.color(C_SUCCESS)
+					.display(Display::None)
+					.apply_if(kdf_signals.success.get(), |s| s.display(Display::Flex))
+			}))
+			.style(|s| s.height(17)),
+			container(
+				button("Re-encrypt vault")
+					.on_click_cont(move |_| change_kdf(kdf_signals)),
+			),
+		)
+			.style(|s| s.flex_col().margin_bottom(20)),
+	)
+		.style(styles::settings_line),)
+		.style(|s| s.flex_col().width_full());
+
+	let unlock_slot = ((
+		"Key file",
+		(
+			label(move || {
+				if key_file_signals.enabled.get() {
+					"This vault needs a key file to unlock"
+				} else {
+					"This vault doesn't use a key file"
+				}
+			})
+			.style(|s| s.color(C_MAIN_TEXT_INACTIVE)),
+			(
+				file_input(
+					key_file_signals.path,
+					String::from("Select key file..."),
+					FileDialogOptions::new().title("Select key file"),
+					move |_| {},
+				)
+				.style(|s| s.width(150)),
+				container(button("Create").on_click_cont(move |_| {
+					let toast_signals = use_context::<ToastSignalsSettings>()
+						.expect("No toast_signals context provider")
+						.inner;
+					save_as(
+						FileDialogOptions::new()
+							.default_name("vault.key")
+							.title("Save new key file"),
+						move |file_info| {
+							if let Some(file) = file_info {
+								let path = file.path[0].to_string_lossy().to_string();
+								match create_key_file(&path) {
+									Ok(_) => key_file_signals.path.set(vec![path]),
+									Err(e) => toast_signals.add(e.to_string()),
+								}
+							}
+						},
+					);
+				})),
+			)
+				.style(|s| s.width(250).gap(5, 0).items_center()),
+			password_field(key_file_signals.password, "Password")
+				.on_event_cont(EventListener::KeyDown, move |event| {
+					let key = match event {
+						Event::KeyDown(k) => k.key.physical_key,
+						_ => PhysicalKey::Code(KeyCode::F35),
+					};
+					if key == PhysicalKey::Code(KeyCode::Enter) {
+						change_key_file(key_file_signals, false);
+					}
+				})
+				.style(|s| s.width(250)),
+		)
+			.style(|s| s.flex_col().gap(0, 5)),
+		empty(),
+		(
+			container("Key file updated successfully".style(move |s| {
+				s.color(C_SUCCESS)
+					.display(Display::None)
+					.apply_if(key_file_signals.success.get(), |s| {
+						s.display(Display::Flex)
+					})
+			}))
+			.style(|s| s.height(17)),
+			(
+				container(
+					button("Use key file")
+						.on_click_cont(move |_| change_key_file(key_file_signals, false)),
+				),
+				container(
+					button("Remove key file")
+						.on_click_cont(move |_| change_key_file(key_file_signals, true)),
+				)
+				.style(move |s| {
+					s.apply_if(!key_file_signals.enabled.get(), |s| {
+						s.display(Display::None)
+					})
+				}),
+			)
+				.style(|s| s.gap(5, 0)),
+		)
+			.style(|s| s.flex_col().margin_bottom(20)),
+		"Recovery code",
+		(
+			label(move || {
+				if recovery_signals.enabled.get() {
+					"The recovery code unlocks this vault if you forget your password"
+				} else {
+					"This vault has no recovery code"
+				}
+			})
+			.style(|s| s.color(C_MAIN_TEXT_INACTIVE)),
+			password_field(recovery_signals.password, "Password")
+				.on_event_cont(EventListener::KeyDown, move |event| {
+					let key = match event {
+						Event::KeyDown(k) => k.key.physical_key,
+						_ => PhysicalKey::Code(KeyCode::F35),
+					};
+					if key == PhysicalKey::Code(KeyCode::Enter) {
+						change_recovery_code(recovery_signals, false);
+					}
+				})
+				.style(|s| s.width(250)),
+		)
+			.style(|s| s.flex_col().gap(0, 5)),
+		empty(),
+		(
+			(
+				"Write this code down, it won't be shown again",
+				label(move || recovery_signals.code.get())
+					.style(|s| s.font_size(15.0).color(C_SUCCESS)),
+			)
+				.style(move |s| {
+					s.flex_col()
+						.margin_bottom(5)
+						.display(Display::None)
+						.apply_if(!recovery_signals.code.get().is_empty(), |s| {
+							s.display(Display::Flex)
+						})
+				}),
+			(
+				container(button("Create recovery code").on_click_cont(move |_| {
+					change_recovery_code(recovery_signals, false)
+				})),
+				container(button("Remove recovery code").on_click_cont(move |_| {
+					change_recovery_code(recovery_signals, true)
+				}))
+				.style(move |s| {
+					s.apply_if(!recovery_signals.enabled.get(), |s| {
+						s.display(Display::None)
+					})
+				}),
+			)
+				.style(|s| s.gap(5, 0)),
+		)
+			.style(|s| s.flex_col().margin_bottom(20)),
 	)
 		.style(styles::settings_line),)
 		.style(|s| s.flex_col().width_full());
 
-	(change_password_slot, debug_settings_slot)
+	(clipboard_slot, change_password_slot, unlock_slot, debug_settings_slot)
 		.style(|s| s.flex_col().margin_bottom(15))
 }
@@ -0,0 +1,167 @@
+use uuid::Uuid;
+
+use floem::{
+	event::{Event, EventListener},
+	keyboard::{KeyCode, PhysicalKey},
+	reactive::{create_rw_signal, use_context, RwSignal},
+	style::CursorStyle,
+	views::{dyn_container, label, Decorators},
+	IntoView, View,
+};
+
+use crate::{
+	env::Environment,
+	ui::{
+		app_view::SidebarList, colors::*, primitives::input_field::input_field,
+	},
+};
+
+pub const GROUP_INDENT: f64 = 12.0;
+
+pub struct SidebarGroup {
+	pub id: Uuid,
+	pub title: String,
+	pub idx: usize,
+	pub depth: usize,
+	pub is_collapsed: bool,
+	pub sidebar_width: RwSignal<f64>,
+	pub collapsed_groups: RwSignal<Vec<Uuid>>,
+	pub search_group: RwSignal<Option<Uuid>>,
+	pub dragged_entry: RwSignal<Option<Uuid>>,
+}
+
+pub fn sidebar_group(params: SidebarGroup, env: Environment) -> impl IntoView {
+	let SidebarGroup {
+		id,
+		title,
+		idx,
+		depth,
+		is_collapsed,
+		sidebar_width,
+		collapsed_groups,
+		search_group,
+		dragged_entry,
+	} = params;
+
+	let list_sidebar_signal = use_context::<SidebarList>()
+		.expect("No list_sidebar_signal context provider");
+
+	let is_renaming = create_rw_signal(false);
+	let rename_value = create_rw_signal(title.clone());
+	let is_drop_target = create_rw_signal(false);
+
+	let env_drop = env.clone();
+
+	(
+		label(move || if is_collapsed { "▸" } else { "▾" })
+			.on_click_stop(move |_| {
+				collapsed_groups.update(|groups| {
+					if is_collapsed {
+						groups.retain(|group| *group != id);
+					} else {
+						groups.push(id);
+					}
+				});
+			})
+			.style(|s| s.width(12).cursor(CursorStyle::Pointer)),
+		dyn_container(
+			move || is_renaming.get(),
+			move |renaming| {
+				if renaming {
+					let env = env.clone();
+					let title = title.clone();
+					let rename_input = input_field(rename_value);
+					let rename_input_id = rename_input.id();
+					rename_input_id.request_focus();
+
+					rename_input
+						.on_event_cont(EventListener::KeyDown, move |event| {
+							let key = match event {
+								Event::KeyDown(k) => k.key.physical_key,
+								_ => PhysicalKey::Code(KeyCode::F35),
+							};
+
+							if key == PhysicalKey::Code(KeyCode::Escape) {
+								rename_value.set(title.clone());
+								is_renaming.set(false);
+							}
+
+							if key == PhysicalKey::Code(KeyCode::Enter)
+								&& !rename_value.get().is_empty()
+							{
+								env.db.rename_group(&id, rename_value.get());
+								let _ = env.db.save();
+								is_renaming.set(false);
+								list_sidebar_signal.update(|_| {});
+							}
+						})
+						.on_event_cont(EventListener::FocusLost, move |_| {
+							is_renaming.set(false);
+						})
+						.style(|s| s.flex_grow(1.0).height(19).font_size(12.0))
+						.into_any()
+				} else {
+					let title = title.clone();
+					label(move || title.clone())
+						// a selected group is what the search looks through
+						.on_click_stop(move |_| {
+							if search_group.get() == Some(id) {
+								search_group.set(None);
+							} else {
+								search_group.set(Some(id));
+							}
+						})
+						.on_event_cont(EventListener::DoubleClick, move |_| {
+							is_renaming.set(true);
+						})
+						.style(|s| {
+							s.flex_grow(1.0)
+								.text_ellipsis()
+								.font_size(12.0)
+								.color(C_SIDE_TEXT)
+								.cursor(CursorStyle::Pointer)
+						})
+						.into_any()
+				}
+			},
+		)
+		.style(|s| s.flex_grow(1.0).min_width(0)),
+	)
+		.on_event_cont(EventListener::DragEnter, move |_| {
+			if dragged_entry.get().is_some() {
+				is_drop_target.set(true);
+			}
+		})
+		.on_event_cont(EventListener::DragLeave, move |_| {
+			is_drop_target.set(false);
+		})
+		.on_event_cont(EventListener::Drop, move |_| {
+			is_drop_target.set(false);
+			if let Some(entry) = dragged_entry.get() {
+				env_drop.db.move_entry(&entry, Some(id));
+				let _ = env_drop.db.save();
+				collapsed_groups.update(|groups| groups.retain(|group| *group != id));
+				list_sidebar_signal.update(|_| {});
+			}
+			dragged_entry.set(None);
+		})
+		.style(move |s| {
+			s.items_center()
+				.height(21)
+				.width(sidebar_width.get())
+				.padding_left(10.0 + GROUP_INDENT * depth as f64)
+				.padding_right(10.0)
+				.border_bottom(1.0)
+				.border_color(C_SIDE_BG_BORDER)
+				.color(C_SIDE_TEXT)
+				.background(if let 0 = idx % 2 {
+					C_SIDE_BG
+				} else {
+					C_SIDE_BG_SELECTED.with_alpha_factor(0.2)
+				})
+				.apply_if(search_group.get() == Some(id), |s| {
+					s.background(C_SIDE_BG_SELECTED.with_alpha_factor(0.6))
+				})
+				.apply_if(is_drop_target.get(), |s| s.border_color(C_FOCUS))
+		})
+}
@@ -0,0 +1,213 @@
+use chrono::{DateTime, Local, Utc};
+use uuid::Uuid;
+
+use floem::{
+	event::{Event, EventListener},
+	reactive::{create_rw_signal, provide_context, use_context, RwSignal},
+	style::{Display, Position},
+	views::{
+		container, label, scroll, virtual_stack, Decorators, VirtualDirection,
+		VirtualItemSize,
+	},
+	IntoView, View,
+};
+
+use crate::{
+	env::Environment,
+	ui::{
+		app_view::SidebarList,
+		colors::*,
+		primitives::{
+			button::{icon_button, IconButton},
+			que::Que,
+			styles,
+			tooltip::{tooltip_view, TooltipSignals},
+		},
+	},
+};
+
+const TOP_HEIGHT: f32 = 50.0;
+const TRASH_LINE_HEIGHT: f64 = 30.0;
+
+fn trash_line(
+	item: (Uuid, String, u64),
+	trash_list: RwSignal<im::Vector<(Uuid, String, u64)>>,
+	env: Environment,
+) -> impl IntoView {
+	let tooltip_signals = use_context::<TooltipSignals>()
+		.expect("No tooltip_signals context provider");
+	let list_sidebar_signal = use_context::<SidebarList>()
+		.expect("No list_sidebar_signal context provider");
+
+	let (id, title, deleted_at) = item;
+	let full_title = title.clone();
+	let does_overflow = create_rw_signal(false);
+
+	let revert_icon = include_str!("./icons/revert.svg");
+	let delete_icon = include_str!("./icons/delete.svg");
+
+	let env_restore = env.clone();
+
+	let datetime_utc: DateTime<Utc> =
+		DateTime::from_timestamp(deleted_at as i64, 0).unwrap_or_default();
+	let datetime_local: DateTime<Local> = datetime_utc.with_timezone(&Local);
+
+	(
+		label(move || title.clone())
+			.on_text_overflow(move |is_overflown| {
+				does_overflow.set(is_overflown);
+			})
+			.on_event_cont(EventListener::PointerEnter, move |_| {
+				if does_overflow.get() {
+					tooltip_signals.show(full_title.clone());
+				}
+			})
+			.on_event_cont(EventListener::PointerLeave, move |_| {
+				tooltip_signals.hide();
+			})
+			.style(|s| s.text_ellipsis().flex_grow(1.0).flex_basis(0).min_width(0)),
+		datetime_local
+			.format("%v")
+			.to_string()
+			.style(|s| s.color(C_SIDE_TEXT_INACTIVE).font_size(9.0).min_width(60))
+			.on_event_cont(EventListener::PointerEnter, move |_| {
+				tooltip_signals
+					.show(format!("Deleted on {}", datetime_local.to_rfc2822()));
+			})
+			.on_event_cont(EventListener::PointerLeave, move |_| {
+				tooltip_signals.hide();
+			}),
+		icon_button(
+			IconButton {
+				icon: String::from(revert_icon),
+				tooltip: String::from("Restore entry"),
+				tooltip_signals,
+				..IconButton::default()
+			},
+			move |_| {
+				tooltip_signals.hide();
+				env_restore.db.restore_entry(&id);
+				let _ = env_restore.db.save();
+				trash_list.set(env_restore.db.get_trash_list());
+				list_sidebar_signal.set(env_restore.db.get_sidebar_list());
+			},
+		),
+		icon_button(
+			IconButton {
+				icon: String::from(delete_icon),
+				tooltip: String::from("Delete entry forever"),
+				tooltip_signals,
+				..IconButton::default()
+			},
+			move |_| {
+				tooltip_signals.hide();
+				env.db.purge_entry(&id);
+				let _ = env.db.save();
+				trash_list.set(env.db.get_trash_list());
+			},
+		),
+	)
+		.style(|s| {
+			s.height(TRASH_LINE_HEIGHT)
+				.padding_horiz(10)
+				.gap(5, 0)
+				.width_full()
+				.items_center()
+		})
+}
+
+pub fn trash_view(que: Que, env: Environment) -> impl IntoView {
+	let tooltip_signals = TooltipSignals::new(que);
+	provide_context(tooltip_signals);
+
+	let trash_list = create_rw_signal(env.db.get_trash_list());
+
+	let env_empty = env.clone();
+
+	let trash_view = (
+		(
+			"Trash".style(|s| s.font_size(21.0).margin_bottom(3)),
+			container(
+				label(move || format!(" Empty trash ({}) ", trash_list.get().len()))
+					.keyboard_navigatable()
+					.style(styles::button)
+					.on_click_cont(move |_| {
+						env_empty.db.empty_trash();
+						let _ = env_empty.db.save();
+						trash_list.set(env_empty.db.get_trash_list());
+					}),
+			)
+			.style(move |s| {
+				s.width_full()
+					.justify_end()
+					.apply_if(trash_list.get().is_empty(), |s| s.display(Display::None))
+			}),
+		)
+			.style(|s| {
+				s.height(TOP_HEIGHT)
+					.gap(5, 0)
+					.padding(5)
+					.items_center()
+					.justify_center()
+					.border_color(C_TOP_BG_BORDER)
+					.border_bottom(1)
+			}),
+		scroll(
+			(
+				"The trash is empty".style(move |s| {
+					s.padding(10)
+						.color(C_MAIN_TEXT_INACTIVE)
+						.display(Display::None)
+						.apply_if(trash_list.get().is_empty(), |s| s.display(Display::Flex))
+				}),
+				virtual_stack(
+					VirtualDirection::Vertical,
+					VirtualItemSize::Fixed(Box::new(|| TRASH_LINE_HEIGHT)),
+					move || trash_list.get(),
+					move |item| (item.0, item.2),
+					move |item| trash_line(item, trash_list, env.clone()),
+				)
+				.style(|s| s.flex_col().width_full().margin_bottom(10)),
+			)
+				.style(|s| s.flex_col().width_full()),
+		)
+		.style(|s| {
+			s.width_full()
+				.position(Position::Absolute)
+				.inset_top(TOP_HEIGHT)
+				.inset_bottom(0.0)
+				.min_width(0)
+				.flex_grow(1.0)
+				.class(scroll::Handle, styles::scrollbar_styles)
+		}),
+		tooltip_view(tooltip_signals),
+	)
+		.style(|s| s.flex_col().flex().width_full().height_full())
+		.on_event_cont(EventListener::PointerMove, move |event| {
+			let pos = match event {
+				Event::PointerMove(p) => p.pos,
+				_ => (0.0, 0.0).into(),
+			};
+			tooltip_signals.mouse_pos.set((pos.x, pos.y));
+		})
+		.on_resize(move |event| {
+			tooltip_signals.window_size.set((event.x1, event.y1));
+		});
+
+	match std::env::var("DEBUG") {
+		Ok(_) => {
+			// for debugging the layout
+			let id = trash_view.id();
+			trash_view.on_event_stop(EventListener::KeyUp, move |e| {
+				if let floem::event::Event::KeyUp(e) = e {
+					if e.key.logical_key
+						== floem::keyboard::Key::Named(floem::keyboard::NamedKey::F11)
+					{
+						id.inspect();
+					}
+				}
+			})
+		},
+		Err(_) => trash_view,
+	}
+}
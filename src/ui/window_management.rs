@@ -1,21 +1,23 @@
 use core::cell::RefCell;
+use uuid::Uuid;
 
 use floem::{
 	event::{Event, EventListener},
 	keyboard::{KeyCode, Modifiers, PhysicalKey},
 	kurbo::Size,
+	reactive::use_context,
 	views::Decorators,
 	window::{close_window, new_window, WindowConfig, WindowId},
 	IntoView,
 };
 
-use crate::db::DbFields;
+use crate::{db::DbFields, ActivitySignals};
 
 thread_local! {
 	pub(crate) static OPEN_WINDOWS: RefCell<Vec<(String, WindowId)>> = const { RefCell::new(Vec::new()) };
 }
 
-pub fn make_field_path(id: usize, field: &DbFields) -> String {
+pub fn make_field_path(id: Uuid, field: &DbFields) -> String {
 	format!("{}-{}", id, field)
 }
 
@@ -56,6 +58,9 @@ pub fn opening_window<V: IntoView + 'static>(
 	size: Size,
 	on_close: impl Fn() + 'static,
 ) {
+	let activity =
+		use_context::<ActivitySignals>().expect("No activity context provider");
+
 	OPEN_WINDOWS.with(|all_windows| {
 		if !all_windows.borrow().iter().any(|item| item.0 == spec.id) {
 			new_window(
@@ -67,7 +72,17 @@ pub fn opening_window<V: IntoView + 'static>(
 						.on_event_cont(EventListener::WindowClosed, move |_| {
 							closing_window(spec.id.clone(), || on_close());
 						})
+						.on_event_cont(EventListener::PointerMove, move |_| {
+							activity.record()
+						})
+						.on_event_cont(EventListener::PointerDown, move |_| {
+							activity.record()
+						})
+						.on_event_cont(EventListener::PointerWheel, move |_| {
+							activity.record()
+						})
 						.on_event_cont(EventListener::KeyDown, move |event| {
+							activity.record();
 							let key = match event {
 								Event::KeyDown(k) => (k.key.physical_key, k.modifiers),
 								_ => (PhysicalKey::Code(KeyCode::F35), Modifiers::default()),